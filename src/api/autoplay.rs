@@ -0,0 +1,34 @@
+use librespot_protocol::autoplay_context_request::AutoplayContextRequest;
+
+use super::{ApiError, SpotifyContext};
+
+/// Autoplay, which picks what plays once a playlist or album ended. It's resolved through
+/// the librespot session, so unlike recommendations it's there for every app.
+impl SpotifyContext {
+    /// Uris of tracks Spotify would play after `context_uri`, a track, artist or playlist,
+    /// going on from `recent_tracks`, the latest last.
+    pub async fn autoplay_tracks(
+        &self,
+        context_uri: &str,
+        recent_tracks: &[String],
+    ) -> Result<Vec<String>, ApiError> {
+        let request = AutoplayContextRequest {
+            context_uri: Some(context_uri.to_string()),
+            recent_track_uri: recent_tracks.to_vec(),
+            ..Default::default()
+        };
+        let context = self
+            .session
+            .spclient()
+            .get_autoplay_context(&request)
+            .await
+            .map_err(|e| ApiError::from_librespot("autoplay_tracks", e))?;
+        Ok(context
+            .pages
+            .iter()
+            .flat_map(|page| &page.tracks)
+            .map(|track| track.uri().to_string())
+            .filter(|uri| !uri.is_empty())
+            .collect())
+    }
+}
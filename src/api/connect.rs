@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use futures_util::{Stream, StreamExt};
+use librespot_core::dealer::protocol::Message;
+use librespot_protocol::connect::ClusterUpdate;
+use reqwest::Method;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::{ApiError, SpotifyContext};
+
+/// Where the dealer sends the state of all of the user's Connect devices.
+const CLUSTER_URI: &str = "hm://connect-state/v1/cluster";
+
+/// A track in the queue of a Connect device, as it's sent back to change the queue.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProvidedTrack {
+    pub uri: String,
+    /// Tells apart the same track being in the queue more than once.
+    pub uid: String,
+    /// Where it came from, like `queue` for tracks the user added or `context` for the
+    /// playlist or album that's playing.
+    pub provider: String,
+    pub metadata: HashMap<String, String>,
+}
+
+impl From<&librespot_protocol::player::ProvidedTrack> for ProvidedTrack {
+    fn from(track: &librespot_protocol::player::ProvidedTrack) -> Self {
+        Self {
+            uri: track.uri.clone(),
+            uid: track.uid.clone(),
+            provider: track.provider.clone(),
+            metadata: track.metadata.clone(),
+        }
+    }
+}
+
+/// Spotify Connect, which knows more about what's playing than the Web API, like which of the
+/// upcoming tracks the user queued. It's reached through the librespot session.
+impl SpotifyContext {
+    /// Every change of the state of the user's Connect devices, starting with the next one.
+    pub fn connect_cluster(
+        &self,
+    ) -> Result<impl Stream<Item = Result<ClusterUpdate, ApiError>> + Send, ApiError> {
+        let updates = self
+            .session
+            .dealer()
+            .listen_for(CLUSTER_URI)
+            .map_err(|e| ApiError::from_librespot("connect_cluster", e))?;
+        Ok(updates.map(|message| {
+            Message::from_raw::<ClusterUpdate>(message)
+                .map_err(|e| ApiError::from_librespot("connect_cluster", e))
+        }))
+    }
+
+    /// Adds `uri` to the end of what the user queued on the Connect device `device_id`.
+    pub async fn connect_add_to_queue(&self, device_id: &str, uri: &str) -> Result<(), ApiError> {
+        self.connect_command(
+            "connect_add_to_queue",
+            device_id,
+            json!({
+                "endpoint": "add_to_queue",
+                "track": { "uri": uri },
+            }),
+        )
+        .await
+    }
+
+    /// Replaces the queue of the Connect device `device_id`, unless it changed since
+    /// `revision`.
+    pub async fn connect_set_queue(
+        &self,
+        device_id: &str,
+        next_tracks: &[ProvidedTrack],
+        prev_tracks: &[ProvidedTrack],
+        revision: &str,
+    ) -> Result<(), ApiError> {
+        self.connect_command(
+            "connect_set_queue",
+            device_id,
+            json!({
+                "endpoint": "set_queue",
+                "next_tracks": next_tracks,
+                "prev_tracks": prev_tracks,
+                "queue_revision": revision,
+            }),
+        )
+        .await
+    }
+
+    async fn connect_command(
+        &self,
+        endpoint: &'static str,
+        device_id: &str,
+        command: Value,
+    ) -> Result<(), ApiError> {
+        let path = format!(
+            "/connect-state/v1/player/command/from/{}/to/{}",
+            self.session.device_id(),
+            device_id
+        );
+        let body = json!({ "command": command }).to_string();
+        self.session
+            .spclient()
+            .request_as_json(&Method::POST, &path, None, Some(&body))
+            .await
+            .map(|_| ())
+            .map_err(|e| ApiError::from_librespot(endpoint, e))
+    }
+}
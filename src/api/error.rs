@@ -0,0 +1,161 @@
+use std::{error::Error, fmt, sync::Arc, time::Duration};
+
+use librespot_core::error::ErrorKind as LibrespotErrorKind;
+use reqwest::StatusCode;
+use rspotify::{http::HttpError, ClientError};
+
+/// Broad category of an [`ApiError`], enough for the UI to decide what to show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiErrorKind {
+    /// Spotify couldn't be reached at all.
+    Offline,
+    RateLimited,
+    /// No answer before the request's deadline.
+    TimedOut,
+    /// The access token was rejected, even after refreshing it.
+    Unauthorized,
+    /// The token lacks a scope for the endpoint, or the account can't use it.
+    Forbidden,
+    NotFound,
+    /// Spotify had an internal error or is unavailable.
+    Server,
+    /// Dropped before it was sent because nothing needed the result anymore.
+    Cancelled,
+    Other,
+}
+
+impl fmt::Display for ApiErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ApiErrorKind::Offline => "offline",
+            ApiErrorKind::RateLimited => "rate limited",
+            ApiErrorKind::TimedOut => "timed out",
+            ApiErrorKind::Unauthorized => "unauthorized",
+            ApiErrorKind::Forbidden => "forbidden",
+            ApiErrorKind::NotFound => "not found",
+            ApiErrorKind::Server => "server error",
+            ApiErrorKind::Cancelled => "cancelled",
+            ApiErrorKind::Other => "error",
+        })
+    }
+}
+
+/// A failed Web API request.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{endpoint}: {kind}{}", self.status.map(|status| format!(" ({status})")).unwrap_or_default())]
+pub struct ApiError {
+    pub kind: ApiErrorKind,
+    /// Name of the `SpotifyContext` method that failed.
+    pub endpoint: &'static str,
+    pub status: Option<StatusCode>,
+    /// How long Spotify asked us to wait before trying again.
+    pub retry_after: Option<Duration>,
+    #[source]
+    pub source: Option<Arc<dyn Error + Send + Sync>>,
+}
+
+/// Keeps the message of errors that can't be shared between threads.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+struct ErrorMessage(String);
+
+impl ApiError {
+    pub fn new(endpoint: &'static str, kind: ApiErrorKind) -> Self {
+        Self {
+            kind,
+            endpoint,
+            status: None,
+            retry_after: None,
+            source: None,
+        }
+    }
+
+    pub fn from_client_error(endpoint: &'static str, error: ClientError) -> Self {
+        match error {
+            ClientError::Http(http) => match *http {
+                HttpError::StatusCode(response) => {
+                    let status = response.status();
+                    let retry_after = response
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .map(Duration::from_secs);
+                    Self {
+                        status: Some(status),
+                        retry_after,
+                        ..Self::new(endpoint, kind_for_status(status))
+                    }
+                }
+                HttpError::Client(e) => {
+                    let kind = if e.is_connect() || e.is_timeout() || e.is_request() {
+                        ApiErrorKind::Offline
+                    } else {
+                        ApiErrorKind::Other
+                    };
+                    Self {
+                        status: e.status(),
+                        source: Some(Arc::new(e)),
+                        ..Self::new(endpoint, kind)
+                    }
+                }
+            },
+            e => Self {
+                source: Some(Arc::new(ErrorMessage(e.to_string()))),
+                ..Self::new(endpoint, ApiErrorKind::Other)
+            },
+        }
+    }
+
+    /// An error of a request librespot made for us, like a Spotify Connect command.
+    pub fn from_librespot(endpoint: &'static str, error: librespot_core::Error) -> Self {
+        let kind = match error.kind {
+            LibrespotErrorKind::Unavailable => ApiErrorKind::Offline,
+            LibrespotErrorKind::ResourceExhausted => ApiErrorKind::RateLimited,
+            LibrespotErrorKind::DeadlineExceeded => ApiErrorKind::TimedOut,
+            LibrespotErrorKind::Unauthenticated => ApiErrorKind::Unauthorized,
+            LibrespotErrorKind::PermissionDenied => ApiErrorKind::Forbidden,
+            LibrespotErrorKind::NotFound => ApiErrorKind::NotFound,
+            LibrespotErrorKind::Internal => ApiErrorKind::Server,
+            _ => ApiErrorKind::Other,
+        };
+        Self {
+            source: Some(Arc::new(ErrorMessage(error.to_string()))),
+            ..Self::new(endpoint, kind)
+        }
+    }
+
+    /// Short explanation suitable for showing to the user.
+    pub fn user_message(&self) -> String {
+        match self.kind {
+            ApiErrorKind::Offline => "Can't reach Spotify, check your connection.".to_string(),
+            ApiErrorKind::RateLimited => match self.retry_after {
+                Some(wait) => format!(
+                    "Spotify is rate limiting requests, try again in {} seconds.",
+                    wait.as_secs()
+                ),
+                None => "Spotify is rate limiting requests, try again later.".to_string(),
+            },
+            ApiErrorKind::TimedOut => "Spotify took too long to respond, try again.".to_string(),
+            ApiErrorKind::Unauthorized => "Your session expired, log in again.".to_string(),
+            ApiErrorKind::Forbidden => {
+                "Not allowed, your login may be missing a permission.".to_string()
+            }
+            ApiErrorKind::NotFound => "This doesn't exist (anymore).".to_string(),
+            ApiErrorKind::Server => "Spotify is having problems, try again later.".to_string(),
+            ApiErrorKind::Cancelled => "The request was cancelled.".to_string(),
+            ApiErrorKind::Other => format!("Something went wrong ({self})."),
+        }
+    }
+}
+
+fn kind_for_status(status: StatusCode) -> ApiErrorKind {
+    match status {
+        StatusCode::TOO_MANY_REQUESTS => ApiErrorKind::RateLimited,
+        StatusCode::UNAUTHORIZED => ApiErrorKind::Unauthorized,
+        StatusCode::FORBIDDEN => ApiErrorKind::Forbidden,
+        StatusCode::NOT_FOUND => ApiErrorKind::NotFound,
+        status if status.is_server_error() => ApiErrorKind::Server,
+        _ => ApiErrorKind::Other,
+    }
+}
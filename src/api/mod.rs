@@ -0,0 +1,821 @@
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{TimeDelta, Utc};
+use cushy::value::{Destination, Dynamic};
+use futures_util::lock::Mutex;
+use librespot_core::Session;
+use librespot_oauth::OAuthToken;
+use rspotify::http::Query;
+use rspotify::model::{
+    AlbumId, AlbumType, ArtistId, CurrentUserQueue, CursorBasedPage, EpisodeId, FullAlbum,
+    FullArtist, FullEpisode, FullPlaylist, FullTrack, ItemPositions, Market, Offset, Page,
+    PlayContextId, PlayHistory, PlayableId, PlaylistId, PlaylistItem, PrivateUser,
+    RecommendationsAttribute, SavedTrack, SearchMultipleResult, SearchType, Show, ShowId,
+    SimplifiedAlbum, SimplifiedEpisode, SimplifiedPlaylist, SimplifiedTrack, TimeRange, TrackId,
+    UserId,
+};
+use rspotify::prelude::*;
+use rspotify::{AuthCodeSpotify, ClientResult, Config, Token};
+
+pub use connect::ProvidedTrack;
+pub use error::{ApiError, ApiErrorKind};
+pub use paginate::{CursorPaginator, Paginator};
+pub use retry::RetryPolicy;
+pub use scheduler::{
+    with_interests, with_priority, Interest, Priority, RowInterests, Scheduler, WeakInterest,
+};
+pub use search::{SearchItem, SearchResults, SEARCH_TYPES};
+
+use crate::auth::store::CredentialStore;
+use crate::auth::{get_access_token_from_refresh_token, AuthConfig, AuthError};
+use crate::player::DynamicPlayer;
+use scheduler::Dropped;
+
+pub mod autoplay;
+pub mod connect;
+pub mod error;
+pub mod paginate;
+pub mod retry;
+pub mod scheduler;
+pub mod search;
+
+/// Refresh the access token this long before it expires.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+/// How long to wait before retrying a failed refresh while the old token is still valid.
+const TOKEN_REFRESH_RETRY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum AuthState {
+    #[default]
+    LoggedIn,
+    /// The access token expired and couldn't be refreshed, the user has to log in again.
+    ReloginRequired,
+}
+
+pub struct SpotifyContext {
+    session: Session,
+    api: AuthCodeSpotify,
+    token: Mutex<OAuthToken>,
+    auth_config: AuthConfig,
+    store: Arc<dyn CredentialStore>,
+    retry_policy: RetryPolicy,
+    scheduler: Scheduler,
+    pub player: DynamicPlayer,
+    pub auth_state: Dynamic<AuthState>,
+}
+
+pub type SpotifyContextRef = Arc<SpotifyContext>;
+
+impl SpotifyContext {
+    pub fn new(
+        session: Session,
+        token: OAuthToken,
+        auth_config: AuthConfig,
+        store: Arc<dyn CredentialStore>,
+        retry_policy: RetryPolicy,
+        player: DynamicPlayer,
+    ) -> SpotifyContext {
+        let config = Config {
+            token_refreshing: false,
+            ..Default::default()
+        };
+        let api = AuthCodeSpotify::from_token_with_config(
+            librespot_token_to_rspotify(&token),
+            rspotify::Credentials::default(),
+            rspotify::OAuth {
+                proxies: None,
+                redirect_uri: auth_config.redirect_uri.clone(),
+                scopes: auth_config.rspotify_scopes(),
+                state: String::new(),
+            },
+            config,
+        );
+        SpotifyContext {
+            session,
+            api,
+            token: Mutex::new(token),
+            auth_config,
+            store,
+            retry_policy,
+            scheduler: Scheduler::default(),
+            player,
+            auth_state: Default::default(),
+        }
+    }
+
+    /// The access token as last refreshed, see [`SpotifyContext::run_token_refresh`].
+    pub async fn current_token(&self) -> OAuthToken {
+        self.token.lock().await.clone()
+    }
+
+    /// Exchanges the refresh token for a new access token and hands it to the Web API client,
+    /// if `stale` says the current one needs replacing. Returns whether it was replaced.
+    ///
+    /// The token stays locked until the new one is stored, so concurrent refreshes run one
+    /// after another and a refresh token rotated by one isn't reused by the next. `stale` sees
+    /// the token as it is once it's this call's turn, a refresh that ran in the meantime
+    /// usually makes another unnecessary.
+    ///
+    /// A librespot session only takes an access token when it connects, so it gets the new
+    /// one through [`SpotifyContext::current_token`] when it has to connect again, see
+    /// [`crate::profile::ProfileSession::reconnection`].
+    async fn refresh_token_if(
+        &self,
+        stale: impl FnOnce(&OAuthToken) -> bool,
+    ) -> Result<bool, AuthError> {
+        let mut current = self.token.lock().await;
+        if !stale(&current) {
+            return Ok(false);
+        }
+        let refresh_token = current.refresh_token.clone();
+        let auth_config = self.auth_config.clone();
+        let store = self.store.clone();
+        // the oauth2 http client is blocking
+        let token = tokio::task::spawn_blocking(move || {
+            get_access_token_from_refresh_token(&auth_config, &*store, &refresh_token)
+        })
+        .await
+        .expect("token refresh panicked")?;
+        *self.api.token.lock().await.unwrap() = Some(librespot_token_to_rspotify(&token));
+        *current = token;
+        self.auth_state.set(AuthState::LoggedIn);
+        Ok(true)
+    }
+
+    /// Replaces `rejected`, an access token the Web API answered with 401, even if it doesn't
+    /// look expired yet. Returns whether a request should be retried with the current token,
+    /// which is also the case when another refresh replaced it in the meantime.
+    async fn renew_rejected_token(&self, rejected: &str) -> bool {
+        match self
+            .refresh_token_if(|token| token.access_token == rejected)
+            .await
+        {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("Failed to refresh access token: {}", e);
+                self.auth_state.set(AuthState::ReloginRequired);
+                false
+            }
+        }
+    }
+
+    /// Refreshes the access token shortly before it expires, for as long as the context lives.
+    /// Failures are retried until the old token expires or the grant got revoked, after which
+    /// a re-login is required.
+    pub async fn run_token_refresh(&self) {
+        loop {
+            let expires_at = self.token.lock().await.expires_at;
+            let refresh_at = expires_at
+                .checked_sub(TOKEN_REFRESH_MARGIN)
+                .unwrap_or(expires_at);
+            tokio::time::sleep_until(refresh_at.into()).await;
+
+            // skipped if a rejected token got replaced in the meantime
+            while let Err(e) = self
+                .refresh_token_if(|token| token.expires_at == expires_at)
+                .await
+            {
+                eprintln!("Failed to refresh access token: {}", e);
+                let expired = self.token.lock().await.expires_at <= Instant::now();
+                let permanent = matches!(e, AuthError::InvalidGrant | AuthError::MissingScopes(_));
+                if expired || permanent {
+                    self.auth_state.set(AuthState::ReloginRequired);
+                    return;
+                }
+                tokio::time::sleep(TOKEN_REFRESH_RETRY).await;
+            }
+        }
+    }
+
+    /// Execute `api_call` once the [`Scheduler`] lets it through, retrying it according to the
+    /// [`RetryPolicy`]. A rejected token is renewed once. Dropping the returned future, e.g.
+    /// by aborting the task of a widget that went away, cancels any pending retry.
+    async fn api_with_retry<'a, F, T: Future<Output = ClientResult<R>>, R>(
+        &'a self,
+        endpoint: &'static str,
+        api_call: F,
+    ) -> Result<R, ApiError>
+    where
+        F: Fn(&'a AuthCodeSpotify) -> T,
+    {
+        let policy = &self.retry_policy;
+        let deadline = Instant::now() + policy.deadline;
+        let mut attempt = 1;
+        let mut refreshed = false;
+        loop {
+            match tokio::time::timeout_at(deadline.into(), self.scheduler.acquire()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(Dropped)) => {
+                    eprintln!("{}: dropped, no longer needed", endpoint);
+                    return Err(ApiError::new(endpoint, ApiErrorKind::Cancelled));
+                }
+                Err(_) => {
+                    let error = ApiError::new(endpoint, ApiErrorKind::TimedOut);
+                    eprintln!("{}: giving up while waiting for its turn", error);
+                    return Err(error);
+                }
+            }
+            let sent_token = self.token.lock().await.access_token.clone();
+            let error = match tokio::time::timeout_at(deadline.into(), api_call(&self.api)).await {
+                Ok(Ok(v)) => return Ok(v),
+                Ok(Err(e)) => ApiError::from_client_error(endpoint, e),
+                Err(_) => {
+                    let error = ApiError::new(endpoint, ApiErrorKind::TimedOut);
+                    eprintln!("{}: giving up after {} attempts", error, attempt);
+                    return Err(error);
+                }
+            };
+
+            if error.kind == ApiErrorKind::Unauthorized && !refreshed {
+                refreshed = true;
+                if self.renew_rejected_token(&sent_token).await {
+                    eprintln!("{}: renewed the access token, retrying", error);
+                    continue;
+                }
+                return Err(error);
+            }
+            if !policy.is_retryable(&error) {
+                eprintln!("{}: not retrying", error);
+                return Err(error);
+            }
+            if attempt >= policy.max_attempts {
+                eprintln!("{}: giving up after {} attempts", error, attempt);
+                return Err(error);
+            }
+            let delay = error.retry_after.unwrap_or_else(|| policy.backoff(attempt));
+            if Instant::now() + delay > deadline {
+                eprintln!(
+                    "{}: giving up, waiting {:?} would exceed the deadline",
+                    error, delay
+                );
+                return Err(error);
+            }
+            eprintln!(
+                "{}: attempt {} of {} failed, retrying in {:?}",
+                error, attempt, policy.max_attempts, delay
+            );
+            if error.kind == ApiErrorKind::RateLimited {
+                // every other request would be rate limited as well
+                self.scheduler.pause_for(delay);
+            } else {
+                tokio::time::sleep(delay).await;
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Walks an offset-paged endpoint, see [`Paginator`].
+    pub fn paginate<F, Fut, T>(self: &Arc<Self>, fetch: F) -> Paginator<F>
+    where
+        F: Fn(SpotifyContextRef, u32, u32) -> Fut,
+        Fut: Future<Output = Result<Page<T>, ApiError>>,
+    {
+        Paginator::new(self.clone(), fetch)
+    }
+
+    /// Walks a cursor-paged endpoint, see [`CursorPaginator`].
+    pub fn paginate_cursor<F, Fut, T>(self: &Arc<Self>, fetch: F) -> CursorPaginator<F>
+    where
+        F: Fn(SpotifyContextRef, u32, Option<String>) -> Fut,
+        Fut: Future<Output = Result<CursorBasedPage<T>, ApiError>>,
+    {
+        CursorPaginator::new(self.clone(), fetch)
+    }
+
+    pub async fn current_user(&self) -> Result<PrivateUser, ApiError> {
+        self.api_with_retry("current_user", |api| api.current_user())
+            .await
+    }
+
+    pub async fn current_user_playlists(
+        &self,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Page<SimplifiedPlaylist>, ApiError> {
+        self.api_with_retry("current_user_playlists", |api| {
+            api.current_user_playlists_manual(limit, offset)
+        })
+        .await
+    }
+
+    pub async fn playlist(&self, id: PlaylistId<'_>) -> Result<FullPlaylist, ApiError> {
+        self.api_with_retry("playlist", |api| api.playlist(id.as_ref(), None, None))
+            .await
+    }
+
+    pub async fn playlist_items(
+        &self,
+        id: PlaylistId<'_>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Page<PlaylistItem>, ApiError> {
+        self.api_with_retry("playlist_items", |api| {
+            api.playlist_items_manual(id.as_ref(), None, None, limit, offset)
+        })
+        .await
+    }
+
+    /// Creates a playlist owned by `user`, who has to be the current user.
+    pub async fn create_playlist(
+        &self,
+        user: UserId<'_>,
+        name: &str,
+        description: Option<&str>,
+        public: bool,
+    ) -> Result<FullPlaylist, ApiError> {
+        self.api_with_retry("create_playlist", |api| {
+            api.user_playlist_create(user.as_ref(), name, Some(public), None, description)
+        })
+        .await
+    }
+
+    /// Changes the name and description of a playlist, leaving out what's `None`. Unlike
+    /// changes to the items, this doesn't take a snapshot id.
+    pub async fn change_playlist_details(
+        &self,
+        id: PlaylistId<'_>,
+        name: Option<&str>,
+        description: Option<&str>,
+    ) -> Result<(), ApiError> {
+        self.api_with_retry("change_playlist_details", |api| {
+            api.playlist_change_detail(id.as_ref(), name, None, description, None)
+        })
+        .await
+        .map(|_| ())
+    }
+
+    /// Adds `items` at `position`, or at the end without one. At most 100 at once. Returns
+    /// the new snapshot id.
+    pub async fn add_playlist_items(
+        &self,
+        id: PlaylistId<'_>,
+        items: &[PlayableId<'static>],
+        position: Option<u32>,
+    ) -> Result<String, ApiError> {
+        self.api_with_retry("add_playlist_items", |api| {
+            api.playlist_add_items(id.as_ref(), items.iter().cloned(), position)
+        })
+        .await
+        .map(|result| result.snapshot_id)
+    }
+
+    /// Removes each item at its position in the playlist's `snapshot_id` version. Spotify
+    /// ignores the positions by now and removes every occurrence of the items. Returns the new
+    /// snapshot id.
+    pub async fn remove_playlist_items(
+        &self,
+        id: PlaylistId<'_>,
+        items: &[(PlayableId<'static>, u32)],
+        snapshot_id: &str,
+    ) -> Result<String, ApiError> {
+        self.api_with_retry("remove_playlist_items", |api| {
+            api.playlist_remove_specific_occurrences_of_items(
+                id.as_ref(),
+                items.iter().map(|(id, position)| ItemPositions {
+                    id: id.clone(),
+                    positions: std::slice::from_ref(position),
+                }),
+                Some(snapshot_id),
+            )
+        })
+        .await
+        .map(|result| result.snapshot_id)
+    }
+
+    /// Moves the item at `range_start` in the playlist's `snapshot_id` version to before the
+    /// item at `insert_before`. Returns the new snapshot id.
+    pub async fn reorder_playlist_items(
+        &self,
+        id: PlaylistId<'_>,
+        range_start: u32,
+        insert_before: u32,
+        snapshot_id: &str,
+    ) -> Result<String, ApiError> {
+        self.api_with_retry("reorder_playlist_items", |api| {
+            api.playlist_reorder_items(
+                id.as_ref(),
+                Some(range_start as i32),
+                Some(insert_before as i32),
+                Some(1),
+                Some(snapshot_id),
+            )
+        })
+        .await
+        .map(|result| result.snapshot_id)
+    }
+
+    pub async fn current_user_saved_tracks(
+        &self,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Page<SavedTrack>, ApiError> {
+        self.api_with_retry("current_user_saved_tracks", |api| {
+            api.current_user_saved_tracks_manual(None, limit, offset)
+        })
+        .await
+    }
+
+    /// Likes `ids`, at most 50 at once.
+    pub async fn save_tracks(&self, ids: &[TrackId<'_>]) -> Result<(), ApiError> {
+        self.api_with_retry("save_tracks", |api| {
+            api.current_user_saved_tracks_add(ids.iter().map(|id| id.as_ref()))
+        })
+        .await
+    }
+
+    /// Removes `ids` from the liked songs, at most 50 at once.
+    pub async fn remove_saved_tracks(&self, ids: &[TrackId<'_>]) -> Result<(), ApiError> {
+        self.api_with_retry("remove_saved_tracks", |api| {
+            api.current_user_saved_tracks_delete(ids.iter().map(|id| id.as_ref()))
+        })
+        .await
+    }
+
+    /// Whether each of `ids` is liked, in the same order. At most 50 at once.
+    pub async fn saved_tracks_contain(&self, ids: &[TrackId<'_>]) -> Result<Vec<bool>, ApiError> {
+        self.api_with_retry("saved_tracks_contain", |api| {
+            api.current_user_saved_tracks_contains(ids.iter().map(|id| id.as_ref()))
+        })
+        .await
+    }
+
+    pub async fn track(&self, id: TrackId<'_>) -> Result<FullTrack, ApiError> {
+        self.api_with_retry("track", |api| api.track(id.as_ref(), None))
+            .await
+    }
+
+    /// The tracks the user played the most over `range`, most played first.
+    pub async fn current_user_top_tracks(
+        &self,
+        range: TimeRange,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Page<FullTrack>, ApiError> {
+        self.api_with_retry("current_user_top_tracks", |api| {
+            api.current_user_top_tracks_manual(Some(range), limit, offset)
+        })
+        .await
+    }
+
+    /// The artists the user played the most over `range`, most played first.
+    pub async fn current_user_top_artists(
+        &self,
+        range: TimeRange,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Page<FullArtist>, ApiError> {
+        self.api_with_retry("current_user_top_artists", |api| {
+            api.current_user_top_artists_manual(Some(range), limit, offset)
+        })
+        .await
+    }
+
+    /// The tracks the user played last, newest first. Spotify only keeps the last 50.
+    pub async fn current_user_recently_played(
+        &self,
+        limit: Option<u32>,
+    ) -> Result<CursorBasedPage<PlayHistory>, ApiError> {
+        self.api_with_retry("current_user_recently_played", |api| {
+            api.current_user_recently_played(limit, None)
+        })
+        .await
+    }
+
+    /// Several tracks at once, at most 50. They aren't relinked to the user's market, so they
+    /// keep the ids they were asked for.
+    pub async fn tracks(&self, ids: &[TrackId<'_>]) -> Result<Vec<FullTrack>, ApiError> {
+        self.api_with_retry("tracks", |api| {
+            api.tracks(ids.iter().map(|id| id.as_ref()), None)
+        })
+        .await
+    }
+
+    /// Several episodes at once, at most 50.
+    pub async fn episodes(&self, ids: &[EpisodeId<'_>]) -> Result<Vec<FullEpisode>, ApiError> {
+        self.api_with_retry("episodes", |api| {
+            api.get_several_episodes(ids.iter().map(|id| id.as_ref()), Some(Market::FromToken))
+        })
+        .await
+    }
+
+    /// Podcasts the user saved, most recently saved first.
+    pub async fn current_user_saved_shows(
+        &self,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Page<Show>, ApiError> {
+        self.api_with_retry("current_user_saved_shows", |api| {
+            api.get_saved_show_manual(limit, offset)
+        })
+        .await
+    }
+
+    /// Episodes of the show, newest first, with where the user stopped listening to them.
+    pub async fn show_episodes(
+        &self,
+        id: ShowId<'_>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Page<SimplifiedEpisode>, ApiError> {
+        self.api_with_retry("show_episodes", |api| {
+            api.get_shows_episodes_manual(id.as_ref(), Some(Market::FromToken), limit, offset)
+        })
+        .await
+    }
+
+    pub async fn episode(&self, id: EpisodeId<'_>) -> Result<FullEpisode, ApiError> {
+        self.api_with_retry("episode", |api| {
+            api.get_an_episode(id.as_ref(), Some(Market::FromToken))
+        })
+        .await
+    }
+
+    pub async fn album(&self, id: AlbumId<'_>) -> Result<FullAlbum, ApiError> {
+        self.api_with_retry("album", |api| api.album(id.as_ref(), None))
+            .await
+    }
+
+    pub async fn album_tracks(
+        &self,
+        id: AlbumId<'_>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Page<SimplifiedTrack>, ApiError> {
+        self.api_with_retry("album_tracks", |api| {
+            api.album_track_manual(id.as_ref(), None, limit, offset)
+        })
+        .await
+    }
+
+    pub async fn artist(&self, id: ArtistId<'_>) -> Result<FullArtist, ApiError> {
+        self.api_with_retry("artist", |api| api.artist(id.as_ref()))
+            .await
+    }
+
+    /// The artist's most popular tracks in the user's country.
+    pub async fn artist_top_tracks(&self, id: ArtistId<'_>) -> Result<Vec<FullTrack>, ApiError> {
+        self.api_with_retry("artist_top_tracks", |api| {
+            api.artist_top_tracks(id.as_ref(), Some(Market::FromToken))
+        })
+        .await
+    }
+
+    /// Albums of the artist of type `group`, where [`AlbumType::AppearsOn`] are other artists'
+    /// albums the artist is featured on.
+    pub async fn artist_albums(
+        &self,
+        id: ArtistId<'_>,
+        group: AlbumType,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Page<SimplifiedAlbum>, ApiError> {
+        self.api_with_retry("artist_albums", |api| {
+            api.artist_albums_manual(id.as_ref(), [group], Some(Market::FromToken), limit, offset)
+        })
+        .await
+    }
+
+    pub async fn artist_related_artists(
+        &self,
+        id: ArtistId<'_>,
+    ) -> Result<Vec<FullArtist>, ApiError> {
+        self.api_with_retry("artist_related_artists", |api| {
+            api.artist_related_artists(id.as_ref())
+        })
+        .await
+    }
+
+    /// Tracks like the seed artists and tracks, at most 5 seeds together. Spotify doesn't
+    /// offer recommendations to every app, these fail with [`ApiErrorKind::NotFound`] or
+    /// [`ApiErrorKind::Forbidden`] then.
+    pub async fn recommendations(
+        &self,
+        artists: &[ArtistId<'_>],
+        tracks: &[TrackId<'_>],
+        limit: Option<u32>,
+    ) -> Result<Vec<SimplifiedTrack>, ApiError> {
+        self.api_with_retry("recommendations", |api| {
+            api.recommendations(
+                std::iter::empty::<RecommendationsAttribute>(),
+                (!artists.is_empty()).then(|| artists.iter().map(|id| id.as_ref())),
+                None::<Vec<&str>>,
+                (!tracks.is_empty()).then(|| tracks.iter().map(|id| id.as_ref())),
+                Some(Market::FromToken),
+                limit,
+            )
+        })
+        .await
+        .map(|recommendations| recommendations.tracks)
+    }
+
+    /// Artists the user follows, the page after the artist `after`.
+    pub async fn current_user_followed_artists(
+        &self,
+        limit: Option<u32>,
+        after: Option<String>,
+    ) -> Result<CursorBasedPage<FullArtist>, ApiError> {
+        self.api_with_retry("current_user_followed_artists", |api| {
+            api.current_user_followed_artists(after.as_deref(), limit)
+        })
+        .await
+    }
+
+    /// Follows `ids`, at most 50 at once.
+    pub async fn follow_artists(&self, ids: &[ArtistId<'_>]) -> Result<(), ApiError> {
+        self.api_with_retry("follow_artists", |api| {
+            api.user_follow_artists(ids.iter().map(|id| id.as_ref()))
+        })
+        .await
+    }
+
+    /// Unfollows `ids`, at most 50 at once.
+    pub async fn unfollow_artists(&self, ids: &[ArtistId<'_>]) -> Result<(), ApiError> {
+        self.api_with_retry("unfollow_artists", |api| {
+            api.user_unfollow_artists(ids.iter().map(|id| id.as_ref()))
+        })
+        .await
+    }
+
+    /// Whether each of `ids` is followed, in the same order. At most 50 at once.
+    pub async fn artists_followed(&self, ids: &[ArtistId<'_>]) -> Result<Vec<bool>, ApiError> {
+        self.api_with_retry("artists_followed", |api| {
+            api.user_artist_check_follow(ids.iter().map(|id| id.as_ref()))
+        })
+        .await
+    }
+
+    /// Follows the playlist, which adds it to the user's playlists.
+    pub async fn follow_playlist(&self, id: PlaylistId<'_>) -> Result<(), ApiError> {
+        self.api_with_retry("follow_playlist", |api| {
+            api.playlist_follow(id.as_ref(), None)
+        })
+        .await
+    }
+
+    /// Unfollows the playlist, which removes it from the user's playlists. For the owner this
+    /// is how a playlist is deleted.
+    pub async fn unfollow_playlist(&self, id: PlaylistId<'_>) -> Result<(), ApiError> {
+        self.api_with_retry("unfollow_playlist", |api| {
+            api.playlist_unfollow(id.as_ref())
+        })
+        .await
+    }
+
+    /// Whether `user`, who has to be the current user for private playlists, follows the
+    /// playlist.
+    pub async fn playlist_followed(
+        &self,
+        id: PlaylistId<'_>,
+        user: UserId<'_>,
+    ) -> Result<bool, ApiError> {
+        self.api_with_retry("playlist_followed", |api| {
+            api.playlist_check_follow(id.as_ref(), std::slice::from_ref(&user))
+        })
+        .await
+        .map(|followed| followed.first().copied().unwrap_or(false))
+    }
+
+    /// Follows `ids`, at most 50 at once.
+    pub async fn follow_users(&self, ids: &[UserId<'_>]) -> Result<(), ApiError> {
+        self.api_with_retry("follow_users", |api| {
+            api.user_follow_users(ids.iter().map(|id| id.as_ref()))
+        })
+        .await
+    }
+
+    /// Unfollows `ids`, at most 50 at once.
+    pub async fn unfollow_users(&self, ids: &[UserId<'_>]) -> Result<(), ApiError> {
+        self.api_with_retry("unfollow_users", |api| {
+            api.user_unfollow_users(ids.iter().map(|id| id.as_ref()))
+        })
+        .await
+    }
+
+    /// Whether each of `ids` is followed, in the same order. At most 50 at once.
+    pub async fn users_followed(&self, ids: &[UserId<'_>]) -> Result<Vec<bool>, ApiError> {
+        // rspotify only checks artists, it's the same endpoint with another type
+        let ids = ids.iter().map(|id| id.id()).collect::<Vec<_>>().join(",");
+        let query = Query::from([("type", "user"), ("ids", ids.as_str())]);
+        self.api_with_retry("users_followed", |api| {
+            let query = &query;
+            async move {
+                let followed = api.api_get("me/following/contains", query).await?;
+                Ok(serde_json::from_str(&followed)?)
+            }
+        })
+        .await
+    }
+
+    /// Searches the catalog for `kind` of things matching `query`.
+    pub async fn search(
+        &self,
+        query: &str,
+        kind: SearchType,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Page<SearchItem>, ApiError> {
+        self.api_with_retry("search", |api| {
+            api.search(query, kind, Some(Market::FromToken), None, limit, offset)
+        })
+        .await
+        .map(search::search_items)
+    }
+
+    /// Searches all of `kinds` with one request, up to `limit` results of each.
+    pub async fn search_multiple(
+        &self,
+        query: &str,
+        kinds: &[SearchType],
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<SearchMultipleResult, ApiError> {
+        self.api_with_retry("search_multiple", |api| {
+            api.search_multiple(
+                query,
+                kinds.iter().copied(),
+                Some(Market::FromToken),
+                None,
+                limit,
+                offset,
+            )
+        })
+        .await
+    }
+
+    /// Plays `context` on this device, starting at `offset` or the first item without one.
+    pub async fn play_context(
+        &self,
+        context: PlayContextId<'_>,
+        offset: Option<Offset>,
+    ) -> Result<(), ApiError> {
+        let device_id = self.session.device_id().to_string();
+        self.api_with_retry("play_context", |api| {
+            api.start_context_playback(context.clone(), Some(&device_id), offset.clone(), None)
+        })
+        .await
+    }
+
+    /// Plays `episode` from `position` within its `show`, so the show's older episodes play
+    /// after it.
+    pub async fn play_episode(
+        &self,
+        show: ShowId<'_>,
+        episode: EpisodeId<'_>,
+        position: Option<TimeDelta>,
+    ) -> Result<(), ApiError> {
+        let device_id = self.session.device_id().to_string();
+        let offset = Offset::Uri(episode.uri());
+        self.api_with_retry("play_episode", |api| {
+            api.start_context_playback(
+                PlayContextId::Show(show.as_ref()),
+                Some(&device_id),
+                Some(offset.clone()),
+                position,
+            )
+        })
+        .await
+    }
+
+    /// What's playing and what comes after it on the active device. Doesn't tell the tracks
+    /// the user queued from the rest, see [`SpotifyContext::connect_cluster`] for that.
+    pub async fn current_user_queue(&self) -> Result<CurrentUserQueue, ApiError> {
+        self.api_with_retry("current_user_queue", |api| api.current_user_queue())
+            .await
+    }
+
+    /// Adds `item` to the end of what the user queued on the active device.
+    pub async fn add_to_queue(&self, item: PlayableId<'_>) -> Result<(), ApiError> {
+        self.api_with_retry("add_to_queue", |api| {
+            api.add_item_to_queue(item.clone(), None)
+        })
+        .await
+    }
+
+    /// Plays `uris` on this device as a list of their own, starting at `offset`.
+    pub async fn play_uris(
+        &self,
+        uris: Vec<PlayableId<'static>>,
+        offset: Option<Offset>,
+    ) -> Result<(), ApiError> {
+        let device_id = self.session.device_id().to_string();
+        self.api_with_retry("play_uris", |api| {
+            api.start_uris_playback(uris.clone(), Some(&device_id), offset.clone(), None)
+        })
+        .await
+    }
+}
+
+fn librespot_token_to_rspotify(token: &OAuthToken) -> Token {
+    let expires_in = token.expires_at.saturating_duration_since(Instant::now());
+    let expires_in = TimeDelta::from_std(expires_in).unwrap_or(TimeDelta::zero());
+    Token {
+        access_token: token.access_token.clone(),
+        scopes: HashSet::from_iter(token.scopes.iter().cloned()),
+        refresh_token: None,
+        expires_at: Some(Utc::now() + expires_in),
+        expires_in,
+    }
+}
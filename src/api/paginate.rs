@@ -0,0 +1,219 @@
+use std::{future::Future, ops::Range, sync::Arc};
+
+use cushy::value::{Destination, Dynamic};
+use futures_util::{future, stream, Stream, StreamExt, TryStreamExt};
+use rspotify::model::{CursorBasedPage, Page};
+
+use super::{ApiError, SpotifyContextRef};
+
+/// Largest page size most endpoints accept.
+pub const MAX_PAGE_SIZE: u32 = 50;
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Walks an offset-paged endpoint. `fetch` is called with the context, limit and offset.
+///
+/// The first page is fetched alone to learn the total, the remaining pages are then fetched
+/// with up to `concurrency` requests in flight, and yielded in order.
+/// Dropping the stream cancels all outstanding requests.
+pub struct Paginator<F> {
+    context: SpotifyContextRef,
+    fetch: F,
+    page_size: u32,
+    concurrency: usize,
+    range: Range<u32>,
+}
+
+impl<F> Paginator<F> {
+    pub fn new(context: SpotifyContextRef, fetch: F) -> Self {
+        Self {
+            context,
+            fetch,
+            page_size: MAX_PAGE_SIZE,
+            concurrency: DEFAULT_CONCURRENCY,
+            range: 0..u32::MAX,
+        }
+    }
+
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Maximum number of requests in flight at once.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Only fetch items with an index within `range`. Must not be empty.
+    pub fn range(mut self, range: Range<u32>) -> Self {
+        self.range = range;
+        self
+    }
+
+    pub fn pages<Fut, T>(self) -> impl Stream<Item = Result<Page<T>, ApiError>> + Send + 'static
+    where
+        F: Fn(SpotifyContextRef, u32, u32) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Page<T>, ApiError>> + Send + 'static,
+        T: Send + 'static,
+    {
+        let Paginator {
+            context,
+            fetch,
+            page_size,
+            concurrency,
+            range,
+        } = self;
+        let fetch = Arc::new(fetch);
+        let first_limit = page_size.min(range.end.saturating_sub(range.start)).max(1);
+        let first = fetch(context.clone(), first_limit, range.start);
+
+        stream::once(first).flat_map(move |first| {
+            let end = match &first {
+                Ok(page) => range.end.min(page.total),
+                // don't hammer an endpoint that just failed
+                Err(_) => range.start,
+            };
+            let context = context.clone();
+            let fetch = fetch.clone();
+            let rest = (range.start.saturating_add(page_size)..end)
+                .step_by(page_size as usize)
+                .map(move |offset| fetch(context.clone(), page_size.min(end - offset), offset));
+            stream::once(future::ready(first)).chain(stream::iter(rest).buffered(concurrency))
+        })
+    }
+
+    pub fn items<Fut, T>(self) -> impl Stream<Item = Result<T, ApiError>> + Send + 'static
+    where
+        F: Fn(SpotifyContextRef, u32, u32) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Page<T>, ApiError>> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.pages()
+            .map_ok(|page| stream::iter(page.items.into_iter().map(Ok)))
+            .try_flatten()
+    }
+
+    /// Items together with their index in the whole collection.
+    pub fn indexed_items<Fut, T>(
+        self,
+    ) -> impl Stream<Item = Result<(usize, T), ApiError>> + Send + 'static
+    where
+        F: Fn(SpotifyContextRef, u32, u32) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Page<T>, ApiError>> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.pages()
+            .map_ok(|page| {
+                let offset = page.offset as usize;
+                stream::iter(
+                    page.items
+                        .into_iter()
+                        .enumerate()
+                        .map(move |(i, item)| Ok((offset + i, item))),
+                )
+            })
+            .try_flatten()
+    }
+}
+
+/// Walks a cursor-paged endpoint. `fetch` is called with the context, limit and the `after`
+/// cursor of the previous page. Pages are necessarily fetched one after another.
+pub struct CursorPaginator<F> {
+    context: SpotifyContextRef,
+    fetch: F,
+    page_size: u32,
+}
+
+impl<F> CursorPaginator<F> {
+    pub fn new(context: SpotifyContextRef, fetch: F) -> Self {
+        Self {
+            context,
+            fetch,
+            page_size: MAX_PAGE_SIZE,
+        }
+    }
+
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    pub fn pages<Fut, T>(
+        self,
+    ) -> impl Stream<Item = Result<CursorBasedPage<T>, ApiError>> + Send + 'static
+    where
+        F: Fn(SpotifyContextRef, u32, Option<String>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<CursorBasedPage<T>, ApiError>> + Send + 'static,
+        T: Send + 'static,
+    {
+        let CursorPaginator {
+            context,
+            fetch,
+            page_size,
+        } = self;
+        let fetch = Arc::new(fetch);
+        // `None` once there are no more pages, `Some(cursor)` otherwise
+        stream::unfold(Some(None), move |after: Option<Option<String>>| {
+            let context = context.clone();
+            let fetch = fetch.clone();
+            async move {
+                let page = fetch(context, page_size, after?).await;
+                let next = match &page {
+                    Ok(page) if page.next.is_some() => page
+                        .cursors
+                        .as_ref()
+                        .and_then(|cursors| cursors.after.clone())
+                        .map(Some),
+                    _ => None,
+                };
+                Some((page, next))
+            }
+        })
+    }
+
+    pub fn items<Fut, T>(self) -> impl Stream<Item = Result<T, ApiError>> + Send + 'static
+    where
+        F: Fn(SpotifyContextRef, u32, Option<String>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<CursorBasedPage<T>, ApiError>> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.pages()
+            .map_ok(|page| stream::iter(page.items.into_iter().map(Ok)))
+            .try_flatten()
+    }
+}
+
+/// Appends everything `items` yields to `target`, stopping at the first error or once
+/// nothing else holds on to `target` anymore.
+pub async fn extend_dynamic<T>(
+    items: impl Stream<Item = Result<T, ApiError>>,
+    target: Dynamic<Vec<T>>,
+) -> Result<(), ApiError>
+where
+    T: Send + 'static,
+{
+    // update the target once per batch instead of once per item
+    let mut batches = std::pin::pin!(items.ready_chunks(MAX_PAGE_SIZE as usize));
+    while let Some(batch) = batches.next().await {
+        if target.instances() <= 1 {
+            break;
+        }
+        let mut loaded = Vec::with_capacity(batch.len());
+        let mut error = None;
+        for item in batch {
+            match item {
+                Ok(item) => loaded.push(item),
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+        target.map_mut(|mut target| target.extend(loaded));
+        if let Some(e) = error {
+            return Err(e);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::StatusCode;
+
+use super::{ApiError, ApiErrorKind};
+use crate::cli::Args;
+
+/// When and how often failed Web API requests are retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts per request, including the first one.
+    pub max_attempts: u32,
+    /// Backoff before the first retry, doubled for every further one.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// How long a request may take in total, retries included.
+    pub deadline: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_args(args: &Args) -> Self {
+        Self {
+            max_attempts: args.api_max_attempts.max(1),
+            initial_backoff: Duration::from_millis(args.api_initial_backoff_ms),
+            max_backoff: Duration::from_millis(args.api_max_backoff_ms),
+            deadline: Duration::from_secs(args.api_retry_deadline),
+        }
+    }
+
+    /// Whether a request that failed with `error` may succeed when sent again.
+    pub fn is_retryable(&self, error: &ApiError) -> bool {
+        match error.kind {
+            ApiErrorKind::Offline | ApiErrorKind::RateLimited => true,
+            ApiErrorKind::Server => matches!(
+                error.status,
+                Some(
+                    StatusCode::BAD_GATEWAY
+                        | StatusCode::SERVICE_UNAVAILABLE
+                        | StatusCode::GATEWAY_TIMEOUT
+                )
+            ),
+            _ => false,
+        }
+    }
+
+    /// How long to wait before retry number `retry`, starting at 1.
+    ///
+    /// The exponential backoff is jittered between half and all of it, so that requests that
+    /// failed together don't all come back at the same time.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(retry.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+        backoff.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    pin::pin,
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+
+use tokio::{sync::Notify, time::Instant};
+
+#[cfg(test)]
+mod tests;
+
+/// Requests allowed in a burst.
+const BUCKET_CAPACITY: f64 = 10.0;
+/// Sustained requests per second, well below what gets rate limited.
+const REFILL_PER_SECOND: f64 = 4.0;
+/// Waiting requests check this often whether they're still wanted.
+const MAX_WAIT: Duration = Duration::from_millis(250);
+
+/// How urgent a Web API request is. Requests of a higher priority always go first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Content that isn't shown yet.
+    Prefetch,
+    /// Content currently on screen.
+    Visible,
+    /// Something the user clicked and is waiting for.
+    UserAction,
+}
+
+/// Keeps requests made on behalf of something on screen wanted. Once every clone is
+/// dropped, e.g. because the page that needed it was closed, requests still waiting for
+/// their turn are dropped.
+#[derive(Debug, Clone, Default)]
+pub struct Interest(Arc<()>);
+
+#[derive(Debug, Clone, Default)]
+pub struct WeakInterest(Weak<()>);
+
+impl Interest {
+    pub fn downgrade(&self) -> WeakInterest {
+        WeakInterest(Arc::downgrade(&self.0))
+    }
+}
+
+impl WeakInterest {
+    pub fn upgrade(&self) -> Option<Interest> {
+        self.0.upgrade().map(Interest)
+    }
+
+    pub fn is_gone(&self) -> bool {
+        self.0.strong_count() == 0
+    }
+}
+
+/// Whether a request made on behalf of `interests` can be dropped. Without any, it's wanted
+/// regardless of what's on screen.
+fn all_gone(interests: &[WeakInterest]) -> bool {
+    !interests.is_empty() && interests.iter().all(WeakInterest::is_gone)
+}
+
+/// One [`Interest`] per range of list rows, shared by the rows in it. Rows hold on to theirs,
+/// so once all rows of a range scrolled away, requests made for them are dropped.
+#[derive(Debug, Clone)]
+pub struct RowInterests {
+    rows_per_range: usize,
+    ranges: Arc<Mutex<HashMap<usize, WeakInterest>>>,
+}
+
+impl RowInterests {
+    pub fn new(rows_per_range: usize) -> Self {
+        Self {
+            rows_per_range: rows_per_range.max(1),
+            ranges: Default::default(),
+        }
+    }
+
+    /// The interest of the range `index` is in, a new one if none of its rows are left.
+    pub fn for_row(&self, index: usize) -> Interest {
+        let mut ranges = self.ranges.lock().unwrap();
+        let range = index / self.rows_per_range;
+        if let Some(interest) = ranges.get(&range).and_then(WeakInterest::upgrade) {
+            return interest;
+        }
+        ranges.retain(|_, interest| !interest.is_gone());
+        let interest = Interest::default();
+        ranges.insert(range, interest.downgrade());
+        interest
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RequestClass {
+    priority: Priority,
+    interests: Vec<WeakInterest>,
+}
+
+tokio::task_local! {
+    static REQUEST_CLASS: RequestClass;
+}
+
+/// Runs `future` with all its Web API requests scheduled as `priority`. If `interest` is
+/// given, requests still queued once it's gone fail with [`ApiErrorKind::Cancelled`].
+///
+/// [`ApiErrorKind::Cancelled`]: super::ApiErrorKind::Cancelled
+pub fn with_priority<F: Future>(
+    priority: Priority,
+    interest: Option<&Interest>,
+    future: F,
+) -> impl Future<Output = F::Output> {
+    with_interests(
+        priority,
+        interest.into_iter().map(Interest::downgrade).collect(),
+        future,
+    )
+}
+
+/// Like [`with_priority`], for requests made on behalf of several things on screen at once.
+/// Requests still queued are only dropped once all of `interests` are gone.
+pub fn with_interests<F: Future>(
+    priority: Priority,
+    interests: Vec<WeakInterest>,
+    future: F,
+) -> impl Future<Output = F::Output> {
+    REQUEST_CLASS.scope(
+        RequestClass {
+            priority,
+            interests,
+        },
+        future,
+    )
+}
+
+/// The request was dropped while waiting because nothing needs it anymore.
+#[derive(Debug)]
+pub struct Dropped;
+
+type QueueKey = (Reverse<Priority>, u64);
+
+#[derive(Debug)]
+struct State {
+    tokens: f64,
+    refilled_at: Instant,
+    paused_until: Option<Instant>,
+    /// Waiting requests, the most urgent first.
+    queue: BTreeMap<QueueKey, Vec<WeakInterest>>,
+    next_id: u64,
+}
+
+impl State {
+    fn refill(&mut self, now: Instant) {
+        // no tokens accumulate while paused
+        if now <= self.refilled_at {
+            return;
+        }
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * REFILL_PER_SECOND).min(BUCKET_CAPACITY);
+        self.refilled_at = now;
+    }
+}
+
+/// Hands out permits to send Web API requests, at most [`REFILL_PER_SECOND`] on average and
+/// most urgent first.
+#[derive(Debug)]
+pub struct Scheduler {
+    state: Mutex<State>,
+    changed: Notify,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(State {
+                tokens: BUCKET_CAPACITY,
+                refilled_at: Instant::now(),
+                paused_until: None,
+                queue: BTreeMap::new(),
+                next_id: 0,
+            }),
+            changed: Notify::new(),
+        }
+    }
+}
+
+impl Scheduler {
+    /// Waits until the current task may send a request, see [`with_priority`].
+    pub async fn acquire(&self) -> Result<(), Dropped> {
+        let class = REQUEST_CLASS
+            .try_with(Clone::clone)
+            .unwrap_or(RequestClass {
+                priority: Priority::Visible,
+                interests: Vec::new(),
+            });
+        let key = {
+            let mut state = self.state.lock().unwrap();
+            let key = (Reverse(class.priority), state.next_id);
+            state.next_id += 1;
+            state.queue.insert(key, class.interests);
+            key
+        };
+        // leaves the queue when the caller gives up waiting
+        let _queued = Dequeue {
+            scheduler: self,
+            key,
+        };
+
+        loop {
+            let mut changed = pin!(self.changed.notified());
+            // don't miss changes between checking the state and waiting
+            changed.as_mut().enable();
+
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                state.refill(now);
+                let queued = state.queue.len();
+                state.queue.retain(|_, interests| !all_gone(interests));
+                if state.queue.len() != queued {
+                    self.changed.notify_waiters();
+                }
+                if !state.queue.contains_key(&key) {
+                    return Err(Dropped);
+                }
+
+                match state.paused_until {
+                    Some(until) if until > now => until - now,
+                    _ if state.tokens < 1.0 => {
+                        Duration::from_secs_f64((1.0 - state.tokens) / REFILL_PER_SECOND)
+                    }
+                    _ if state.queue.first_key_value().map(|(k, _)| k) == Some(&key) => {
+                        state.tokens -= 1.0;
+                        state.queue.remove(&key);
+                        // the next request may be able to go as well
+                        self.changed.notify_waiters();
+                        return Ok(());
+                    }
+                    // a more urgent request goes first
+                    _ => MAX_WAIT,
+                }
+            };
+            let _ = tokio::time::timeout(wait.min(MAX_WAIT), changed).await;
+        }
+    }
+
+    /// Holds back all requests for `duration`, e.g. after Spotify asked us to slow down.
+    pub fn pause_for(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let until = Instant::now() + duration;
+        state.paused_until = Some(state.paused_until.map_or(until, |paused| paused.max(until)));
+        state.tokens = 0.0;
+        state.refilled_at = state.refilled_at.max(until);
+    }
+}
+
+struct Dequeue<'a> {
+    scheduler: &'a Scheduler,
+    key: QueueKey,
+}
+
+impl Drop for Dequeue<'_> {
+    fn drop(&mut self) {
+        let removed = self.scheduler.state.lock().unwrap().queue.remove(&self.key);
+        if removed.is_some() {
+            self.scheduler.changed.notify_waiters();
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    future::Future,
+    pin::{pin, Pin},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures_util::{future, FutureExt};
+use tokio::{
+    runtime,
+    time::{self, Instant},
+};
+
+use super::{
+    with_interests, with_priority, Dropped, Interest, Priority, RowInterests, Scheduler, State,
+    BUCKET_CAPACITY, MAX_WAIT, REFILL_PER_SECOND,
+};
+
+/// Time between two tokens once the bucket is empty.
+const TOKEN_INTERVAL: Duration = Duration::from_millis((1000.0 / REFILL_PER_SECOND) as u64);
+/// Timers fire on the millisecond after their deadline.
+const TICK: Duration = Duration::from_millis(1);
+
+/// Runs `future` with the clock paused, it only moves on with [`time::advance`] or once
+/// everything waits for it.
+fn block_on_paused<F: Future>(future: F) -> F::Output {
+    runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap()
+        .block_on(async {
+            time::pause();
+            future.await
+        })
+}
+
+/// Polls `request` once, without letting any time pass.
+fn poll_once(request: Pin<&mut impl Future<Output = Result<(), Dropped>>>) -> Option<bool> {
+    request.now_or_never().map(|result| result.is_ok())
+}
+
+fn empty_state(refilled_at: Instant) -> State {
+    State {
+        tokens: 0.0,
+        refilled_at,
+        paused_until: None,
+        queue: Default::default(),
+        next_id: 0,
+    }
+}
+
+#[test]
+fn refill_adds_tokens_at_the_refill_rate() {
+    let now = Instant::now();
+    let mut state = empty_state(now - Duration::from_millis(500));
+
+    state.refill(now);
+
+    assert!((state.tokens - REFILL_PER_SECOND / 2.0).abs() < 1e-9);
+    assert_eq!(state.refilled_at, now);
+}
+
+#[test]
+fn refill_is_capped_at_the_bucket_capacity() {
+    let now = Instant::now();
+    let mut state = empty_state(now - Duration::from_secs(3600));
+
+    state.refill(now);
+
+    assert_eq!(state.tokens, BUCKET_CAPACITY);
+}
+
+#[test]
+fn no_tokens_accumulate_while_paused() {
+    let scheduler = Scheduler::default();
+    scheduler.pause_for(Duration::from_secs(60));
+    let mut state = scheduler.state.lock().unwrap();
+
+    state.refill(Instant::now() + Duration::from_secs(30));
+
+    assert_eq!(state.tokens, 0.0);
+}
+
+#[test]
+fn a_full_bucket_allows_a_burst_then_waits_for_the_refill() {
+    block_on_paused(async {
+        let scheduler = Scheduler::default();
+        for _ in 0..BUCKET_CAPACITY as usize {
+            let request = pin!(scheduler.acquire());
+            assert_eq!(poll_once(request), Some(true));
+        }
+
+        let mut request = pin!(scheduler.acquire());
+        assert_eq!(poll_once(request.as_mut()), None);
+        time::advance(TOKEN_INTERVAL / 2).await;
+        assert_eq!(poll_once(request.as_mut()), None);
+        time::advance(TOKEN_INTERVAL / 2 + TICK).await;
+        assert_eq!(poll_once(request), Some(true));
+    });
+}
+
+#[test]
+fn more_urgent_requests_go_first() {
+    let order = Arc::new(Mutex::new(Vec::new()));
+
+    block_on_paused(async {
+        let scheduler = Scheduler::default();
+        scheduler.pause_for(Duration::from_millis(50));
+        let request = |priority| {
+            let order = order.clone();
+            let scheduler = &scheduler;
+            with_priority(priority, None, async move {
+                scheduler.acquire().await.unwrap();
+                order.lock().unwrap().push(priority);
+            })
+        };
+
+        future::join3(
+            request(Priority::Prefetch),
+            request(Priority::Visible),
+            request(Priority::UserAction),
+        )
+        .await;
+    });
+
+    assert_eq!(
+        *order.lock().unwrap(),
+        [Priority::UserAction, Priority::Visible, Priority::Prefetch]
+    );
+}
+
+#[test]
+fn waiting_requests_are_dropped_once_their_interest_is_gone() {
+    block_on_paused(async {
+        let scheduler = Scheduler::default();
+        scheduler.pause_for(Duration::from_secs(60));
+        let interest = Interest::default();
+        let mut request = pin!(with_priority(
+            Priority::Visible,
+            Some(&interest),
+            scheduler.acquire()
+        ));
+        assert_eq!(poll_once(request.as_mut()), None);
+
+        drop(interest);
+        time::advance(MAX_WAIT + TICK).await;
+
+        assert_eq!(poll_once(request), Some(false));
+    });
+}
+
+#[test]
+fn waiting_requests_for_several_rows_stay_until_all_of_them_are_gone() {
+    block_on_paused(async {
+        let scheduler = Scheduler::default();
+        scheduler.pause_for(Duration::from_secs(60));
+        let (first, second) = (Interest::default(), Interest::default());
+        let mut request = pin!(with_interests(
+            Priority::Visible,
+            vec![first.downgrade(), second.downgrade()],
+            scheduler.acquire(),
+        ));
+        assert_eq!(poll_once(request.as_mut()), None);
+
+        drop(first);
+        time::advance(MAX_WAIT + TICK).await;
+        assert_eq!(poll_once(request.as_mut()), None);
+
+        drop(second);
+        time::advance(MAX_WAIT + TICK).await;
+        assert_eq!(poll_once(request), Some(false));
+    });
+}
+
+#[test]
+fn rows_of_a_range_share_their_interest() {
+    let interests = RowInterests::new(10);
+
+    let first = interests.for_row(3);
+
+    assert!(Arc::ptr_eq(&first.0, &interests.for_row(9).0));
+    assert!(!Arc::ptr_eq(&first.0, &interests.for_row(10).0));
+}
+
+#[test]
+fn a_range_gets_a_new_interest_once_its_rows_are_gone() {
+    let interests = RowInterests::new(10);
+    let gone = interests.for_row(3).downgrade();
+
+    let again = interests.for_row(4);
+
+    assert!(gone.is_gone());
+    assert!(!again.downgrade().is_gone());
+}
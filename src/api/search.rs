@@ -0,0 +1,175 @@
+use std::cmp::Reverse;
+
+use rspotify::model::{
+    FullArtist, FullTrack, Page, SearchMultipleResult, SearchResult, SearchType, SimplifiedAlbum,
+    SimplifiedEpisode, SimplifiedPlaylist, SimplifiedShow,
+};
+
+use super::{ApiError, SpotifyContextRef};
+
+/// What a search looks for, in the order the results are shown.
+pub const SEARCH_TYPES: [SearchType; 6] = [
+    SearchType::Track,
+    SearchType::Artist,
+    SearchType::Album,
+    SearchType::Playlist,
+    SearchType::Show,
+    SearchType::Episode,
+];
+
+/// One search result, whatever its type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchItem {
+    Track(FullTrack),
+    Artist(FullArtist),
+    Album(SimplifiedAlbum),
+    Playlist(SimplifiedPlaylist),
+    Show(SimplifiedShow),
+    Episode(SimplifiedEpisode),
+}
+
+impl SearchItem {
+    pub fn name(&self) -> &str {
+        match self {
+            SearchItem::Track(track) => &track.name,
+            SearchItem::Artist(artist) => &artist.name,
+            SearchItem::Album(album) => &album.name,
+            SearchItem::Playlist(playlist) => &playlist.name,
+            SearchItem::Show(show) => &show.name,
+            SearchItem::Episode(episode) => &episode.name,
+        }
+    }
+
+    /// Only tracks and artists have one, from 0 to 100.
+    fn popularity(&self) -> u32 {
+        match self {
+            SearchItem::Track(track) => track.popularity,
+            SearchItem::Artist(artist) => artist.popularity,
+            _ => 0,
+        }
+    }
+}
+
+/// Turns the results of a single-type search into items.
+pub(super) fn search_items(result: SearchResult) -> Page<SearchItem> {
+    match result {
+        SearchResult::Tracks(page) => map_page(page, SearchItem::Track),
+        SearchResult::Artists(page) => map_page(page, SearchItem::Artist),
+        SearchResult::Albums(page) => map_page(page, SearchItem::Album),
+        SearchResult::Playlists(page) => map_page(page, SearchItem::Playlist),
+        SearchResult::Shows(page) => map_page(page, SearchItem::Show),
+        SearchResult::Episodes(page) => map_page(page, SearchItem::Episode),
+    }
+}
+
+/// Turns the results of a multi-type search into a page per type, in the order of
+/// [`SEARCH_TYPES`]. Types that weren't searched for are left out.
+fn search_pages(result: SearchMultipleResult) -> Vec<(SearchType, Page<SearchItem>)> {
+    let SearchMultipleResult {
+        tracks,
+        artists,
+        albums,
+        playlists,
+        shows,
+        episodes,
+    } = result;
+    [
+        (
+            SearchType::Track,
+            tracks.map(|page| map_page(page, SearchItem::Track)),
+        ),
+        (
+            SearchType::Artist,
+            artists.map(|page| map_page(page, SearchItem::Artist)),
+        ),
+        (
+            SearchType::Album,
+            albums.map(|page| map_page(page, SearchItem::Album)),
+        ),
+        (
+            SearchType::Playlist,
+            playlists.map(|page| map_page(page, SearchItem::Playlist)),
+        ),
+        (
+            SearchType::Show,
+            shows.map(|page| map_page(page, SearchItem::Show)),
+        ),
+        (
+            SearchType::Episode,
+            episodes.map(|page| map_page(page, SearchItem::Episode)),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(kind, page)| Some((kind, page?)))
+    .collect()
+}
+
+fn map_page<T, U>(page: Page<T>, f: impl FnMut(T) -> U) -> Page<U> {
+    Page {
+        href: page.href,
+        items: page.items.into_iter().map(f).collect(),
+        limit: page.limit,
+        next: page.next,
+        offset: page.offset,
+        previous: page.previous,
+        total: page.total,
+    }
+}
+
+/// The first results of every type for a query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResults {
+    pub query: String,
+    /// A page for each of [`SEARCH_TYPES`], in that order.
+    pub pages: Vec<(SearchType, Page<SearchItem>)>,
+}
+
+impl SearchResults {
+    /// Searches every type with a single request, getting the first `limit` results of each.
+    pub async fn fetch(
+        context: &SpotifyContextRef,
+        query: String,
+        limit: u32,
+    ) -> Result<Self, ApiError> {
+        let result = context
+            .search_multiple(&query, &SEARCH_TYPES, Some(limit), Some(0))
+            .await?;
+        Ok(Self {
+            pages: search_pages(result),
+            query,
+        })
+    }
+
+    /// The result most likely to be what the user is looking for: the best match by name
+    /// among the first result of each type, then the most popular, then the type shown first.
+    /// Episodes are never the top result, they often just mention what was searched for.
+    pub fn top_result(&self) -> Option<&SearchItem> {
+        let query = self.query.to_lowercase();
+        self.pages
+            .iter()
+            .filter_map(|(_, page)| page.items.first())
+            .filter(|item| !matches!(item, SearchItem::Episode(_)))
+            .enumerate()
+            .max_by_key(|(order, item)| {
+                (
+                    name_match(&item.name().to_lowercase(), &query),
+                    item.popularity(),
+                    Reverse(*order),
+                )
+            })
+            .map(|(_, item)| item)
+    }
+}
+
+/// How well `name` matches `query`, both lowercase.
+fn name_match(name: &str, query: &str) -> u8 {
+    if name == query {
+        3
+    } else if name.starts_with(query) {
+        2
+    } else if name.contains(query) {
+        1
+    } else {
+        0
+    }
+}
@@ -0,0 +1,57 @@
+use std::fmt;
+
+use librespot_oauth::OAuthError;
+use oauth2::{
+    basic::{BasicErrorResponse, BasicErrorResponseType},
+    RequestTokenError,
+};
+
+use super::store::StoreError;
+
+/// Everything that can go wrong while logging in or refreshing a token.
+///
+/// Neither `Display` nor `Debug` ever include token material, so these are safe to log.
+#[derive(thiserror::Error)]
+pub enum AuthError {
+    #[error("couldn't reach Spotify: {0}")]
+    Network(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// The refresh token was revoked, expired or is otherwise no longer accepted.
+    #[error("the stored login is no longer valid, log in again")]
+    InvalidGrant,
+    /// The login doesn't cover all configured scopes, e.g. after new ones were added.
+    #[error("the stored login lacks the scopes {}, log in again", .0.join(", "))]
+    MissingScopes(Vec<String>),
+    #[error("Spotify rejected the token request: {0}")]
+    Rejected(String),
+    #[error("credential storage failed: {0}")]
+    Storage(#[from] StoreError),
+    #[error("login failed: {0}")]
+    Login(#[from] OAuthError),
+}
+
+impl fmt::Debug for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the derived impl would print the wrapped errors, which may contain response bodies
+        write!(f, "AuthError({self})")
+    }
+}
+
+impl<RE> From<RequestTokenError<RE, BasicErrorResponse>> for AuthError
+where
+    RE: std::error::Error + Send + Sync + 'static,
+{
+    fn from(error: RequestTokenError<RE, BasicErrorResponse>) -> Self {
+        match error {
+            RequestTokenError::ServerResponse(response) => match response.error() {
+                BasicErrorResponseType::InvalidGrant => AuthError::InvalidGrant,
+                other => AuthError::Rejected(other.as_ref().to_string()),
+            },
+            RequestTokenError::Request(e) => AuthError::Network(Box::new(e)),
+            // the body of an unparsable response could still contain tokens, don't keep it
+            RequestTokenError::Parse(_, _) => {
+                AuthError::Rejected("unexpected token response".to_string())
+            }
+            RequestTokenError::Other(message) => AuthError::Rejected(message),
+        }
+    }
+}
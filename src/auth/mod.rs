@@ -0,0 +1,287 @@
+use std::{collections::HashSet, io::BufRead, time::{Duration, Instant}};
+use librespot_core::SessionConfig;
+use librespot_oauth::{get_access_token, OAuthError, OAuthToken};
+use oauth2::{basic::{BasicClient, BasicTokenResponse}, reqwest::http_client, url::Url, AuthUrl, AuthorizationCode, ClientId, CsrfToken, PkceCodeChallenge, RedirectUrl, RefreshToken, Scope, TokenResponse, TokenUrl};
+use store::CredentialStore;
+
+use crate::cli::{Args, LoginMode};
+
+pub use error::AuthError;
+
+pub mod error;
+pub mod store;
+#[cfg(test)]
+mod tests;
+
+/// Scopes requested unless configured otherwise, despot needs all of them for full functionality.
+pub const DEFAULT_SCOPES: [&str; 16] = [
+    "user-read-playback-state",
+    "user-modify-playback-state",
+    "user-read-currently-playing",
+    "app-remote-control",
+    "streaming",
+    "playlist-read-private",
+    "playlist-read-collaborative",
+    "playlist-modify-private",
+    "playlist-modify-public",
+    "user-follow-modify",
+    "user-follow-read",
+    "user-read-playback-position",
+    "user-top-read",
+    "user-read-recently-played",
+    "user-library-modify",
+    "user-library-read",
+];
+
+/// Spotify's OAuth endpoints.
+pub const SPOTIFY_AUTHORIZE_URL: &str = "https://accounts.spotify.com/authorize";
+pub const SPOTIFY_TOKEN_URL: &str = "https://accounts.spotify.com/api/token";
+
+/// The Spotify client to log in with and how to log in when there's no usable refresh token.
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    pub authorize_url: String,
+    pub token_url: String,
+    pub client_id: String,
+    /// Must be registered as a redirect URI of the Spotify client.
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    pub login_mode: LoginMode,
+}
+
+impl AuthConfig {
+    pub fn from_args(args: &Args) -> Self {
+        Self {
+            authorize_url: SPOTIFY_AUTHORIZE_URL.to_string(),
+            token_url: SPOTIFY_TOKEN_URL.to_string(),
+            client_id: args
+                .client_id
+                .clone()
+                .unwrap_or_else(|| SessionConfig::default().client_id),
+            redirect_uri: args.redirect_uri.clone().unwrap_or_else(|| {
+                format!("http://{}:{}/login", args.redirect_host, args.redirect_port)
+            }),
+            scopes: args
+                .scopes
+                .clone()
+                .unwrap_or_else(|| DEFAULT_SCOPES.map(str::to_string).to_vec()),
+            login_mode: args.login_mode,
+        }
+    }
+
+    pub fn rspotify_scopes(&self) -> HashSet<String> {
+        HashSet::from_iter(self.scopes.iter().cloned())
+    }
+
+    /// Configured scopes that weren't granted to `token`.
+    pub fn missing_scopes(&self, token: &OAuthToken) -> Vec<String> {
+        self.scopes
+            .iter()
+            .filter(|scope| !token.scopes.contains(scope))
+            .cloned()
+            .collect()
+    }
+}
+
+fn read_refresh_token(store: &dyn CredentialStore) -> Option<String> {
+    match store.load() {
+        Ok(token) => token,
+        Err(e) => {
+            eprintln!("Failed to read stored credentials: {}", e);
+            None
+        }
+    }
+}
+
+fn write_refresh_token(store: &dyn CredentialStore, token: &str) -> Result<(), AuthError> {
+    if token.is_empty() {
+        return Ok(());
+    }
+    Ok(store.save(token)?)
+}
+
+fn oauth2_client(config: &AuthConfig) -> Result<BasicClient, OAuthError> {
+    let auth_url = AuthUrl::new(config.authorize_url.clone())
+        .map_err(|_| OAuthError::InvalidSpotifyUri)?;
+    let token_url = TokenUrl::new(config.token_url.clone())
+        .map_err(|_| OAuthError::InvalidSpotifyUri)?;
+    let redirect_url =
+        RedirectUrl::new(config.redirect_uri.clone()).map_err(|e| OAuthError::InvalidRedirectUri {
+            uri: config.redirect_uri.clone(),
+            e,
+        })?;
+    let client = BasicClient::new(
+        ClientId::new(config.client_id.clone()),
+        None,
+        auth_url,
+        Some(token_url),
+    );
+    let client = client.set_redirect_uri(redirect_url);
+    Ok(client)
+}
+
+pub fn get_access_token_from_refresh_token(
+    config: &AuthConfig,
+    store: &dyn CredentialStore,
+    refresh_token: &str,
+) -> Result<OAuthToken, AuthError> {
+    let client = oauth2_client(config)?;
+    let token = client
+        .exchange_refresh_token(&RefreshToken::new(refresh_token.to_string()))
+        .request(http_client)?;
+
+    // the refresh token may or may not be rotated, keep the old one if it isn't
+    let token = into_oauth_token(token, &config.scopes, refresh_token);
+    write_refresh_token(store, &token.refresh_token)?;
+
+    // the grant keeps the scopes of the original login, new ones need a new login
+    let missing = config.missing_scopes(&token);
+    if !missing.is_empty() {
+        return Err(AuthError::MissingScopes(missing));
+    }
+    Ok(token)
+}
+
+/// `requested_scopes` are assumed to be granted when the response doesn't list the scopes,
+/// `previous_refresh_token` is kept when the response doesn't include a new one.
+fn into_oauth_token(
+    token: BasicTokenResponse,
+    requested_scopes: &[String],
+    previous_refresh_token: &str,
+) -> OAuthToken {
+    let refresh_token = match token.refresh_token() {
+        Some(t) => t.secret().to_string(),
+        _ => previous_refresh_token.to_string(),
+    };
+
+    OAuthToken {
+        access_token: token.access_token().secret().to_string(),
+        refresh_token,
+        expires_at: Instant::now()
+            + token
+                .expires_in()
+                .unwrap_or_else(|| Duration::from_secs(3600)),
+        token_type: format!("{:?}", token.token_type()).to_string(), // Urgh!?
+        scopes: match token.scopes() {
+            Some(scopes) => scopes.iter().map(|scope| scope.as_str().to_string()).collect(),
+            None => requested_scopes.to_vec(),
+        },
+    }
+}
+
+/// Logs in without a local browser: prints the authorize URL and reads the URL the
+/// browser was redirected to (or just its `code` parameter) from stdin.
+fn get_access_token_headless(config: &AuthConfig) -> Result<OAuthToken, AuthError> {
+    let client = oauth2_client(config)?;
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let (auth_url, csrf_token) = client
+        .authorize_url(CsrfToken::new_random)
+        .add_scopes(config.scopes.iter().cloned().map(Scope::new))
+        .set_pkce_challenge(pkce_challenge)
+        .url();
+
+    println!("Open this URL in a browser on any device and log in:\n\n{auth_url}\n");
+    println!(
+        "The browser then gets redirected to {}, which will likely fail to load.",
+        config.redirect_uri
+    );
+    println!("Paste the URL from the address bar (or just its code) here:");
+    let mut input = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut input)
+        .map_err(|_| OAuthError::AuthCodeStdinRead)?;
+    let code = parse_auth_code(input.trim(), &csrf_token)?;
+
+    let token = client
+        .exchange_code(code)
+        .set_pkce_verifier(pkce_verifier)
+        .request(http_client)?;
+    Ok(into_oauth_token(token, &config.scopes, ""))
+}
+
+/// Extracts the authorization code from a pasted redirect URL, or takes the input as the code.
+fn parse_auth_code(input: &str, csrf_token: &CsrfToken) -> Result<AuthorizationCode, AuthError> {
+    let Ok(url) = Url::parse(input) else {
+        if input.is_empty() {
+            return Err(OAuthError::AuthCodeStdinRead.into());
+        }
+        return Ok(AuthorizationCode::new(input.to_string()));
+    };
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    if param("state").is_some_and(|state| state != *csrf_token.secret()) {
+        return Err(AuthError::Rejected(
+            "state of the redirect doesn't match the login request".to_string(),
+        ));
+    }
+    param("code")
+        .map(AuthorizationCode::new)
+        .ok_or_else(|| {
+            OAuthError::AuthCodeNotFound {
+                uri: input.to_string(),
+            }
+            .into()
+        })
+}
+
+pub fn get_token(
+    store: &dyn CredentialStore,
+    config: &AuthConfig,
+) -> Result<OAuthToken, AuthError> {
+    get_token_with_login(store, config, |config| match config.login_mode {
+        LoginMode::Browser => get_access_token(
+            &config.client_id,
+            &config.redirect_uri,
+            config.scopes.iter().map(String::as_str).collect(),
+        )
+        .map_err(AuthError::from),
+        LoginMode::Headless => get_access_token_headless(config),
+    })
+}
+
+/// Like [`get_token`], but logs in with `login` when the stored refresh token can't be used.
+fn get_token_with_login(
+    store: &dyn CredentialStore,
+    config: &AuthConfig,
+    login: impl FnOnce(&AuthConfig) -> Result<OAuthToken, AuthError>,
+) -> Result<OAuthToken, AuthError> {
+    let refresh = read_refresh_token(store);
+
+    match refresh {
+        Some(token) => {
+            let token = get_access_token_from_refresh_token(config, store, &token);
+            match token {
+                Ok(token) => return Ok(token),
+                // logging in again won't work without network either
+                Err(e @ AuthError::Network(_)) => return Err(e),
+                // nor would a new login be stored
+                Err(e @ AuthError::Storage(_)) => return Err(e),
+                Err(e) => {
+                    eprintln!("Error refreshing token, trying to relogin. Error: {}", e);
+                }
+            }
+        }
+        None => {}
+    };
+
+    let token = match login(config) {
+        Ok(token) => token,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return Err(e);
+        }
+    };
+
+    write_refresh_token(store, &token.refresh_token)?;
+
+    Ok(token)
+}
+
+/// Forgets the stored login, the next start will ask to log in again.
+pub fn logout(store: &dyn CredentialStore) -> Result<(), AuthError> {
+    Ok(store.clear()?)
+}
\ No newline at end of file
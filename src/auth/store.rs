@@ -0,0 +1,233 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Key, Nonce,
+};
+use argon2::Argon2;
+use rand::RngCore;
+
+use crate::{
+    cli::{Args, CredentialBackend},
+    paths::paths,
+};
+
+#[cfg(test)]
+mod tests;
+
+const KEYRING_SERVICE: &str = "despot";
+
+const FILE_MAGIC: &[u8; 8] = b"DESPOT01";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Files that may contain a machine-unique identifier, in order of preference.
+const MACHINE_ID_FILES: [&str; 2] = ["/etc/machine-id", "/var/lib/dbus/machine-id"];
+
+/// Somewhere to keep the OAuth refresh token between launches.
+pub trait CredentialStore: Send + Sync {
+    /// Returns the stored refresh token, or `None` if nothing was stored yet.
+    fn load(&self) -> Result<Option<String>, StoreError>;
+
+    /// Stores `refresh_token`, replacing any previously stored token.
+    fn save(&self, refresh_token: &str) -> Result<(), StoreError>;
+
+    /// Deletes the stored token. Does nothing if there's none.
+    fn clear(&self) -> Result<(), StoreError>;
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("credential file error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("no machine id found, set a passphrase instead")]
+    NoMachineKey,
+    #[error("failed to derive the credential encryption key")]
+    KeyDerivation,
+    #[error("failed to encrypt the credentials")]
+    Encrypt,
+    #[error("stored credentials could not be decrypted (wrong passphrase or corrupted file)")]
+    Decrypt,
+    #[error("secret service error: {0}")]
+    SecretService(#[from] keyring::Error),
+}
+
+/// Where the key of an [`EncryptedFileStore`] comes from.
+pub enum KeySource {
+    /// A user provided passphrase.
+    Passphrase(String),
+    /// The machine id of this computer. Protects against copying the file elsewhere,
+    /// but not against other users of the same machine if they can read the file.
+    MachineKey,
+}
+
+impl KeySource {
+    fn secret(&self) -> Result<Vec<u8>, StoreError> {
+        match self {
+            KeySource::Passphrase(passphrase) => Ok(passphrase.as_bytes().to_vec()),
+            KeySource::MachineKey => MACHINE_ID_FILES
+                .iter()
+                .find_map(|path| fs::read_to_string(path).ok())
+                .map(|id| id.trim().as_bytes().to_vec())
+                .filter(|id| !id.is_empty())
+                .ok_or(StoreError::NoMachineKey),
+        }
+    }
+
+    fn derive_key(&self, salt: &[u8]) -> Result<Key<Aes256Gcm>, StoreError> {
+        let mut key = Key::<Aes256Gcm>::default();
+        Argon2::default()
+            .hash_password_into(&self.secret()?, salt, &mut key)
+            .map_err(|_| StoreError::KeyDerivation)?;
+        Ok(key)
+    }
+}
+
+/// Stores the token AES-256-GCM encrypted in a file only readable by the current user.
+///
+/// File layout is `magic | salt | nonce | ciphertext`, the key is derived from the
+/// [`KeySource`] with Argon2id.
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    key_source: KeySource,
+}
+
+impl EncryptedFileStore {
+    pub fn new(path: impl Into<PathBuf>, key_source: KeySource) -> Self {
+        Self {
+            path: path.into(),
+            key_source,
+        }
+    }
+}
+
+impl CredentialStore for EncryptedFileStore {
+    fn load(&self) -> Result<Option<String>, StoreError> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let header_len = FILE_MAGIC.len() + SALT_LEN + NONCE_LEN;
+        if data.len() < header_len || !data.starts_with(FILE_MAGIC) {
+            return Err(StoreError::Decrypt);
+        }
+        let (salt, rest) = data[FILE_MAGIC.len()..].split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+        let cipher = Aes256Gcm::new(&self.key_source.derive_key(salt)?);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| StoreError::Decrypt)?;
+        String::from_utf8(plaintext)
+            .map(Some)
+            .map_err(|_| StoreError::Decrypt)
+    }
+
+    fn save(&self, refresh_token: &str) -> Result<(), StoreError> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let cipher = Aes256Gcm::new(&self.key_source.derive_key(&salt)?);
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), refresh_token.as_bytes())
+            .map_err(|_| StoreError::Encrypt)?;
+
+        let mut data = Vec::with_capacity(FILE_MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+        data.extend_from_slice(FILE_MAGIC);
+        data.extend_from_slice(&salt);
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+
+        write_private(&self.path, &data)?;
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), StoreError> {
+        match fs::remove_file(&self.path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Writes `data` to `path` atomically, with the file readable only by the current user.
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&tmp_path)?;
+    // `mode` only applies to newly created files, tighten a leftover temp file too
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(tmp_path, path)
+}
+
+/// Stores the token in the desktop keyring through the freedesktop Secret Service API.
+pub struct SecretServiceStore {
+    entry: keyring::Entry,
+}
+
+impl SecretServiceStore {
+    pub fn new(account: &str) -> Result<Self, StoreError> {
+        Ok(Self {
+            entry: keyring::Entry::new(KEYRING_SERVICE, account)?,
+        })
+    }
+}
+
+impl CredentialStore for SecretServiceStore {
+    fn load(&self) -> Result<Option<String>, StoreError> {
+        match self.entry.get_password() {
+            Ok(token) => Ok(Some(token)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, refresh_token: &str) -> Result<(), StoreError> {
+        Ok(self.entry.set_password(refresh_token)?)
+    }
+
+    fn clear(&self) -> Result<(), StoreError> {
+        match self.entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Opens the credential store of `profile`, using the backend selected on the command line.
+pub fn open_store(args: &Args, profile: &str) -> Result<Arc<dyn CredentialStore>, StoreError> {
+    Ok(match args.credential_store {
+        CredentialBackend::EncryptedFile => {
+            let key_source = match &args.passphrase {
+                Some(passphrase) => KeySource::Passphrase(passphrase.clone()),
+                None => KeySource::MachineKey,
+            };
+            Arc::new(EncryptedFileStore::new(paths().credentials_file(profile), key_source))
+        }
+        CredentialBackend::SecretService => Arc::new(SecretServiceStore::new(profile)?),
+    })
+}
@@ -0,0 +1,105 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use super::{CredentialStore, EncryptedFileStore, KeySource, StoreError, FILE_MAGIC};
+
+const TOKEN: &str = "AQD-refresh-token";
+
+/// A credentials file path of its own in the system's temp dir, removed when dropped.
+struct TempPath(PathBuf);
+
+impl TempPath {
+    fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "despot-store-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        );
+        Self(std::env::temp_dir().join(name).join("credentials.bin"))
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        if let Some(dir) = self.0.parent() {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+fn store(path: &TempPath, passphrase: &str) -> EncryptedFileStore {
+    EncryptedFileStore::new(&path.0, KeySource::Passphrase(passphrase.to_string()))
+}
+
+#[test]
+fn round_trip() {
+    let path = TempPath::new();
+    let store = store(&path, "passphrase");
+    assert!(store.load().unwrap().is_none());
+
+    store.save(TOKEN).unwrap();
+    assert_eq!(store.load().unwrap().as_deref(), Some(TOKEN));
+    let data = fs::read(&path.0).unwrap();
+    assert!(data.starts_with(FILE_MAGIC));
+    assert!(!data
+        .windows(TOKEN.len())
+        .any(|window| window == TOKEN.as_bytes()));
+
+    store.save("rotated").unwrap();
+    assert_eq!(store.load().unwrap().as_deref(), Some("rotated"));
+    store.clear().unwrap();
+    assert!(store.load().unwrap().is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn file_is_private() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = TempPath::new();
+    store(&path, "passphrase").save(TOKEN).unwrap();
+    let mode = fs::metadata(&path.0).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[test]
+fn wrong_passphrase() {
+    let path = TempPath::new();
+    store(&path, "passphrase").save(TOKEN).unwrap();
+    assert!(matches!(
+        store(&path, "other passphrase").load(),
+        Err(StoreError::Decrypt)
+    ));
+}
+
+#[test]
+fn truncated_file() {
+    let path = TempPath::new();
+    let store = store(&path, "passphrase");
+    store.save(TOKEN).unwrap();
+    let data = fs::read(&path.0).unwrap();
+
+    // cut into the ciphertext, then into the header
+    for len in [data.len() - 1, FILE_MAGIC.len() + 4, 0] {
+        fs::write(&path.0, &data[..len]).unwrap();
+        assert!(
+            matches!(store.load(), Err(StoreError::Decrypt)),
+            "{len} bytes"
+        );
+    }
+}
+
+#[test]
+fn not_a_credentials_file() {
+    let path = TempPath::new();
+    let store = store(&path, "passphrase");
+    store.save(TOKEN).unwrap();
+    let mut data = fs::read(&path.0).unwrap();
+    data[0] ^= 0xff;
+    fs::write(&path.0, data).unwrap();
+    assert!(matches!(store.load(), Err(StoreError::Decrypt)));
+}
@@ -0,0 +1,315 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use librespot_oauth::OAuthToken;
+
+use super::{
+    get_access_token_from_refresh_token, get_token_with_login,
+    store::{CredentialStore, StoreError},
+    AuthConfig, AuthError,
+};
+use crate::cli::LoginMode;
+
+const SCOPES: [&str; 2] = ["streaming", "user-library-read"];
+
+/// In-process stand-in for Spotify's token endpoint, answering each request with the next
+/// canned `(status, body)` response.
+struct MockTokenServer {
+    url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+    thread: JoinHandle<()>,
+}
+
+impl MockTokenServer {
+    fn start(responses: Vec<(u16, &'static str)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api/token", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let thread = thread::spawn({
+            let requests = requests.clone();
+            move || {
+                for (status, body) in responses {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream);
+                    let mut content_length = 0;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        let line = line.trim_end();
+                        if line.is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    let mut request_body = vec![0; content_length];
+                    reader.read_exact(&mut request_body).unwrap();
+                    requests
+                        .lock()
+                        .unwrap()
+                        .push(String::from_utf8(request_body).unwrap());
+
+                    let reason = if status == 200 { "OK" } else { "Bad Request" };
+                    let response = format!(
+                        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    reader.get_mut().write_all(response.as_bytes()).unwrap();
+                }
+            }
+        });
+        Self {
+            url,
+            requests,
+            thread,
+        }
+    }
+
+    /// Waits for all responses to be served and returns the received request bodies.
+    fn finish(self) -> Vec<String> {
+        self.thread.join().unwrap();
+        Arc::try_unwrap(self.requests).unwrap().into_inner().unwrap()
+    }
+}
+
+#[derive(Default)]
+struct MemoryStore(Mutex<Option<String>>);
+
+impl MemoryStore {
+    fn with_token(token: &str) -> Self {
+        Self(Mutex::new(Some(token.to_string())))
+    }
+
+    fn token(&self) -> Option<String> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl CredentialStore for MemoryStore {
+    fn load(&self) -> Result<Option<String>, StoreError> {
+        Ok(self.token())
+    }
+
+    fn save(&self, refresh_token: &str) -> Result<(), StoreError> {
+        *self.0.lock().unwrap() = Some(refresh_token.to_string());
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), StoreError> {
+        *self.0.lock().unwrap() = None;
+        Ok(())
+    }
+}
+
+/// Holds a token but fails to store a new one, like a credential file that became read-only.
+struct ReadOnlyStore(Option<String>);
+
+impl CredentialStore for ReadOnlyStore {
+    fn load(&self) -> Result<Option<String>, StoreError> {
+        Ok(self.0.clone())
+    }
+
+    fn save(&self, _: &str) -> Result<(), StoreError> {
+        Err(std::io::Error::from(std::io::ErrorKind::PermissionDenied).into())
+    }
+
+    fn clear(&self) -> Result<(), StoreError> {
+        Ok(())
+    }
+}
+
+fn config(token_url: &str) -> AuthConfig {
+    AuthConfig {
+        authorize_url: "http://127.0.0.1/authorize".to_string(),
+        token_url: token_url.to_string(),
+        client_id: "test-client".to_string(),
+        redirect_uri: "http://127.0.0.1:8898/login".to_string(),
+        scopes: SCOPES.map(str::to_string).to_vec(),
+        login_mode: LoginMode::Headless,
+    }
+}
+
+fn fresh_login(refresh_token: &str) -> OAuthToken {
+    OAuthToken {
+        access_token: "login-access".to_string(),
+        refresh_token: refresh_token.to_string(),
+        expires_at: Instant::now() + Duration::from_secs(3600),
+        token_type: "Bearer".to_string(),
+        scopes: SCOPES.map(str::to_string).to_vec(),
+    }
+}
+
+fn no_login(_: &AuthConfig) -> Result<OAuthToken, AuthError> {
+    panic!("unexpected login")
+}
+
+#[test]
+fn refresh_rotates_and_persists_refresh_token() {
+    let server = MockTokenServer::start(vec![(
+        200,
+        r#"{"access_token":"access-1","token_type":"Bearer","expires_in":3600,"refresh_token":"rotated","scope":"streaming user-library-read"}"#,
+    )]);
+    let store = MemoryStore::with_token("original");
+
+    let token = get_token_with_login(&store, &config(&server.url), no_login).unwrap();
+
+    assert_eq!(token.access_token, "access-1");
+    assert_eq!(token.refresh_token, "rotated");
+    assert_eq!(store.token().as_deref(), Some("rotated"));
+    let requests = server.finish();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].contains("grant_type=refresh_token"));
+    assert!(requests[0].contains("refresh_token=original"));
+}
+
+#[test]
+fn rotated_token_is_used_for_the_next_refresh() {
+    let server = MockTokenServer::start(vec![
+        (
+            200,
+            r#"{"access_token":"access-1","token_type":"Bearer","expires_in":3600,"refresh_token":"rotated"}"#,
+        ),
+        (
+            200,
+            r#"{"access_token":"access-2","token_type":"Bearer","expires_in":3600,"refresh_token":"rotated-again"}"#,
+        ),
+    ]);
+    let store = MemoryStore::with_token("original");
+    let config = config(&server.url);
+
+    get_token_with_login(&store, &config, no_login).unwrap();
+    let token = get_token_with_login(&store, &config, no_login).unwrap();
+
+    assert_eq!(token.access_token, "access-2");
+    assert_eq!(store.token().as_deref(), Some("rotated-again"));
+    let requests = server.finish();
+    assert!(requests[1].contains("refresh_token=rotated"));
+}
+
+#[test]
+fn missing_refresh_token_keeps_the_previous_one() {
+    let server = MockTokenServer::start(vec![(
+        200,
+        r#"{"access_token":"access-1","token_type":"Bearer","expires_in":3600}"#,
+    )]);
+    let store = MemoryStore::with_token("original");
+
+    let token =
+        get_access_token_from_refresh_token(&config(&server.url), &store, "original").unwrap();
+
+    assert_eq!(token.refresh_token, "original");
+    assert_eq!(store.token().as_deref(), Some("original"));
+    server.finish();
+}
+
+#[test]
+fn revoked_grant_falls_back_to_login() {
+    let server = MockTokenServer::start(vec![(
+        400,
+        r#"{"error":"invalid_grant","error_description":"Refresh token revoked"}"#,
+    )]);
+    let store = MemoryStore::with_token("revoked");
+
+    let token = get_token_with_login(&store, &config(&server.url), |_| Ok(fresh_login("fresh")))
+        .unwrap();
+
+    assert_eq!(token.access_token, "login-access");
+    assert_eq!(store.token().as_deref(), Some("fresh"));
+    server.finish();
+}
+
+#[test]
+fn revoked_grant_is_reported_without_the_token() {
+    let server = MockTokenServer::start(vec![(
+        400,
+        r#"{"error":"invalid_grant","error_description":"Refresh token revoked"}"#,
+    )]);
+    let store = MemoryStore::default();
+
+    let error =
+        get_access_token_from_refresh_token(&config(&server.url), &store, "secret-refresh")
+            .unwrap_err();
+
+    assert!(matches!(error, AuthError::InvalidGrant));
+    assert!(!error.to_string().contains("secret-refresh"));
+    assert!(!format!("{error:?}").contains("secret-refresh"));
+    server.finish();
+}
+
+#[test]
+fn unparsable_response_is_reported_without_its_body() {
+    let server = MockTokenServer::start(vec![(200, r#"{"access_token":"secret-access"}"#)]);
+    let store = MemoryStore::default();
+
+    let error = get_access_token_from_refresh_token(&config(&server.url), &store, "refresh")
+        .unwrap_err();
+
+    assert!(matches!(error, AuthError::Rejected(_)));
+    assert!(!format!("{error} {error:?}").contains("secret-access"));
+    assert_eq!(store.token(), None);
+    server.finish();
+}
+
+#[test]
+fn missing_scope_falls_back_to_login() {
+    let server = MockTokenServer::start(vec![(
+        200,
+        r#"{"access_token":"access-1","token_type":"Bearer","expires_in":3600,"refresh_token":"rotated","scope":"streaming"}"#,
+    )]);
+    let store = MemoryStore::with_token("original");
+
+    let token = get_token_with_login(&store, &config(&server.url), |_| Ok(fresh_login("fresh")))
+        .unwrap();
+
+    assert_eq!(token.access_token, "login-access");
+    assert_eq!(store.token().as_deref(), Some("fresh"));
+    server.finish();
+}
+
+#[test]
+fn network_error_does_not_start_a_login() {
+    // nothing listens on the port once the listener is dropped
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/api/token", listener.local_addr().unwrap());
+    drop(listener);
+    let store = MemoryStore::with_token("original");
+
+    let error = get_token_with_login(&store, &config(&url), no_login).unwrap_err();
+
+    assert!(matches!(error, AuthError::Network(_)));
+    assert_eq!(store.token().as_deref(), Some("original"));
+}
+
+#[test]
+fn failed_save_after_refresh_is_reported_without_a_login() {
+    let server = MockTokenServer::start(vec![(
+        200,
+        r#"{"access_token":"access-1","token_type":"Bearer","expires_in":3600,"refresh_token":"rotated","scope":"streaming user-library-read"}"#,
+    )]);
+    let store = ReadOnlyStore(Some("original".to_string()));
+
+    let error = get_token_with_login(&store, &config(&server.url), no_login).unwrap_err();
+
+    assert!(matches!(error, AuthError::Storage(_)));
+    server.finish();
+}
+
+#[test]
+fn failed_save_after_login_is_reported() {
+    let store = ReadOnlyStore(None);
+
+    let error = get_token_with_login(&store, &config("http://127.0.0.1/api/token"), |_| {
+        Ok(fresh_login("fresh"))
+    })
+    .unwrap_err();
+
+    assert!(matches!(error, AuthError::Storage(_)));
+}
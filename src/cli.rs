@@ -1,7 +1,111 @@
-use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::profile;
 
 /// Simple program to greet a person
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(version, about, long_about = None)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Profile to start with. Each profile has its own account, caches and device name.
+    /// Only ASCII letters, digits, `_` and `-` are allowed.
+    #[arg(long, default_value = "default", value_parser = parse_profile)]
+    pub profile: String,
+
+    /// Where to keep the Spotify refresh token
+    #[arg(long, value_enum, default_value_t)]
+    pub credential_store: CredentialBackend,
+
+    /// Passphrase for the encrypted credential file. The machine id is used when unset.
+    #[arg(long, env = "DESPOT_PASSPHRASE", hide_env_values = true)]
+    pub passphrase: Option<String>,
+
+    /// How to log in when there's no stored session
+    #[arg(long, value_enum, default_value_t)]
+    pub login_mode: LoginMode,
+
+    /// Host of the OAuth redirect URI. Must match the redirect URIs registered for the client.
+    #[arg(long, default_value = "127.0.0.1")]
+    pub redirect_host: String,
+
+    /// Port of the OAuth redirect URI, the browser login listens on it
+    #[arg(long, default_value_t = 8898)]
+    pub redirect_port: u16,
+
+    /// Full OAuth redirect URI, overrides --redirect-host and --redirect-port
+    #[arg(long, env = "DESPOT_REDIRECT_URI")]
+    pub redirect_uri: Option<String>,
+
+    /// Client ID of your own Spotify app [default: the client ID used by librespot]
+    #[arg(long, env = "DESPOT_CLIENT_ID")]
+    pub client_id: Option<String>,
+
+    /// OAuth scopes to request, comma separated [default: all scopes despot uses]
+    #[arg(long = "scopes", env = "DESPOT_SCOPES", value_delimiter = ',')]
+    pub scopes: Option<Vec<String>>,
+
+    /// Attempts per Web API request before giving up, including the first one
+    #[arg(long, default_value_t = 4)]
+    pub api_max_attempts: u32,
+
+    /// Backoff before retrying a failed Web API request in milliseconds, doubled for each
+    /// further retry
+    #[arg(long, default_value_t = 500)]
+    pub api_initial_backoff_ms: u64,
+
+    /// Upper bound for the backoff between Web API retries in milliseconds
+    #[arg(long, default_value_t = 10_000)]
+    pub api_max_backoff_ms: u64,
+
+    /// Give up on a Web API request after this many seconds, retries included
+    #[arg(long, default_value_t = 30)]
+    pub api_retry_deadline: u64,
+
+    /// Configuration directory [default: $XDG_CONFIG_HOME/despot]
+    #[arg(long)]
+    pub config_dir: Option<PathBuf>,
+
+    /// Cache directory [default: $XDG_CACHE_HOME/despot]
+    #[arg(long)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// State directory, holds the stored credentials [default: $XDG_STATE_HOME/despot]
+    #[arg(long)]
+    pub state_dir: Option<PathBuf>,
+}
+
+fn parse_profile(name: &str) -> Result<String, String> {
+    if profile::is_valid_name(name) {
+        Ok(name.to_string())
+    } else {
+        Err("only ASCII letters, digits, '_' and '-' are allowed".to_string())
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Delete the stored credentials of the profile and exit
+    Logout,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum CredentialBackend {
+    /// An encrypted file only readable by the current user
+    #[default]
+    EncryptedFile,
+    /// The desktop keyring, through the freedesktop Secret Service API
+    SecretService,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum LoginMode {
+    /// Open a browser on this machine and catch the redirect with a local server
+    #[default]
+    Browser,
+    /// Print the login URL and paste the redirect URL back, for machines without a browser
+    Headless,
 }
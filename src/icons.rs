@@ -87,3 +87,12 @@ pub const EXPLICIT: &str = "\u{e01e}";
 pub const ALBUM: &str = "\u{e019}";
 pub const LYRICS: &str = "\u{ec0b}";
 pub const MUSIC_CAST: &str = "\u{eb1a}";
+pub const FAVORITE: &str = "\u{e87d}";
+pub const ADD: &str = "\u{e145}";
+pub const EDIT: &str = "\u{e3c9}";
+pub const DELETE: &str = "\u{e872}";
+pub const DRAG_INDICATOR: &str = "\u{e945}";
+pub const ADD_TO_QUEUE: &str = "\u{e05c}";
+pub const QUEUE_PLAY_NEXT: &str = "\u{e066}";
+pub const CHECK_CIRCLE: &str = "\u{e86c}";
+pub const RADIO: &str = "\u{e03e}";
@@ -0,0 +1,354 @@
+use std::{collections::HashMap, fs, io, path::Path, sync::Mutex};
+
+use rspotify::model::{FullTrack, PlaylistItem, SavedTrack, SimplifiedPlaylist};
+use rspotify::prelude::*;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+#[cfg(test)]
+mod tests;
+
+/// Statements creating each schema version from the previous one. The schema version stored
+/// in the database is the number of migrations applied to it.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE artists (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE albums (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE tracks (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        album_id TEXT,
+        duration_ms INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE track_artists (
+        track_id TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        artist_id TEXT NOT NULL,
+        PRIMARY KEY (track_id, position)
+    );
+    CREATE INDEX track_artists_artist ON track_artists (artist_id);
+    CREATE TABLE playlists (
+        id TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        owner_id TEXT NOT NULL,
+        snapshot_id TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE saved_tracks (
+        position INTEGER PRIMARY KEY,
+        track_id TEXT NOT NULL,
+        added_at TEXT NOT NULL
+    );
+    CREATE INDEX saved_tracks_track ON saved_tracks (track_id);
+",
+    "
+    CREATE TABLE playlist_items (
+        playlist_id TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (playlist_id, position)
+    );
+    CREATE TABLE playlist_snapshots (
+        playlist_id TEXT PRIMARY KEY,
+        snapshot_id TEXT NOT NULL
+    );
+",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum DbError {
+    #[error("failed to create the library directory: {0}")]
+    Io(#[from] io::Error),
+    #[error("database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("invalid stored data: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// SQLite copy of a profile's library, so it can be shown before anything was fetched.
+pub struct LibraryDb {
+    conn: Mutex<Connection>,
+}
+
+impl LibraryDb {
+    pub fn open(path: &Path) -> Result<Self, DbError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Self::new(Connection::open(path)?)
+    }
+
+    /// A database that's gone once dropped, for when the file can't be opened.
+    pub fn open_in_memory() -> Result<Self, DbError> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(mut conn: Connection) -> Result<Self, DbError> {
+        migrate(&mut conn)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// The user's playlists, in the order Spotify lists them.
+    pub fn playlists(&self) -> Result<Vec<SimplifiedPlaylist>, DbError> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare_cached("SELECT data FROM playlists ORDER BY position")?;
+        let rows = statement.query_map([], |row| row.get::<_, String>(0))?;
+        let mut playlists = Vec::new();
+        for data in rows {
+            playlists.push(serde_json::from_str(&data?)?);
+        }
+        Ok(playlists)
+    }
+
+    pub fn replace_playlists(&self, playlists: &[SimplifiedPlaylist]) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM playlists", [])?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT OR REPLACE INTO playlists (id, position, name, owner_id, snapshot_id, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for (position, playlist) in playlists.iter().enumerate() {
+                insert.execute(params![
+                    playlist.id.uri(),
+                    position,
+                    playlist.name,
+                    playlist.owner.id.uri(),
+                    playlist.snapshot_id,
+                    serde_json::to_string(playlist)?,
+                ])?;
+            }
+        }
+        tx.execute_batch(
+            "DELETE FROM playlist_items WHERE playlist_id NOT IN (SELECT id FROM playlists);
+             DELETE FROM playlist_snapshots WHERE playlist_id NOT IN (SELECT id FROM playlists);",
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Snapshot ids of the playlists whose items are stored, by playlist URI.
+    pub fn playlist_snapshots(&self) -> Result<HashMap<String, String>, DbError> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement =
+            conn.prepare_cached("SELECT playlist_id, snapshot_id FROM playlist_snapshots")?;
+        let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut snapshots = HashMap::new();
+        for row in rows {
+            let (playlist_id, snapshot_id) = row?;
+            snapshots.insert(playlist_id, snapshot_id);
+        }
+        Ok(snapshots)
+    }
+
+    /// Stored items of the playlist `playlist_id` and the snapshot id they're from, `None` if
+    /// they were never stored.
+    pub fn playlist_items(
+        &self,
+        playlist_id: &str,
+    ) -> Result<Option<(String, Vec<PlaylistItem>)>, DbError> {
+        let conn = self.conn.lock().unwrap();
+        let snapshot_id = conn
+            .prepare_cached("SELECT snapshot_id FROM playlist_snapshots WHERE playlist_id = ?1")?
+            .query_row([playlist_id], |row| row.get(0))
+            .optional()?;
+        let Some(snapshot_id) = snapshot_id else {
+            return Ok(None);
+        };
+        let mut statement = conn.prepare_cached(
+            "SELECT data FROM playlist_items WHERE playlist_id = ?1 ORDER BY position",
+        )?;
+        let rows = statement.query_map([playlist_id], |row| row.get::<_, String>(0))?;
+        let mut items = Vec::new();
+        for data in rows {
+            items.push(serde_json::from_str(&data?)?);
+        }
+        Ok(Some((snapshot_id, items)))
+    }
+
+    /// Stores the items of the playlist `playlist_id` as of `snapshot_id`.
+    pub fn replace_playlist_items(
+        &self,
+        playlist_id: &str,
+        snapshot_id: &str,
+        items: &[PlaylistItem],
+    ) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM playlist_items WHERE playlist_id = ?1",
+            [playlist_id],
+        )?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO playlist_items (playlist_id, position, data) VALUES (?1, ?2, ?3)",
+            )?;
+            for (position, item) in items.iter().enumerate() {
+                insert.execute(params![playlist_id, position, serde_json::to_string(item)?])?;
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO playlist_snapshots (playlist_id, snapshot_id) VALUES (?1, ?2)",
+            [playlist_id, snapshot_id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Liked songs, most recently added first.
+    pub fn saved_tracks(&self) -> Result<Vec<SavedTrack>, DbError> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare_cached(
+            "SELECT saved_tracks.added_at, tracks.data FROM saved_tracks
+             JOIN tracks ON tracks.id = saved_tracks.track_id
+             ORDER BY saved_tracks.position",
+        )?;
+        let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))?;
+        let mut saved_tracks = Vec::new();
+        for row in rows {
+            let (added_at, track) = row?;
+            saved_tracks.push(SavedTrack {
+                added_at,
+                track: serde_json::from_str(&track)?,
+            });
+        }
+        Ok(saved_tracks)
+    }
+
+    pub fn replace_saved_tracks(&self, saved_tracks: &[SavedTrack]) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM saved_tracks", [])?;
+        for (position, saved) in saved_tracks.iter().enumerate() {
+            let track_id = upsert_track(&tx, &saved.track)?;
+            tx.prepare_cached(
+                "INSERT INTO saved_tracks (position, track_id, added_at) VALUES (?1, ?2, ?3)",
+            )?
+            .execute(params![position, track_id, saved.added_at])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Removes the tracks no liked song refers to anymore, then the albums and artists none
+    /// of the remaining tracks refer to.
+    pub fn remove_orphans(&self) -> Result<(), DbError> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute_batch(
+            "DELETE FROM tracks WHERE id NOT IN (SELECT track_id FROM saved_tracks);
+             DELETE FROM track_artists WHERE track_id NOT IN (SELECT id FROM tracks);
+             DELETE FROM albums WHERE id NOT IN
+                (SELECT album_id FROM tracks WHERE album_id IS NOT NULL);
+             DELETE FROM artists WHERE id NOT IN (SELECT artist_id FROM track_artists);",
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// Key of a track in the database. Local files have no id, so they're told apart by what
+/// Spotify knows about them, like their local uris do.
+pub fn track_key(track: &FullTrack) -> String {
+    match &track.id {
+        Some(id) => id.uri(),
+        None => {
+            let artists: Vec<_> = track.artists.iter().map(|artist| &artist.name).collect();
+            let fields = serde_json::json!([
+                artists,
+                track.album.name,
+                track.name,
+                track.duration.num_milliseconds(),
+            ]);
+            format!("local:{fields}")
+        }
+    }
+}
+
+/// Stores `track` together with its album and artists, returns its key.
+fn upsert_track(tx: &Transaction, track: &FullTrack) -> Result<String, DbError> {
+    let key = track_key(track);
+    let album_id = track.album.id.as_ref().map(|id| id.uri());
+    if let Some(album_id) = &album_id {
+        tx.prepare_cached("INSERT OR REPLACE INTO albums (id, name, data) VALUES (?1, ?2, ?3)")?
+            .execute(params![
+                album_id,
+                track.album.name,
+                serde_json::to_string(&track.album)?
+            ])?;
+    }
+    tx.prepare_cached(
+        "INSERT OR REPLACE INTO tracks (id, name, album_id, duration_ms, data)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?
+    .execute(params![
+        key,
+        track.name,
+        album_id,
+        track.duration.num_milliseconds(),
+        serde_json::to_string(track)?,
+    ])?;
+
+    tx.prepare_cached("DELETE FROM track_artists WHERE track_id = ?1")?
+        .execute([&key])?;
+    for (position, artist) in track.artists.iter().enumerate() {
+        let Some(artist_id) = artist.id.as_ref().map(|id| id.uri()) else {
+            continue;
+        };
+        tx.prepare_cached("INSERT OR REPLACE INTO artists (id, name, data) VALUES (?1, ?2, ?3)")?
+            .execute(params![
+                artist_id,
+                artist.name,
+                serde_json::to_string(artist)?
+            ])?;
+        tx.prepare_cached(
+            "INSERT INTO track_artists (track_id, position, artist_id) VALUES (?1, ?2, ?3)",
+        )?
+        .execute(params![key, position, artist_id])?;
+    }
+    Ok(key)
+}
+
+/// Brings the schema up to date. Databases written by a newer version are only a cache, so
+/// they're wiped and recreated instead of failing.
+fn migrate(conn: &mut Connection) -> Result<(), DbError> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    let tx = conn.transaction()?;
+    let version = if version > MIGRATIONS.len() {
+        eprintln!(
+            "Library database has unknown schema version {}, recreating it",
+            version
+        );
+        tx.execute_batch(
+            "DROP TABLE IF EXISTS artists;
+             DROP TABLE IF EXISTS albums;
+             DROP TABLE IF EXISTS tracks;
+             DROP TABLE IF EXISTS track_artists;
+             DROP TABLE IF EXISTS playlists;
+             DROP TABLE IF EXISTS saved_tracks;
+             DROP TABLE IF EXISTS playlist_items;
+             DROP TABLE IF EXISTS playlist_snapshots;",
+        )?;
+        0
+    } else {
+        version
+    };
+    for migration in &MIGRATIONS[version..] {
+        tx.execute_batch(migration)?;
+    }
+    tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+    tx.commit()?;
+    Ok(())
+}
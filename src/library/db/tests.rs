@@ -0,0 +1,93 @@
+use rspotify::model::{FullTrack, SavedTrack};
+use serde_json::json;
+
+use super::LibraryDb;
+
+fn saved(id: &str, album_id: &str, artist_id: &str) -> SavedTrack {
+    let artist = json!({
+        "external_urls": {},
+        "href": null,
+        "id": artist_id,
+        "name": artist_id,
+    });
+    let track: FullTrack = serde_json::from_value(json!({
+        "album": {
+            "artists": [artist],
+            "available_markets": [],
+            "external_urls": {},
+            "href": null,
+            "id": album_id,
+            "images": [],
+            "name": album_id,
+        },
+        "artists": [artist],
+        "available_markets": [],
+        "disc_number": 1,
+        "duration_ms": 180_000,
+        "explicit": false,
+        "external_ids": {},
+        "external_urls": {},
+        "href": null,
+        "id": id,
+        "is_local": false,
+        "name": id,
+        "popularity": 0,
+        "preview_url": null,
+        "track_number": 1,
+    }))
+    .unwrap();
+    SavedTrack {
+        added_at: Default::default(),
+        track,
+    }
+}
+
+fn count(db: &LibraryDb, table: &str) -> usize {
+    let conn = db.conn.lock().unwrap();
+    conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+        row.get(0)
+    })
+    .unwrap()
+}
+
+#[test]
+fn orphans_of_unliked_songs_are_removed() {
+    let db = LibraryDb::open_in_memory().unwrap();
+    let kept = saved(
+        "4iV5W9uYEdYUVa79Axb7Rh",
+        "1DFixLWuPkv3KT3TnV35m3",
+        "0OdUWJ0sBjDrqHygGUXeCF",
+    );
+    let unliked = saved(
+        "1301WleyT98MSxVHPZCA6M",
+        "6akEvsycLGftJxYudPjmqK",
+        "08td7MxkoHQkXnWAYD8d6Q",
+    );
+    db.replace_saved_tracks(&[kept.clone(), unliked]).unwrap();
+    db.replace_saved_tracks(&[kept]).unwrap();
+
+    db.remove_orphans().unwrap();
+
+    assert_eq!(count(&db, "tracks"), 1);
+    assert_eq!(count(&db, "track_artists"), 1);
+    assert_eq!(count(&db, "albums"), 1);
+    assert_eq!(count(&db, "artists"), 1);
+    assert_eq!(db.saved_tracks().unwrap().len(), 1);
+}
+
+#[test]
+fn albums_and_artists_of_remaining_tracks_are_kept() {
+    let db = LibraryDb::open_in_memory().unwrap();
+    let album = "1DFixLWuPkv3KT3TnV35m3";
+    let artist = "0OdUWJ0sBjDrqHygGUXeCF";
+    let kept = saved("4iV5W9uYEdYUVa79Axb7Rh", album, artist);
+    let unliked = saved("1301WleyT98MSxVHPZCA6M", album, artist);
+    db.replace_saved_tracks(&[kept.clone(), unliked]).unwrap();
+    db.replace_saved_tracks(&[kept]).unwrap();
+
+    db.remove_orphans().unwrap();
+
+    assert_eq!(count(&db, "tracks"), 1);
+    assert_eq!(count(&db, "albums"), 1);
+    assert_eq!(count(&db, "artists"), 1);
+}
@@ -0,0 +1,340 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use cushy::value::{Destination, Dynamic, Source};
+use futures_util::{lock::Mutex, TryStreamExt};
+use rspotify::model::{
+    FullPlaylist, PlayableId, PlayableItem, PlaylistItem, PlaylistTracksRef, SimplifiedPlaylist,
+};
+use rspotify::prelude::*;
+
+use super::Library;
+use crate::api::{ApiError, SpotifyContextRef};
+
+#[cfg(test)]
+mod tests;
+
+/// The most items added by a single request.
+const ADD_BATCH: usize = 100;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EditError {
+    #[error(transparent)]
+    Api(#[from] ApiError),
+    /// The playlist was changed somewhere else since its items were loaded. They have been
+    /// reloaded, so the change can be made again on the current version.
+    #[error("the playlist was changed somewhere else and has been reloaded, try again")]
+    Conflict,
+}
+
+/// Creates an empty playlist owned by the current user and adds it to the library.
+pub async fn create_playlist(
+    context: &SpotifyContextRef,
+    library: &Library,
+    name: &str,
+) -> Result<SimplifiedPlaylist, ApiError> {
+    let user = context.current_user().await?;
+    let created = context.create_playlist(user.id, name, None, false).await?;
+    let playlist = simplified(&created);
+    library.update_playlist(playlist.clone()).await;
+    library.store_playlist_items(&playlist, Vec::new()).await;
+    Ok(playlist)
+}
+
+/// Changes a playlist, keeping the shown items and the library in step with it.
+///
+/// Every change first makes sure the playlist is still at the snapshot its items were loaded
+/// from, reloading them with [`EditError::Conflict`] otherwise, and sends that snapshot id
+/// along where the endpoint takes one. Changes are made one at a time.
+#[derive(Clone)]
+pub struct PlaylistEditor {
+    context: SpotifyContextRef,
+    library: Library,
+    /// Its snapshot id is the version of `items`.
+    pub playlist: Dynamic<SimplifiedPlaylist>,
+    /// All items of the playlist, in order.
+    pub items: Dynamic<Vec<PlaylistItem>>,
+    busy: Arc<Mutex<()>>,
+}
+
+impl PlaylistEditor {
+    /// Edits `playlist`, whose `items` have to be loaded completely.
+    pub fn new(
+        context: SpotifyContextRef,
+        library: Library,
+        playlist: Dynamic<SimplifiedPlaylist>,
+        items: Dynamic<Vec<PlaylistItem>>,
+    ) -> Self {
+        Self {
+            context,
+            library,
+            playlist,
+            items,
+            busy: Default::default(),
+        }
+    }
+
+    /// Renames the playlist and changes its description.
+    pub async fn change_details(&self, name: &str, description: &str) -> Result<(), EditError> {
+        let _busy = self.busy.lock().await;
+        self.check_snapshot().await?;
+        let id = self.playlist.map_ref(|playlist| playlist.id.clone());
+        self.context
+            .change_playlist_details(id.clone(), Some(name), Some(description))
+            .await?;
+        self.playlist
+            .map_mut(|mut playlist| playlist.name = name.to_string());
+        // the rename moved the playlist to a snapshot the endpoint doesn't tell
+        let snapshot_id = match self.context.playlist(id).await {
+            Ok(renamed) => renamed.snapshot_id,
+            Err(e) => {
+                // still at the old snapshot, so the next change reloads the playlist
+                self.library.update_playlist(self.playlist.get()).await;
+                return Err(e.into());
+            }
+        };
+        self.applied(snapshot_id, |_| {}).await;
+        Ok(())
+    }
+
+    /// Positions of the items that are `id`, to warn before adding it again.
+    pub fn positions_of(&self, id: &PlayableId<'_>) -> Vec<usize> {
+        let uri = id.uri();
+        self.items.map_ref(|items| {
+            items
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| {
+                    item.track.as_ref().and_then(playable_uri).as_deref() == Some(uri.as_str())
+                })
+                .map(|(position, _)| position)
+                .collect()
+        })
+    }
+
+    /// Adds `tracks` at the end, whether they're in the playlist already or not. Local files
+    /// can't be added. If a batch fails, the ones added before it are still kept.
+    pub async fn add(&self, tracks: Vec<PlayableItem>) -> Result<(), EditError> {
+        let _busy = self.busy.lock().await;
+        let mut snapshot_id = self.check_snapshot().await?;
+        let id = self.playlist.map_ref(|playlist| playlist.id.clone());
+        let (ids, tracks): (Vec<_>, Vec<_>) = tracks
+            .into_iter()
+            .filter_map(|track| Some((playable_id(&track)?, track)))
+            .unzip();
+        let mut added = 0;
+        let mut result = Ok(());
+        for chunk in ids.chunks(ADD_BATCH) {
+            match self
+                .context
+                .add_playlist_items(id.clone(), chunk, None)
+                .await
+            {
+                Ok(added_snapshot_id) => {
+                    snapshot_id = added_snapshot_id;
+                    added += chunk.len();
+                }
+                Err(e) => {
+                    result = Err(e.into());
+                    break;
+                }
+            }
+        }
+        if added > 0 {
+            let added_at = Utc::now();
+            self.applied(snapshot_id, move |items| {
+                items.extend(tracks.into_iter().take(added).map(|track| PlaylistItem {
+                    added_at: Some(added_at),
+                    added_by: None,
+                    is_local: false,
+                    track: Some(track),
+                }));
+            })
+            .await;
+        }
+        result
+    }
+
+    /// Removes the item at `position`, leaving other occurrences of it. Spotify removes all
+    /// of them, so the others are added back where they were, as if they were added just now.
+    /// If adding one back fails, the ones after it stay removed.
+    pub async fn remove(&self, position: usize) -> Result<(), EditError> {
+        let _busy = self.busy.lock().await;
+        let snapshot_id = self.check_snapshot().await?;
+        let id = self.playlist.map_ref(|playlist| playlist.id.clone());
+        let Some(item) = self.items.map_ref(|items| {
+            items
+                .get(position)
+                .and_then(|item| item.track.as_ref())
+                .and_then(playable_id)
+        }) else {
+            // local files have no id to remove them by
+            return Ok(());
+        };
+        let kept = kept_copies(&self.positions_of(&item), position);
+        let mut snapshot_id = self
+            .context
+            .remove_playlist_items(id.clone(), &[(item.clone(), position as u32)], &snapshot_id)
+            .await?;
+
+        let mut readded = 0;
+        let mut result = Ok(());
+        for &at in &kept {
+            match self
+                .context
+                .add_playlist_items(id.clone(), std::slice::from_ref(&item), Some(at as u32))
+                .await
+            {
+                Ok(added_snapshot_id) => {
+                    snapshot_id = added_snapshot_id;
+                    readded += 1;
+                }
+                Err(e) => {
+                    result = Err(e.into());
+                    break;
+                }
+            }
+        }
+        let added_at = Utc::now();
+        self.applied(snapshot_id, move |items| {
+            if position >= items.len() {
+                return;
+            }
+            items.remove(position);
+            for &at in kept[readded..].iter().rev() {
+                if at < items.len() {
+                    items.remove(at);
+                }
+            }
+            for &at in &kept[..readded] {
+                if let Some(copy) = items.get_mut(at) {
+                    copy.added_at = Some(added_at);
+                    copy.added_by = None;
+                }
+            }
+        })
+        .await;
+        result
+    }
+
+    /// Moves the item at `from` to `to`, counted before it's moved.
+    pub async fn reorder(&self, from: usize, to: usize) -> Result<(), EditError> {
+        if from == to {
+            return Ok(());
+        }
+        let _busy = self.busy.lock().await;
+        let snapshot_id = self.check_snapshot().await?;
+        let id = self.playlist.map_ref(|playlist| playlist.id.clone());
+        // moving down, it goes before the item that's at `to` after removing it
+        let insert_before = if to > from { to + 1 } else { to };
+        let snapshot_id = self
+            .context
+            .reorder_playlist_items(id, from as u32, insert_before as u32, &snapshot_id)
+            .await?;
+        self.applied(snapshot_id, move |items| {
+            if from < items.len() && to < items.len() {
+                let item = items.remove(from);
+                items.insert(to, item);
+            }
+        })
+        .await;
+        Ok(())
+    }
+
+    /// The snapshot id the playlist is at, unless it isn't the one of `items`.
+    async fn check_snapshot(&self) -> Result<String, EditError> {
+        let (id, known) = self
+            .playlist
+            .map_ref(|playlist| (playlist.id.clone(), playlist.snapshot_id.clone()));
+        let current = self.context.playlist(id).await?;
+        if current.snapshot_id == known {
+            return Ok(known);
+        }
+        eprintln!(
+            "Playlist {} changed from {} to {}, reloading it",
+            current.name, known, current.snapshot_id
+        );
+        self.reload(&current).await?;
+        Err(EditError::Conflict)
+    }
+
+    /// Replaces the items with the ones of `current`.
+    async fn reload(&self, current: &FullPlaylist) -> Result<(), ApiError> {
+        let id = current.id.clone();
+        let items: Vec<PlaylistItem> = self
+            .context
+            .paginate(move |context, limit, offset| {
+                let id = id.clone();
+                async move { context.playlist_items(id, Some(limit), Some(offset)).await }
+            })
+            .items()
+            .try_collect()
+            .await?;
+        let snapshot_id = current.snapshot_id.clone();
+        self.playlist.map_mut(|mut playlist| {
+            playlist.name = current.name.clone();
+        });
+        self.applied(snapshot_id, move |old| *old = items).await;
+        Ok(())
+    }
+
+    /// Makes a change to `items` that brought the playlist to `snapshot_id`, and stores it.
+    async fn applied(&self, snapshot_id: String, change: impl FnOnce(&mut Vec<PlaylistItem>)) {
+        let items = self.items.map_mut(|mut items| {
+            change(&mut *items);
+            items.clone()
+        });
+        self.playlist.map_mut(|mut playlist| {
+            playlist.snapshot_id = snapshot_id;
+            playlist.tracks.total = items.len() as u32;
+        });
+        let playlist = self.playlist.get();
+        self.library.update_playlist(playlist.clone()).await;
+        self.library.store_playlist_items(&playlist, items).await;
+    }
+}
+
+/// Where to add back the other occurrences of the item at `position` once all of them were
+/// removed, in the order to add them. `positions` are the positions of all occurrences.
+fn kept_copies(positions: &[usize], position: usize) -> Vec<usize> {
+    let mut kept: Vec<_> = positions
+        .iter()
+        .filter(|&&other| other != position)
+        // everything after the removed one moves up
+        .map(|&other| if other > position { other - 1 } else { other })
+        .collect();
+    // adding them from the front on, each goes where it ends up
+    kept.sort_unstable();
+    kept
+}
+
+/// The id to add or remove `item` by.
+fn playable_id(item: &PlayableItem) -> Option<PlayableId<'static>> {
+    match item {
+        PlayableItem::Track(track) => Some(PlayableId::Track(track.id.clone()?)),
+        PlayableItem::Episode(episode) => Some(PlayableId::Episode(episode.id.clone())),
+    }
+}
+
+fn playable_uri(item: &PlayableItem) -> Option<String> {
+    playable_id(item).map(|id| id.uri())
+}
+
+/// The library's version of a playlist that was just fetched in full.
+fn simplified(playlist: &FullPlaylist) -> SimplifiedPlaylist {
+    SimplifiedPlaylist {
+        collaborative: playlist.collaborative,
+        external_urls: playlist.external_urls.clone(),
+        href: playlist.href.clone(),
+        id: playlist.id.clone(),
+        images: playlist.images.clone(),
+        name: playlist.name.clone(),
+        owner: playlist.owner.clone(),
+        public: playlist.public,
+        snapshot_id: playlist.snapshot_id.clone(),
+        tracks: PlaylistTracksRef {
+            href: playlist.tracks.href.clone(),
+            total: playlist.tracks.total,
+        },
+    }
+}
@@ -0,0 +1,44 @@
+use super::kept_copies;
+
+/// What Spotify does when removing the item at `position` of `items`: every occurrence of
+/// it is removed, then the copies are added back where [`kept_copies`] says.
+fn remove(items: &[&'static str], position: usize) -> Vec<&'static str> {
+    let removed = items[position];
+    let positions: Vec<_> = (0..items.len()).filter(|&i| items[i] == removed).collect();
+    let mut remote: Vec<_> = items
+        .iter()
+        .copied()
+        .filter(|&item| item != removed)
+        .collect();
+    for at in kept_copies(&positions, position) {
+        remote.insert(at, removed);
+    }
+    remote
+}
+
+#[test]
+fn nothing_is_added_back_for_a_single_occurrence() {
+    assert_eq!(kept_copies(&[2], 2), Vec::<usize>::new());
+    assert_eq!(remove(&["a", "b", "c"], 1), ["a", "c"]);
+}
+
+#[test]
+fn copies_before_and_after_stay_where_they_were() {
+    let items = ["x", "a", "x", "b", "x", "c"];
+
+    assert_eq!(kept_copies(&[0, 2, 4], 2), [0, 3]);
+    assert_eq!(remove(&items, 2), ["x", "a", "b", "x", "c"]);
+}
+
+#[test]
+fn removing_one_of_adjacent_copies_keeps_the_other() {
+    assert_eq!(remove(&["a", "x", "x", "b"], 1), ["a", "x", "b"]);
+    assert_eq!(remove(&["a", "x", "x", "b"], 2), ["a", "x", "b"]);
+}
+
+#[test]
+fn removing_the_first_of_several_copies_keeps_the_rest() {
+    let items = ["x", "a", "x", "x", "b", "x"];
+
+    assert_eq!(remove(&items, 0), ["a", "x", "x", "b", "x"]);
+}
@@ -0,0 +1,241 @@
+use std::{
+    slice,
+    sync::{Arc, OnceLock},
+};
+
+use cushy::value::{Destination, Dynamic, Source};
+use futures_util::TryStreamExt;
+use rspotify::model::{ArtistId, FullArtist, PlaylistId, PublicUser, SimplifiedPlaylist, UserId};
+use rspotify::prelude::*;
+
+use super::{toggles::Toggles, Library};
+use crate::{
+    api::{with_priority, ApiError, ApiErrorKind, Interest, Priority, SpotifyContextRef},
+    nodebug::NoDebug,
+    rt::tokio_runtime,
+};
+
+/// The most artists or users checked by a single request.
+const CONTAINS_BATCH: usize = 50;
+
+/// Something the user can follow.
+#[derive(Debug, Clone)]
+pub enum Followable {
+    Artist(FullArtist),
+    /// Following a playlist adds it to the user's playlists.
+    Playlist(SimplifiedPlaylist),
+    User(PublicUser),
+}
+
+impl Followable {
+    fn uri(&self) -> String {
+        match self {
+            Followable::Artist(artist) => artist.id.uri(),
+            Followable::Playlist(playlist) => playlist.id.uri(),
+            Followable::User(user) => user.id.uri(),
+        }
+    }
+}
+
+/// Whether artists, playlists and users are followed, and which artists are. The playlists
+/// in the library are known to be followed, everything else is checked when it's shown,
+/// artists and users in batches. Following or unfollowing changes the state right away, going
+/// back if the request fails.
+#[derive(Debug, Clone)]
+pub struct Follows {
+    artist_states: Toggles<ArtistId<'static>>,
+    playlist_states: Toggles<PlaylistId<'static>>,
+    user_states: Toggles<UserId<'static>>,
+    /// The artists the user follows, by name.
+    pub artists: Dynamic<Vec<FullArtist>>,
+    /// Error of the last failed follow, unfollow or load of the followed artists.
+    pub error: Dynamic<Option<ApiError>>,
+
+    context: NoDebug<SpotifyContextRef>,
+    library: NoDebug<Library>,
+}
+
+impl Follows {
+    pub fn new(context: SpotifyContextRef, library: &Library) -> Self {
+        let artist_states = Toggles::new("followed artists", CONTAINS_BATCH, {
+            let context = context.clone();
+            move |ids: Vec<ArtistId<'static>>| {
+                let context = context.clone();
+                async move { context.artists_followed(&ids).await }
+            }
+        });
+        let user_states = Toggles::new("followed users", CONTAINS_BATCH, {
+            let context = context.clone();
+            move |ids: Vec<UserId<'static>>| {
+                let context = context.clone();
+                async move { context.users_followed(&ids).await }
+            }
+        });
+        // there's no batch endpoint for playlists, and checking one needs the user
+        let playlist_states = Toggles::new("followed playlists", 1, {
+            let context = context.clone();
+            let user_id = Arc::new(OnceLock::new());
+            move |ids: Vec<PlaylistId<'static>>| {
+                let context = context.clone();
+                let user_id = user_id.clone();
+                async move {
+                    let user_id = current_user_id(&context, &user_id).await?;
+                    let mut followed = Vec::with_capacity(ids.len());
+                    for id in ids {
+                        followed.push(context.playlist_followed(id, user_id.clone()).await?);
+                    }
+                    Ok(followed)
+                }
+            }
+        });
+        // the library has every followed playlist, but a missing one might not be synced yet
+        library
+            .playlists
+            .for_each({
+                let playlist_states = playlist_states.clone();
+                move |playlists| {
+                    playlist_states.set_on(playlists.iter().map(|playlist| playlist.id.clone()));
+                }
+            })
+            .persist();
+
+        Self {
+            artist_states,
+            playlist_states,
+            user_states,
+            artists: Default::default(),
+            error: Default::default(),
+            context: context.into(),
+            library: library.clone().into(),
+        }
+    }
+
+    /// Loads all of the artists the user follows.
+    pub async fn sync(&self) {
+        let artists = self
+            .context
+            .paginate_cursor(|context, limit, after| async move {
+                context
+                    .current_user_followed_artists(Some(limit), after)
+                    .await
+            })
+            .items()
+            .try_collect::<Vec<_>>()
+            .await;
+        match artists {
+            Ok(mut artists) => {
+                artists.sort_by_cached_key(|artist| artist.name.to_lowercase());
+                self.artist_states
+                    .set_on(artists.iter().map(|artist| artist.id.clone()));
+                self.artists.set(artists);
+            }
+            Err(e) if e.kind == ApiErrorKind::Cancelled => {}
+            Err(e) => {
+                eprintln!("Failed to load followed artists: {}", e);
+                self.error.set(Some(e));
+            }
+        }
+    }
+
+    /// Whether `item` is followed, `None` while that's being checked for as long as
+    /// `interest` isn't gone.
+    pub fn followed(
+        &self,
+        item: &Followable,
+        interest: Option<&Interest>,
+    ) -> Dynamic<Option<bool>> {
+        match item {
+            Followable::Artist(artist) => self.artist_states.state(artist.id.clone(), interest),
+            Followable::Playlist(playlist) => {
+                self.playlist_states.state(playlist.id.clone(), interest)
+            }
+            Followable::User(user) => self.user_states.state(user.id.clone(), interest),
+        }
+    }
+
+    /// Follows or unfollows `item`, showing it right away. The followed artists and the
+    /// library's playlists are updated once Spotify made the change.
+    pub fn set_followed(&self, item: Followable, followed: bool) {
+        let changed = match &item {
+            Followable::Artist(artist) => self.artist_states.set(artist.id.clone(), followed),
+            Followable::Playlist(playlist) => {
+                self.playlist_states.set(playlist.id.clone(), followed)
+            }
+            Followable::User(user) => self.user_states.set(user.id.clone(), followed),
+        };
+        if !changed {
+            return;
+        }
+
+        let follows = self.clone();
+        tokio_runtime().spawn(with_priority(Priority::UserAction, None, async move {
+            if let Err(e) = follows.send(&item, followed).await {
+                eprintln!("Failed to change followed state of {}: {}", item.uri(), e);
+                match &item {
+                    Followable::Artist(artist) => {
+                        follows.artist_states.revert(&artist.id, followed)
+                    }
+                    Followable::Playlist(playlist) => {
+                        follows.playlist_states.revert(&playlist.id, followed)
+                    }
+                    Followable::User(user) => follows.user_states.revert(&user.id, followed),
+                }
+                follows.error.set(Some(e));
+                return;
+            }
+
+            match item {
+                Followable::Artist(artist) => follows.artists.map_mut(|mut artists| {
+                    artists.retain(|other| other.id != artist.id);
+                    if followed {
+                        let name = artist.name.to_lowercase();
+                        let position =
+                            artists.partition_point(|other| other.name.to_lowercase() < name);
+                        artists.insert(position, artist);
+                    }
+                }),
+                Followable::Playlist(playlist) if followed => {
+                    follows.library.update_playlist(playlist).await
+                }
+                Followable::Playlist(playlist) => {
+                    follows.library.remove_playlist(&playlist.id).await
+                }
+                Followable::User(_) => {}
+            }
+        }));
+    }
+
+    async fn send(&self, item: &Followable, followed: bool) -> Result<(), ApiError> {
+        let context = &self.context;
+        match (item, followed) {
+            (Followable::Artist(artist), true) => {
+                context.follow_artists(slice::from_ref(&artist.id)).await
+            }
+            (Followable::Artist(artist), false) => {
+                context.unfollow_artists(slice::from_ref(&artist.id)).await
+            }
+            (Followable::Playlist(playlist), true) => {
+                context.follow_playlist(playlist.id.as_ref()).await
+            }
+            (Followable::Playlist(playlist), false) => {
+                context.unfollow_playlist(playlist.id.as_ref()).await
+            }
+            (Followable::User(user), true) => context.follow_users(slice::from_ref(&user.id)).await,
+            (Followable::User(user), false) => {
+                context.unfollow_users(slice::from_ref(&user.id)).await
+            }
+        }
+    }
+}
+
+/// The id of the current user, fetched once and kept in `user_id`.
+async fn current_user_id(
+    context: &SpotifyContextRef,
+    user_id: &OnceLock<UserId<'static>>,
+) -> Result<UserId<'static>, ApiError> {
+    if let Some(user_id) = user_id.get() {
+        return Ok(user_id.clone());
+    }
+    let user = context.current_user().await?;
+    Ok(user_id.get_or_init(|| user.id).clone())
+}
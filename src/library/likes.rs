@@ -0,0 +1,157 @@
+use std::{collections::HashMap, mem};
+
+use chrono::Utc;
+use cushy::value::{Destination, Dynamic, Source};
+use rspotify::model::{SavedTrack, TrackId};
+
+use super::{toggles::Toggles, Library};
+use crate::{
+    api::{with_priority, ApiError, Interest, Priority, SpotifyContextRef},
+    nodebug::NoDebug,
+    rt::tokio_runtime,
+};
+
+/// The most tracks checked by a single request.
+const CONTAINS_BATCH: usize = 50;
+
+/// Whether tracks are liked. Tracks that aren't known to be liked from the stored library are
+/// checked in batches as their rows are shown, and liking one changes the state right away,
+/// going back if the request fails.
+#[derive(Debug, Clone)]
+pub struct Likes {
+    states: Toggles<TrackId<'static>>,
+    saved_tracks: Dynamic<HashMap<usize, SavedTrack>>,
+    saved_track_count: Dynamic<usize>,
+    /// Error of the last failed like or unlike, the change was undone.
+    pub error: Dynamic<Option<ApiError>>,
+
+    context: NoDebug<SpotifyContextRef>,
+}
+
+impl Likes {
+    pub fn new(context: SpotifyContextRef, library: &Library) -> Self {
+        let states = Toggles::new("liked tracks", CONTAINS_BATCH, {
+            let context = context.clone();
+            move |ids: Vec<TrackId<'static>>| {
+                let context = context.clone();
+                async move { context.saved_tracks_contain(&ids).await }
+            }
+        });
+        // everything in the liked songs is liked, but a song missing from them might just
+        // not be loaded yet
+        library
+            .saved_tracks
+            .for_each({
+                let states = states.clone();
+                move |saved_tracks| {
+                    states.set_on(
+                        saved_tracks
+                            .values()
+                            .filter_map(|saved| saved.track.id.clone()),
+                    );
+                }
+            })
+            .persist();
+
+        Self {
+            states,
+            saved_tracks: library.saved_tracks.clone(),
+            saved_track_count: library.saved_track_count.clone(),
+            error: Default::default(),
+            context: context.into(),
+        }
+    }
+
+    /// Whether `id` is liked, `None` while that's being checked for as long as `interest`
+    /// isn't gone.
+    pub fn liked(
+        &self,
+        id: TrackId<'static>,
+        interest: Option<&Interest>,
+    ) -> Dynamic<Option<bool>> {
+        self.states.state(id, interest)
+    }
+
+    /// Likes or unlikes `id`, showing it right away. The liked songs are updated as well.
+    pub fn set_liked(&self, id: TrackId<'static>, liked: bool) {
+        if !self.states.set(id.clone(), liked) {
+            return;
+        }
+        let removed = if liked { None } else { self.remove_saved(&id) };
+
+        let likes = self.clone();
+        tokio_runtime().spawn(with_priority(Priority::UserAction, None, async move {
+            let ids = [id.clone()];
+            let result = if liked {
+                likes.context.save_tracks(&ids).await
+            } else {
+                likes.context.remove_saved_tracks(&ids).await
+            };
+            if let Err(e) = result {
+                eprintln!("Failed to change liked state of {}: {}", id.id(), e);
+                likes.states.revert(&id, liked);
+                if let Some((position, saved)) = removed {
+                    likes.insert_saved(position, saved);
+                }
+                likes.error.set(Some(e));
+                return;
+            }
+
+            if liked {
+                // the liked songs show it with its album, which needs the full track
+                match likes.context.track(id.clone()).await {
+                    Ok(track) => likes.insert_saved(
+                        0,
+                        SavedTrack {
+                            added_at: Utc::now(),
+                            track,
+                        },
+                    ),
+                    Err(e) => eprintln!("Failed to load liked track {}: {}", id.id(), e),
+                }
+            }
+        }));
+    }
+
+    /// Removes `id` from the liked songs, returning where it was.
+    fn remove_saved(&self, id: &TrackId<'static>) -> Option<(usize, SavedTrack)> {
+        let removed = self.saved_tracks.map_mut(|mut saved_tracks| {
+            let position = saved_tracks
+                .iter()
+                .find(|(_, saved)| saved.track.id.as_ref() == Some(id))
+                .map(|(position, _)| *position)?;
+            let saved = saved_tracks.remove(&position)?;
+            *saved_tracks = mem::take(&mut *saved_tracks)
+                .into_iter()
+                .map(|(i, saved)| (if i > position { i - 1 } else { i }, saved))
+                .collect();
+            Some((position, saved))
+        });
+        if removed.is_some() {
+            self.saved_track_count
+                .map_mut(|mut count| *count = count.saturating_sub(1));
+        }
+        removed
+    }
+
+    /// Puts `saved` back into the liked songs at `position`, unless it's there already.
+    fn insert_saved(&self, position: usize, saved: SavedTrack) {
+        let inserted = self.saved_tracks.map_mut(|mut saved_tracks| {
+            if saved_tracks
+                .values()
+                .any(|other| other.track.id == saved.track.id)
+            {
+                return false;
+            }
+            *saved_tracks = mem::take(&mut *saved_tracks)
+                .into_iter()
+                .map(|(i, saved)| (if i >= position { i + 1 } else { i }, saved))
+                .collect();
+            saved_tracks.insert(position, saved);
+            true
+        });
+        if inserted {
+            self.saved_track_count.map_mut(|mut count| *count += 1);
+        }
+    }
+}
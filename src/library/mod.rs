@@ -0,0 +1,201 @@
+use std::{collections::HashMap, sync::Arc};
+
+use cushy::value::{Destination, Dynamic, Source};
+use rspotify::model::{PlaylistId, PlaylistItem, SavedTrack, SimplifiedPlaylist};
+use rspotify::prelude::*;
+
+use crate::{
+    api::{with_priority, ApiError, Priority, SpotifyContextRef},
+    paths::paths,
+};
+use db::{DbError, LibraryDb};
+pub use edit::{EditError, PlaylistEditor};
+pub use follows::{Followable, Follows};
+pub use likes::Likes;
+pub use sync::SyncProgress;
+
+pub mod db;
+pub mod edit;
+pub mod follows;
+pub mod likes;
+pub mod sync;
+mod toggles;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SyncError {
+    #[error(transparent)]
+    Api(#[from] ApiError),
+    #[error("failed to store the library: {0}")]
+    Db(#[from] DbError),
+}
+
+/// The profile's library as shown by the UI, backed by a local database and refreshed by
+/// [`Library::sync`].
+#[derive(Clone)]
+pub struct Library {
+    db: Arc<LibraryDb>,
+    pub playlists: Dynamic<Vec<SimplifiedPlaylist>>,
+    /// Liked songs by their position in the list.
+    pub saved_tracks: Dynamic<HashMap<usize, SavedTrack>>,
+    pub saved_track_count: Dynamic<usize>,
+    /// Error of the last failed sync, cleared once a sync succeeds.
+    pub sync_error: Dynamic<Option<ApiError>>,
+    pub progress: Dynamic<SyncProgress>,
+}
+
+impl Library {
+    /// Opens the library of `profile` and loads what's stored of it. Blocks while reading the
+    /// database.
+    pub fn open(profile: &str) -> Self {
+        let db = LibraryDb::open(&paths().library_db(profile))
+            .or_else(|e| {
+                eprintln!("Failed to open library database, not keeping it: {}", e);
+                LibraryDb::open_in_memory()
+            })
+            .expect("failed to create in-memory library database");
+
+        let playlists = db.playlists().unwrap_or_else(|e| {
+            eprintln!("Failed to load stored playlists: {}", e);
+            Vec::new()
+        });
+        let saved_tracks = db.saved_tracks().unwrap_or_else(|e| {
+            eprintln!("Failed to load stored liked songs: {}", e);
+            Vec::new()
+        });
+
+        Self {
+            db: Arc::new(db),
+            playlists: Dynamic::new(playlists),
+            saved_track_count: Dynamic::new(saved_tracks.len()),
+            saved_tracks: Dynamic::new(saved_tracks.into_iter().enumerate().collect()),
+            sync_error: Dynamic::default(),
+            progress: Dynamic::default(),
+        }
+    }
+
+    /// Brings the stored library up to date, only fetching what changed since the last sync.
+    pub async fn sync(&self, context: &SpotifyContextRef) {
+        let result = async {
+            if self.saved_track_count.get() == 0 {
+                // nothing to show on the liked songs page yet, don't make it wait for playlists
+                with_priority(
+                    Priority::Visible,
+                    None,
+                    sync::sync_saved_tracks(self, context),
+                )
+                .await?;
+                sync::sync_playlists(self, context).await?;
+            } else {
+                sync::sync_playlists(self, context).await?;
+                with_priority(
+                    Priority::Prefetch,
+                    None,
+                    sync::sync_saved_tracks(self, context),
+                )
+                .await?;
+            }
+            // unliked songs leave their tracks, albums and artists behind
+            self.with_db(LibraryDb::remove_orphans).await?;
+            Ok::<_, SyncError>(())
+        }
+        .await;
+        self.progress.set(SyncProgress::Idle);
+        match result {
+            Ok(()) => self.sync_error.set(None),
+            Err(SyncError::Api(e)) => {
+                eprintln!("Failed to sync library: {}", e);
+                self.sync_error.set(Some(e));
+            }
+            Err(e) => eprintln!("Failed to sync library: {}", e),
+        }
+    }
+
+    /// Stored items of `playlist`, `None` unless they're from its current snapshot.
+    pub async fn playlist_items(&self, playlist: &SimplifiedPlaylist) -> Option<Vec<PlaylistItem>> {
+        let playlist_id = playlist.id.uri();
+        match self
+            .with_db(move |db| db.playlist_items(&playlist_id))
+            .await
+        {
+            Ok(Some((snapshot_id, items))) if snapshot_id == playlist.snapshot_id => Some(items),
+            Ok(_) => None,
+            Err(e) => {
+                eprintln!("Failed to load stored playlist items: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Stores the items of `playlist` fetched for its current snapshot.
+    pub async fn store_playlist_items(
+        &self,
+        playlist: &SimplifiedPlaylist,
+        items: Vec<PlaylistItem>,
+    ) {
+        let playlist_id = playlist.id.uri();
+        let snapshot_id = playlist.snapshot_id.clone();
+        let result = self
+            .with_db(move |db| db.replace_playlist_items(&playlist_id, &snapshot_id, &items))
+            .await;
+        if let Err(e) = result {
+            eprintln!("Failed to store playlist items: {}", e);
+        }
+    }
+
+    /// Puts `playlist` in place of its old version after it was changed from here, or first
+    /// if it's new.
+    pub async fn update_playlist(&self, playlist: SimplifiedPlaylist) {
+        let playlists = self.playlists.map_mut(|mut playlists| {
+            match playlists.iter_mut().find(|other| other.id == playlist.id) {
+                Some(other) => *other = playlist,
+                None => playlists.insert(0, playlist),
+            }
+            playlists.clone()
+        });
+        let result = self
+            .with_db(move |db| db.replace_playlists(&playlists))
+            .await;
+        if let Err(e) = result {
+            eprintln!("Failed to store playlists: {}", e);
+        }
+    }
+
+    /// Takes the playlist `id` out of the playlists after it was unfollowed from here.
+    pub async fn remove_playlist(&self, id: &PlaylistId<'_>) {
+        let playlists = self.playlists.map_mut(|mut playlists| {
+            playlists.retain(|playlist| playlist.id != *id);
+            playlists.clone()
+        });
+        let result = self
+            .with_db(move |db| db.replace_playlists(&playlists))
+            .await;
+        if let Err(e) = result {
+            eprintln!("Failed to store playlists: {}", e);
+        }
+    }
+
+    async fn store_saved_tracks(&self, saved_tracks: Vec<SavedTrack>) -> Result<(), SyncError> {
+        let saved_tracks = self
+            .with_db(move |db| {
+                db.replace_saved_tracks(&saved_tracks)
+                    .map(|()| saved_tracks)
+            })
+            .await?;
+        self.saved_track_count.set(saved_tracks.len());
+        self.saved_tracks
+            .set(saved_tracks.into_iter().enumerate().collect());
+        Ok(())
+    }
+
+    /// Runs `f` with the database on a thread where blocking is fine.
+    async fn with_db<R, F>(&self, f: F) -> Result<R, DbError>
+    where
+        F: FnOnce(&LibraryDb) -> Result<R, DbError> + Send + 'static,
+        R: Send + 'static,
+    {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || f(&db))
+            .await
+            .expect("library database task panicked")
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fmt,
+    future::Future,
+    pin::pin,
+};
+
+use chrono::{DateTime, Utc};
+use cushy::value::{Destination, Source};
+use futures_util::{StreamExt, TryStreamExt};
+use rspotify::model::{PlaylistItem, SavedTrack};
+use rspotify::prelude::*;
+
+use super::{db::track_key, Library, SyncError};
+use crate::api::{with_priority, ApiError, Priority, SpotifyContextRef};
+
+#[cfg(test)]
+mod tests;
+
+/// Above this many removed liked songs, refetching all of them is cheaper than finding each.
+const MAX_TARGETED_REMOVALS: usize = 20;
+
+/// What a running sync is doing, for showing it in the UI.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum SyncProgress {
+    #[default]
+    Idle,
+    /// Fetching the items of playlists whose snapshot changed.
+    Playlists { done: usize, total: usize },
+    /// Fetching liked songs added since the last sync.
+    NewLikedSongs { fetched: usize },
+    /// Looking for liked songs removed since the last sync.
+    RemovedLikedSongs { done: usize, total: usize },
+    /// Refetching all liked songs.
+    AllLikedSongs { fetched: usize, total: usize },
+}
+
+impl fmt::Display for SyncProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncProgress::Idle => Ok(()),
+            SyncProgress::Playlists { done, total } => {
+                write!(f, "Syncing playlists ({done}/{total})")
+            }
+            SyncProgress::NewLikedSongs { fetched } => {
+                write!(f, "Syncing liked songs ({fetched} new)")
+            }
+            SyncProgress::RemovedLikedSongs { done, total } => {
+                write!(f, "Syncing removed liked songs ({done}/{total})")
+            }
+            SyncProgress::AllLikedSongs { fetched, total } => {
+                write!(f, "Syncing liked songs ({fetched}/{total})")
+            }
+        }
+    }
+}
+
+/// Identifies a liked song. Liking a song again moves it to the top, so it's a new entry.
+fn saved_key(saved: &SavedTrack) -> (String, DateTime<Utc>) {
+    (track_key(&saved.track), saved.added_at)
+}
+
+/// Updates the playlists, and the items of those whose snapshot id changed.
+pub(super) async fn sync_playlists(
+    library: &Library,
+    context: &SpotifyContextRef,
+) -> Result<(), SyncError> {
+    let playlists: Vec<_> = with_priority(
+        Priority::Visible,
+        None,
+        context
+            .paginate(|context, limit, offset| async move {
+                context
+                    .current_user_playlists(Some(limit), Some(offset))
+                    .await
+            })
+            .items()
+            .try_collect(),
+    )
+    .await?;
+    let (playlists, snapshots) = library
+        .with_db(move |db| {
+            db.replace_playlists(&playlists)?;
+            Ok((playlists, db.playlist_snapshots()?))
+        })
+        .await?;
+    library.playlists.set(playlists.clone());
+
+    let changed: Vec<_> = playlists
+        .into_iter()
+        .filter(|playlist| snapshots.get(&playlist.id.uri()) != Some(&playlist.snapshot_id))
+        .collect();
+    for (done, playlist) in changed.iter().enumerate() {
+        library.progress.set(SyncProgress::Playlists {
+            done,
+            total: changed.len(),
+        });
+        let id = playlist.id.clone();
+        let items: Vec<PlaylistItem> = with_priority(
+            Priority::Prefetch,
+            None,
+            context
+                .paginate(move |context, limit, offset| {
+                    let id = id.clone();
+                    async move { context.playlist_items(id, Some(limit), Some(offset)).await }
+                })
+                .items()
+                .try_collect(),
+        )
+        .await?;
+        let playlist_id = playlist.id.uri();
+        let snapshot_id = playlist.snapshot_id.clone();
+        library
+            .with_db(move |db| db.replace_playlist_items(&playlist_id, &snapshot_id, &items))
+            .await?;
+    }
+    Ok(())
+}
+
+/// Fetches liked songs added since the last sync, then finds removed ones if the total
+/// doesn't add up anymore. Requests are sent with the priority of the caller.
+pub(super) async fn sync_saved_tracks(
+    library: &Library,
+    context: &SpotifyContextRef,
+) -> Result<(), SyncError> {
+    let stored = library.with_db(|db| db.saved_tracks()).await?;
+    if stored.is_empty() {
+        return sync_all_saved_tracks(library, context).await;
+    }
+
+    // the newest songs come first, everything from the first known one on is stored already
+    let known: HashSet<_> = stored.iter().map(saved_key).collect();
+    let mut new = Vec::new();
+    let mut total = 0;
+    {
+        library
+            .progress
+            .set(SyncProgress::NewLikedSongs { fetched: 0 });
+        let mut pages = pin!(context
+            .paginate(|context, limit, offset| async move {
+                context
+                    .current_user_saved_tracks(Some(limit), Some(offset))
+                    .await
+            })
+            .concurrency(1)
+            .pages());
+        'pages: while let Some(page) = pages.next().await {
+            let page = page?;
+            total = page.total as usize;
+            for saved in page.items {
+                if known.contains(&saved_key(&saved)) {
+                    break 'pages;
+                }
+                new.push(saved);
+            }
+            library
+                .progress
+                .set(SyncProgress::NewLikedSongs { fetched: new.len() });
+        }
+    }
+
+    let added = new.len();
+    let mut merged = new;
+    merged.extend(stored);
+    if merged.len() < total {
+        // songs were added somewhere other than the top, don't try to make sense of it
+        return sync_all_saved_tracks(library, context).await;
+    }
+    let removed = merged.len() - total;
+    if removed > MAX_TARGETED_REMOVALS {
+        return sync_all_saved_tracks(library, context).await;
+    }
+
+    let dropped = drop_removed(
+        &mut merged,
+        added,
+        removed,
+        saved_key,
+        |position| async move {
+            let page = context
+                .current_user_saved_tracks(Some(1), Some(position as u32))
+                .await?;
+            Ok::<_, ApiError>(page.items.first().map(saved_key))
+        },
+        |done| {
+            library.progress.set(SyncProgress::RemovedLikedSongs {
+                done,
+                total: removed,
+            })
+        },
+    )
+    .await?;
+    if !dropped {
+        // the list shrank while syncing
+        return sync_all_saved_tracks(library, context).await;
+    }
+
+    if added == 0 && removed == 0 {
+        return Ok(());
+    }
+    library.store_saved_tracks(merged).await
+}
+
+/// Drops the `removed` entries of `stored` that aren't in the remote list anymore, finding
+/// each with a binary search over the remote positions. The first `matching` entries are
+/// known to be at the same position remotely. `remote_key` fetches the key of the remote
+/// entry at a position, each at most once, and `progress` is told how many were found.
+///
+/// Returns `false` if the remote list turned out shorter than expected, `stored` is only
+/// partially updated then.
+async fn drop_removed<T, K, E, Fut>(
+    stored: &mut Vec<T>,
+    mut matching: usize,
+    removed: usize,
+    key: impl Fn(&T) -> K,
+    mut remote_key: impl FnMut(usize) -> Fut,
+    mut progress: impl FnMut(usize),
+) -> Result<bool, E>
+where
+    K: PartialEq,
+    Fut: Future<Output = Result<Option<K>, E>>,
+{
+    let total = stored.len() - removed;
+    // remote keys by position
+    let mut remote = HashMap::new();
+    for done in 0..removed {
+        progress(done);
+        // binary search for the first position where the stored entry isn't the remote one
+        let (mut low, mut high) = (matching, total);
+        while low < high {
+            let mid = (low + high) / 2;
+            let remote_mid = match remote.entry(mid) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let Some(found) = remote_key(mid).await? else {
+                        return Ok(false);
+                    };
+                    entry.insert(found)
+                }
+            };
+            if *remote_mid == key(&stored[mid]) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        stored.remove(low);
+        matching = low;
+    }
+    Ok(true)
+}
+
+/// Refetches all liked songs, replacing the stored ones. Without any shown yet, each page is
+/// shown as soon as it arrives.
+async fn sync_all_saved_tracks(
+    library: &Library,
+    context: &SpotifyContextRef,
+) -> Result<(), SyncError> {
+    let progressive = library.saved_tracks.map_ref(HashMap::is_empty);
+    let mut saved_tracks = Vec::new();
+    let mut pages = pin!(context
+        .paginate(|context, limit, offset| async move {
+            context
+                .current_user_saved_tracks(Some(limit), Some(offset))
+                .await
+        })
+        .pages());
+    while let Some(page) = pages.next().await {
+        let page = page?;
+        if progressive {
+            let offset = page.offset as usize;
+            library.saved_track_count.set(page.total as usize);
+            library.saved_tracks.map_mut(|mut shown| {
+                shown.extend(
+                    page.items
+                        .iter()
+                        .cloned()
+                        .enumerate()
+                        .map(|(i, track)| (offset + i, track)),
+                );
+            });
+        }
+        saved_tracks.extend(page.items);
+        library.progress.set(SyncProgress::AllLikedSongs {
+            fetched: saved_tracks.len(),
+            total: page.total as usize,
+        });
+    }
+    library.store_saved_tracks(saved_tracks).await
+}
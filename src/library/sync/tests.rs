@@ -0,0 +1,114 @@
+use std::cell::Cell;
+
+use futures_util::{future, FutureExt};
+
+use super::drop_removed;
+
+/// Runs [`drop_removed`] on `stored` against `remote`, returning the result and how many
+/// remote entries were fetched.
+fn run(stored: &mut Vec<u32>, remote: &[u32], matching: usize) -> (bool, usize) {
+    let fetched = Cell::new(0);
+    let removed = stored.len() - remote.len();
+    let result = drop_removed(
+        stored,
+        matching,
+        removed,
+        |entry| *entry,
+        |position| {
+            fetched.set(fetched.get() + 1);
+            future::ready(Ok::<_, ()>(remote.get(position).copied()))
+        },
+        |_| {},
+    )
+    .now_or_never()
+    .expect("nothing to wait for")
+    .unwrap();
+    (result, fetched.get())
+}
+
+#[test]
+fn nothing_removed() {
+    let remote: Vec<u32> = (0..100).collect();
+    let mut stored = remote.clone();
+
+    assert_eq!(run(&mut stored, &remote, 0), (true, 0));
+    assert_eq!(stored, remote);
+}
+
+#[test]
+fn finds_a_single_removal() {
+    for removed in [0, 1, 50, 98, 99] {
+        let mut stored: Vec<u32> = (0..100).collect();
+        let mut remote = stored.clone();
+        remote.remove(removed);
+
+        let (dropped, fetched) = run(&mut stored, &remote, 0);
+
+        assert!(dropped);
+        assert_eq!(stored, remote, "removed {removed}");
+        // a binary search over 99 positions
+        assert!(fetched <= 7, "fetched {fetched} to find {removed}");
+    }
+}
+
+#[test]
+fn finds_several_removals() {
+    let mut stored: Vec<u32> = (0..1000).collect();
+    let remote: Vec<u32> = stored
+        .iter()
+        .copied()
+        .filter(|entry| ![3, 4, 500, 999].contains(entry))
+        .collect();
+
+    let (dropped, fetched) = run(&mut stored, &remote, 0);
+
+    assert!(dropped);
+    assert_eq!(stored, remote);
+    assert!(fetched <= 4 * 10, "fetched {fetched}");
+}
+
+#[test]
+fn removes_one_of_two_equal_entries() {
+    let mut stored = vec![1, 2, 2, 3];
+    let remote = [1, 2, 3];
+
+    assert!(run(&mut stored, &remote, 0).0);
+    assert_eq!(stored, remote);
+}
+
+#[test]
+fn skips_entries_known_to_match() {
+    let mut stored: Vec<u32> = (0..100).collect();
+    let mut remote = stored.clone();
+    remote.remove(90);
+    let fetched_from_start = run(&mut stored.clone(), &remote, 0).1;
+
+    let (dropped, fetched) = run(&mut stored, &remote, 80);
+
+    assert!(dropped);
+    assert_eq!(stored, remote);
+    assert!(fetched < fetched_from_start);
+}
+
+#[test]
+fn reports_a_remote_list_that_shrank() {
+    let mut stored: Vec<u32> = (0..100).collect();
+    let mut remote = stored.clone();
+    remote.remove(90);
+    // the second half was removed as well while syncing
+    remote.truncate(50);
+    let removed = 1;
+
+    let result = drop_removed(
+        &mut stored,
+        0,
+        removed,
+        |entry| *entry,
+        |position| future::ready(Ok::<_, ()>(remote.get(position).copied())),
+        |_| {},
+    )
+    .now_or_never()
+    .unwrap();
+
+    assert_eq!(result, Ok(false));
+}
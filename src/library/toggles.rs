@@ -0,0 +1,216 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    future::Future,
+    hash::Hash,
+    mem,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use cushy::value::{Destination, Dynamic, Source};
+use futures_util::{future::BoxFuture, FutureExt};
+
+use crate::{
+    api::{with_interests, ApiError, ApiErrorKind, Interest, Priority, WeakInterest},
+    nodebug::NoDebug,
+    rt::tokio_runtime,
+};
+
+/// How long to collect keys to check, so rows shown together are checked together.
+const BATCH_DELAY: Duration = Duration::from_millis(20);
+
+type Check<K> = dyn Fn(Vec<K>) -> BoxFuture<'static, Result<Vec<bool>, ApiError>> + Send + Sync;
+
+/// Whether things are on, like a track being liked or an artist followed. Unknown ones are
+/// checked in batches as they're shown, dropping those whose rows are gone before their turn.
+/// Toggling one shows the new state right away, to be undone if the request fails.
+#[derive(Debug)]
+pub struct Toggles<K> {
+    states: Dynamic<HashMap<K, bool>>,
+    batch: Arc<Mutex<Batch<K>>>,
+    /// The most keys checked at once.
+    batch_size: usize,
+    /// What's checked, for logging.
+    what: &'static str,
+    check: NoDebug<Arc<Check<K>>>,
+}
+
+impl<K> Clone for Toggles<K> {
+    fn clone(&self) -> Self {
+        Self {
+            states: self.states.clone(),
+            batch: self.batch.clone(),
+            batch_size: self.batch_size,
+            what: self.what,
+            check: self.check.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Batch<K> {
+    /// Waiting for the next request.
+    queued: Vec<K>,
+    /// Queued or being checked, with the interests of the rows waiting for each. `None` if
+    /// one is wanted regardless of what's shown.
+    requested: HashMap<K, Option<Vec<WeakInterest>>>,
+    /// A task is sending the queued keys.
+    flushing: bool,
+}
+
+impl<K> Default for Batch<K> {
+    fn default() -> Self {
+        Self {
+            queued: Vec::new(),
+            requested: HashMap::new(),
+            flushing: false,
+        }
+    }
+}
+
+impl<K> Toggles<K>
+where
+    K: Clone + Eq + Hash + Send + Sync + 'static,
+{
+    /// `check` tells for up to `batch_size` keys whether each of them is on, in order.
+    pub fn new<F, Fut>(what: &'static str, batch_size: usize, check: F) -> Self
+    where
+        F: Fn(Vec<K>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Vec<bool>, ApiError>> + Send + 'static,
+    {
+        let check: Arc<Check<K>> = Arc::new(move |keys| check(keys).boxed());
+        Self {
+            states: Default::default(),
+            batch: Default::default(),
+            batch_size: batch_size.max(1),
+            what,
+            check: check.into(),
+        }
+    }
+
+    /// Whether `key` is on, `None` while that's being checked. The check is dropped once
+    /// `interest` is gone, the returned state holds on to it.
+    pub fn state(&self, key: K, interest: Option<&Interest>) -> Dynamic<Option<bool>> {
+        if !self.states.map_ref(|states| states.contains_key(&key)) {
+            self.check(key.clone(), interest);
+        }
+        let interest = interest.cloned();
+        self.states.map_each(move |states| {
+            let _wanted = &interest;
+            states.get(&key).copied()
+        })
+    }
+
+    /// Marks `keys` as on, e.g. because they're in the library.
+    pub fn set_on(&self, keys: impl IntoIterator<Item = K>) {
+        self.states.map_mut(|mut states| {
+            for key in keys {
+                states.insert(key, true);
+            }
+        });
+    }
+
+    /// Shows `key` as `on` right away, returning whether that changed anything.
+    pub fn set(&self, key: K, on: bool) -> bool {
+        let previous = self.states.map_mut(|mut states| states.insert(key, on));
+        previous != Some(on)
+    }
+
+    /// Undoes [`Toggles::set`] after sending the change failed, unless `key` was toggled
+    /// again in the meantime.
+    pub fn revert(&self, key: &K, on: bool) {
+        self.states.map_mut(|mut states| {
+            if states.get(key) == Some(&on) {
+                states.insert(key.clone(), !on);
+            }
+        });
+    }
+
+    /// Checks whether `key` is on with the next batch.
+    fn check(&self, key: K, interest: Option<&Interest>) {
+        let interest = interest.map(Interest::downgrade);
+        let mut batch = self.batch.lock().unwrap();
+        match batch.requested.entry(key.clone()) {
+            Entry::Occupied(mut entry) => {
+                // wanted by another row as well
+                match (entry.get_mut(), interest) {
+                    (Some(interests), Some(interest)) => interests.push(interest),
+                    (wanted, None) => *wanted = None,
+                    (None, Some(_)) => {}
+                }
+                return;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(interest.map(|interest| vec![interest]));
+            }
+        }
+        batch.queued.push(key);
+        if mem::replace(&mut batch.flushing, true) {
+            return;
+        }
+        drop(batch);
+
+        let toggles = self.clone();
+        tokio_runtime().spawn(async move {
+            loop {
+                tokio::time::sleep(BATCH_DELAY).await;
+                let Some((keys, interests)) = toggles.next_batch() else {
+                    return;
+                };
+                if keys.is_empty() {
+                    continue;
+                }
+                let check = (toggles.check)(keys.clone());
+                match with_interests(Priority::Visible, interests, check).await {
+                    Ok(on) => toggles.states.map_mut(|mut states| {
+                        for (key, on) in keys.iter().zip(on) {
+                            states.entry(key.clone()).or_insert(on);
+                        }
+                    }),
+                    Err(e) if e.kind == ApiErrorKind::Cancelled => {}
+                    Err(e) => eprintln!("Failed to check {}: {}", toggles.what, e),
+                }
+                // failed ones are checked again when they're shown again
+                {
+                    let mut batch = toggles.batch.lock().unwrap();
+                    for key in &keys {
+                        batch.requested.remove(key);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Takes the next keys to check together with the interests of their rows, skipping
+    /// those whose rows are gone already. `None` once there's nothing left to check.
+    fn next_batch(&self) -> Option<(Vec<K>, Vec<WeakInterest>)> {
+        let mut batch = self.batch.lock().unwrap();
+        if batch.queued.is_empty() {
+            batch.flushing = false;
+            return None;
+        }
+        let count = batch.queued.len().min(self.batch_size);
+        let queued: Vec<_> = batch.queued.drain(..count).collect();
+
+        let mut keys = Vec::new();
+        // stays empty if any key is wanted regardless
+        let mut interests = Some(Vec::new());
+        for key in queued {
+            match batch.requested.get(&key) {
+                Some(Some(waiting)) if waiting.iter().all(WeakInterest::is_gone) => {
+                    // checked again if it's shown again
+                    batch.requested.remove(&key);
+                    continue;
+                }
+                Some(Some(waiting)) => {
+                    if let Some(interests) = &mut interests {
+                        interests.extend(waiting.iter().cloned());
+                    }
+                }
+                _ => interests = None,
+            }
+            keys.push(key);
+        }
+        Some((keys, interests.unwrap_or_default()))
+    }
+}
@@ -1,37 +1,48 @@
-use std::{sync::Arc, thread};
+use std::{pin::pin, sync::Arc};
 
-use api::{SpotifyContext, SpotifyContextRef};
-use auth::get_token;
+use api::{AuthState, SpotifyContextRef};
+use auth::{logout, store::open_store, AuthError};
 use clap::Parser;
-use cli::Args;
+use cli::{Args, Command};
 use cushy::{
-    value::Dynamic, widget::MakeWidget, window::MakeWindow, Application, Open, PendingApp, Run,
-    TokioRuntime,
+    value::{Destination, Dynamic, Source},
+    widget::{MakeWidget, WidgetInstance},
+    widgets::Space,
+    window::MakeWindow,
+    Application, Open, PendingApp, Run, TokioRuntime,
 };
+use futures_util::future::{self, Either};
 use icons::load_fonts;
-use librespot_connect::{
-    spirc::{Spirc, SpircLoadCommand},
-    state::ConnectStateConfig,
-};
-use librespot_core::{authentication::Credentials, cache::Cache, Session, SessionConfig};
-use librespot_playback::{
-    audio_backend,
-    config::{AudioFormat, PlayerConfig},
-    mixer::{softmixer::SoftMixer, Mixer, MixerConfig, NoOpVolume},
-    player::{Player, PlayerEvent},
-};
-use player::{new_dynamic_player, DynamicPlayer, DynamicPlayerInner};
+use library::Library;
+use paths::{init_paths, paths, Paths};
+use profile::{unless_switched, Profile, ProfileSession, Switchable};
+use tokio::sync::mpsc;
 use widgets::{
-    library::playlist::playlists_widget, pages::liked::LikedSongsPage, playback::bar::bar,
-    ActivePage,
+    error::{error_banner, error_toast},
+    library::{
+        artist::followed_artists_widget, playlist::playlists_widget, profile::profiles_widget,
+        sync::sync_status_widget,
+    },
+    pages::{
+        album::AlbumPage, artist::ArtistPage, episode::EpisodePage, liked::LikedSongsPage,
+        playlist::PlaylistPage, search::SearchPage, show::ShowPage, shows::ShowsPage,
+        stats::StatsPage,
+    },
+    playback::{bar::bar, queue::queue_panel},
+    ActivePage, AppContext,
 };
 
 mod api;
 mod auth;
 mod cli;
 mod icons;
+mod library;
 mod nodebug;
+mod paths;
 mod player;
+mod profile;
+mod queue;
+mod radio;
 mod rt;
 mod theme;
 mod vibrancy;
@@ -39,95 +50,268 @@ mod widgets;
 
 fn main() -> cushy::Result {
     let args = Args::parse();
-    let app = PendingApp::new(TokioRuntime::default());
+    let mut app = PendingApp::new(TokioRuntime::default());
     // doesn't load fonts correctly yet, cushy bug
     // load_fonts(app.cushy().fonts());
 
-    let token = get_token().unwrap();
+    let resolved_paths = Paths::resolve(&args);
+    if let Err(e) = resolved_paths.create_dirs() {
+        eprintln!("Failed to create data directories: {}", e);
+    }
+    init_paths(resolved_paths);
+    paths().migrate_legacy(&args);
 
-    let cache = match Cache::new(None, Some("./cache/volume"), Some("./cache/audio"), None) {
-        Ok(cache) => Some(cache),
-        Err(e) => {
-            eprintln!("Failed to create cache: {}", e);
-            None
+    if let Some(Command::Logout) = args.command {
+        let result = open_store(&args, &args.profile)
+            .map_err(AuthError::from)
+            .and_then(|store| logout(&*store));
+        match result {
+            Ok(()) => println!("Logged out of profile {}", args.profile),
+            Err(e) => eprintln!("Failed to log out: {}", e),
         }
-    };
-    let session_config = SessionConfig::default();
-    let player_config = PlayerConfig::default();
-    let audio_format = AudioFormat::default();
-    let credentials = Credentials::with_access_token(&token.access_token);
-    let default_connect_config = ConnectStateConfig::default();
-    let connect_config = ConnectStateConfig {
-        name: "Despot".to_string(),
-        device_type: librespot_core::config::DeviceType::Computer,
-        volume_steps: 256,
-        initial_volume: cache
-            .as_ref()
-            .and_then(Cache::volume)
-            .map(Into::into)
-            .unwrap_or(default_connect_config.initial_volume),
-        ..Default::default()
-    };
-    let backend = audio_backend::find(None).unwrap();
-
-    let session;
+        return Ok(());
+    }
+
+    let active_profile = Dynamic::new(args.profile.clone());
+    let content = Dynamic::new("Logging in...".centered().make_widget());
 
     {
         let guard = app.cushy().enter_runtime();
-        session = Session::new(session_config, cache);
+        tokio::spawn(run_profiles(args, active_profile, content.clone()));
+        drop(guard);
+    }
 
-        dbg!(session.user_data());
+    let win = content.expand().into_window();
+    load_fonts(&win.fonts);
+    win.open(&mut app).unwrap();
 
-        let player = Player::new(
-            player_config,
-            session.clone(),
-            Box::new(NoOpVolume),
-            move || backend(None, audio_format),
+    app.run()
+}
+
+/// Logs in the active profile and shows its library, restarting everything whenever
+/// `active_profile` changes or the librespot session lost its connection.
+async fn run_profiles(
+    args: Args,
+    active_profile: Dynamic<String>,
+    content: Dynamic<WidgetInstance>,
+) {
+    let (profile_tx, mut profile_rx) = mpsc::unbounded_channel();
+    active_profile
+        .for_each({
+            let profile_tx = profile_tx.clone();
+            move |name| {
+                let _ = profile_tx.send(name.clone());
+            }
+        })
+        .persist();
+
+    // sent by a session that lost its connection
+    let (reconnect_tx, mut reconnect_rx) = mpsc::unbounded_channel();
+
+    let mut name = active_profile.get();
+    let mut running: Option<ProfileSession> = None;
+    let mut reconnection = None;
+    loop {
+        if let Some(session) = running.take() {
+            session.shutdown().await;
+        }
+        // another profile can be picked while waiting for the user to log in
+        content.set(
+            format!("Logging in to profile {name}...")
+                .and(profiles_widget(Profile::list(), active_profile.clone()))
+                .into_rows()
+                .centered()
+                .make_widget(),
         );
 
-        let dynplayer = new_dynamic_player(player.clone());
-        let context = SpotifyContextRef::new(SpotifyContext::new(
-            session.clone(),
-            token,
-            dynplayer.clone(),
-        ));
-
-        let mut app = app.as_app();
-        tokio::spawn(async move {
-            let (_spirc, spirc_task) = Spirc::new(
-                connect_config,
-                session.clone(),
-                credentials,
-                player,
-                Arc::new(SoftMixer::open(MixerConfig::default())),
-            )
-            .await
-            .unwrap();
-            // this cannot happen in `{}` inside join for some reason
-            let dynplayer2 = dynplayer.clone();
-            tokio::join!(spirc_task, dynplayer2.run(), async move {
-                let user = context.current_user().await.unwrap();
-                dbg!(&user);
-
-                let playlists = context.current_user_playlists(None, None).await.unwrap();
-
-                let selected_page = Dynamic::new(ActivePage::default());
-
-                let win = playlists_widget(playlists.items, selected_page)
-                    .and(LikedSongsPage::new(context.clone()).into_widget())
-                    .into_columns()
-                    .expand()
-                    .and(bar(dynplayer))
-                    .into_rows()
-                    .expand()
-                    .into_window();
-                load_fonts(&win.fonts);
-                win.open(&mut app).unwrap();
-            });
-        });
+        let login = match reconnection.take() {
+            Some(logged_in) => Switchable::Done(Ok(logged_in)),
+            None => {
+                let login = ProfileSession::log_in(Profile::load(&name), &args);
+                unless_switched(&name, login, &mut profile_rx).await
+            }
+        };
+        let started = match login {
+            Switchable::Done(Ok(logged_in)) => {
+                // from a session that's gone already
+                while reconnect_rx.try_recv().is_ok() {}
+                let reconnect_tx = reconnect_tx.clone();
+                ProfileSession::start(logged_in, &args, move || {
+                    let _ = reconnect_tx.send(());
+                })
+                .await
+            }
+            Switchable::Done(Err(e)) => Err(e),
+            Switchable::Switched(next) => {
+                name = next;
+                continue;
+            }
+        };
+        match started {
+            Ok(session) => {
+                let context = session.context.clone();
+                let relogin = {
+                    let profile_tx = profile_tx.clone();
+                    let name = name.clone();
+                    move || {
+                        let _ = profile_tx.send(name.clone());
+                    }
+                };
+                let app = AppContext {
+                    likes: session.likes.clone(),
+                    follows: session.follows.clone(),
+                    queue: session.queue.clone(),
+                    radio: session.radio.clone(),
+                    selected_page: Dynamic::new(ActivePage::default()),
+                };
+                content.set(library(
+                    context,
+                    session.library.clone(),
+                    app,
+                    active_profile.clone(),
+                    relogin,
+                ));
+                running = Some(session);
+            }
+            Err(e) => {
+                eprintln!("Failed to start profile {}: {}", name, e);
+                content.set(
+                    format!("Failed to log in to profile {name}: {e}")
+                        .and(profiles_widget(Profile::list(), active_profile.clone()))
+                        .into_rows()
+                        .centered()
+                        .make_widget(),
+                );
+            }
+        }
 
-        drop(guard);
+        let next = match &running {
+            Some(session) => {
+                match future::select(pin!(profile_rx.recv()), pin!(reconnect_rx.recv())).await {
+                    Either::Left((next, _)) => next,
+                    Either::Right(_) => {
+                        eprintln!("Lost the connection to Spotify, reconnecting");
+                        // with the current token, a new login only if it expired
+                        reconnection = session.reconnection().await;
+                        continue;
+                    }
+                }
+            }
+            None => profile_rx.recv().await,
+        };
+        let Some(next) = next else {
+            break;
+        };
+        name = next;
     }
+}
 
-    app.run()
+fn library(
+    context: SpotifyContextRef,
+    library: Library,
+    app: AppContext,
+    active_profile: Dynamic<String>,
+    relogin: impl Fn() + Send + Sync + 'static,
+) -> WidgetInstance {
+    // kept around so switching back doesn't start over
+    let liked_songs = LikedSongsPage::new(context.clone(), &library, &app)
+        .into_widget()
+        .make_widget();
+    // keeps the query and results while browsing them
+    let search = SearchPage::new(context.clone(), &app)
+        .into_widget()
+        .make_widget();
+    let page = app.selected_page.map_each({
+        let context = context.clone();
+        let library = library.clone();
+        let app = app.clone();
+        move |page| match page {
+            ActivePage::LikedSongs => liked_songs.clone(),
+            ActivePage::Search => search.clone(),
+            ActivePage::Stats => StatsPage::new(context.clone(), &app)
+                .into_widget()
+                .make_widget(),
+            ActivePage::Playlist(playlist) => {
+                PlaylistPage::new(context.clone(), &library, playlist.clone(), &app)
+                    .into_widget()
+                    .make_widget()
+            }
+            ActivePage::Album(album) => AlbumPage::new(context.clone(), album.clone(), &app)
+                .into_widget()
+                .make_widget(),
+            ActivePage::Artist(artist) => ArtistPage::new(context.clone(), artist.clone(), &app)
+                .into_widget()
+                .make_widget(),
+            ActivePage::Shows => ShowsPage::new(context.clone(), &app)
+                .into_widget()
+                .make_widget(),
+            ActivePage::Show(show) => ShowPage::new(context.clone(), show.clone(), &app)
+                .into_widget()
+                .make_widget(),
+            ActivePage::Episode(episode) => {
+                EpisodePage::new(context.clone(), episode.clone(), &app)
+                    .into_widget()
+                    .make_widget()
+            }
+        }
+    });
+
+    let queue_open = Dynamic::new(false);
+    let queue_view = queue_open.map_each({
+        let app = app.clone();
+        move |open| {
+            if *open {
+                queue_panel(&app.queue, &app.radio).make_widget()
+            } else {
+                Space::clear().make_widget()
+            }
+        }
+    });
+
+    let library_view = profiles_widget(Profile::list(), active_profile)
+        .and(
+            playlists_widget(context.clone(), &library, app.selected_page.clone())
+                .expand_vertically(),
+        )
+        .and(followed_artists_widget(
+            &app.follows,
+            app.selected_page.clone(),
+        ))
+        .and(sync_status_widget(library.progress.clone()))
+        .into_rows()
+        .and(page.expand())
+        .and(queue_view)
+        .into_columns()
+        .expand();
+
+    auth_banner(&context, relogin)
+        .and(error_banner(library.sync_error.clone()))
+        .and(library_view)
+        .and(error_toast(app.likes.error.clone()))
+        .and(error_toast(app.follows.error.clone()))
+        .and(error_toast(app.queue.error.clone()))
+        .and(error_toast(app.radio.error.clone()))
+        .and(bar(context.clone(), &app, queue_open))
+        .into_rows()
+        .expand()
+        .make_widget()
+}
+
+/// Tells the user when the session expired, offering to log in again.
+fn auth_banner(
+    context: &SpotifyContextRef,
+    relogin: impl Fn() + Send + Sync + 'static,
+) -> impl MakeWidget {
+    let relogin = Arc::new(relogin);
+    context.auth_state.map_each(move |state| match state {
+        AuthState::LoggedIn => Space::clear().make_widget(),
+        AuthState::ReloginRequired => {
+            let relogin = relogin.clone();
+            "Your session expired, re-login required."
+                .and("Log in again".into_button().on_click(move |_| relogin()))
+                .into_columns()
+                .centered()
+                .make_widget()
+        }
+    })
 }
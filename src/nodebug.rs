@@ -10,6 +10,17 @@ impl<T> Debug for NoDebug<T> {
     }
 }
 
+impl<T> Clone for NoDebug<T>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
 impl<T> From<T> for NoDebug<T> {
     fn from(value: T) -> Self {
         Self { inner: value }
@@ -0,0 +1,187 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::{auth::store::open_store, cli::Args, profile::DEFAULT_PROFILE};
+
+const APP_DIR: &str = "despot";
+
+/// Directory older versions kept all of their caches in, relative to the working directory.
+const LEGACY_CACHE_DIR: &str = "./cache";
+/// File older versions kept the refresh token in, in plaintext, relative to the working
+/// directory.
+const LEGACY_REFRESH_TOKEN_FILE: &str = "./refresh_token.txt";
+
+static PATHS: OnceLock<Paths> = OnceLock::new();
+
+/// Base directories used by despot, following the XDG base directory specification.
+#[derive(Debug, Clone)]
+pub struct Paths {
+    /// User editable settings, `$XDG_CONFIG_HOME/despot`.
+    pub config: PathBuf,
+    /// Data that can be thrown away at any time, `$XDG_CACHE_HOME/despot`.
+    pub cache: PathBuf,
+    /// Data that should survive restarts, like credentials, `$XDG_STATE_HOME/despot`.
+    pub state: PathBuf,
+}
+
+impl Paths {
+    /// Resolves the directories from the command line, falling back to the XDG environment.
+    pub fn resolve(args: &Args) -> Self {
+        Self {
+            config: args
+                .config_dir
+                .clone()
+                .unwrap_or_else(|| xdg_dir("XDG_CONFIG_HOME", ".config").join(APP_DIR)),
+            cache: args
+                .cache_dir
+                .clone()
+                .unwrap_or_else(|| xdg_dir("XDG_CACHE_HOME", ".cache").join(APP_DIR)),
+            state: args
+                .state_dir
+                .clone()
+                .unwrap_or_else(|| xdg_dir("XDG_STATE_HOME", ".local/state").join(APP_DIR)),
+        }
+    }
+
+    /// Creates all base directories.
+    pub fn create_dirs(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.config)?;
+        fs::create_dir_all(&self.cache)?;
+        fs::create_dir_all(&self.state)
+    }
+
+    /// Settings of a single profile, see [`crate::profile::Profile`].
+    pub fn profile_settings(&self, profile: &str) -> PathBuf {
+        self.config.join("profiles").join(format!("{profile}.toml"))
+    }
+
+    /// Directory with the state of every profile, one subdirectory per profile.
+    pub fn profiles_state(&self) -> PathBuf {
+        self.state.join("profiles")
+    }
+
+    pub fn credentials_file(&self, profile: &str) -> PathBuf {
+        self.profiles_state().join(profile).join("credentials.bin")
+    }
+
+    pub fn volume_cache(&self, profile: &str) -> PathBuf {
+        self.cache.join("profiles").join(profile).join("volume")
+    }
+
+    pub fn audio_cache(&self, profile: &str) -> PathBuf {
+        self.cache.join("profiles").join(profile).join("audio")
+    }
+
+    /// Local copy of the profile's library.
+    pub fn library_db(&self, profile: &str) -> PathBuf {
+        self.cache.join("profiles").join(profile).join("library.sqlite")
+    }
+
+    /// Cache of downloaded images, shared by all profiles.
+    pub fn http_cache(&self) -> PathBuf {
+        self.cache.join("http-cacache")
+    }
+
+    /// Moves data left in the working directory by older versions into the new locations,
+    /// assigning it to the default profile. The refresh token goes into the credential store
+    /// selected by `args`, so this must be called after [`init_paths`].
+    /// Anything that already exists at the new location is left alone.
+    pub fn migrate_legacy(&self, args: &Args) {
+        let legacy_cache = Path::new(LEGACY_CACHE_DIR);
+        for (legacy, new) in [
+            (legacy_cache.join("volume"), self.volume_cache(DEFAULT_PROFILE)),
+            (legacy_cache.join("audio"), self.audio_cache(DEFAULT_PROFILE)),
+            (legacy_cache.join("http-cacache"), self.http_cache()),
+        ] {
+            if !legacy.exists() || new.exists() {
+                continue;
+            }
+            match move_path(&legacy, &new) {
+                Ok(()) => println!("Migrated {} to {}", legacy.display(), new.display()),
+                Err(e) => eprintln!("Failed to migrate {}: {}", legacy.display(), e),
+            }
+        }
+        // only succeeds once everything was moved out
+        let _ = fs::remove_dir(legacy_cache);
+
+        migrate_legacy_refresh_token(args);
+    }
+}
+
+/// Sets the global paths. Must be called once, before [`paths`] is used.
+pub fn init_paths(paths: Paths) {
+    PATHS.set(paths).expect("paths initialized twice");
+}
+
+/// Returns the global paths set by [`init_paths`].
+pub fn paths() -> &'static Paths {
+    PATHS.get().expect("paths not initialized")
+}
+
+/// Moves the plaintext refresh token into the default profile's credential store, unless that
+/// has one already.
+fn migrate_legacy_refresh_token(args: &Args) {
+    let Ok(token) = fs::read_to_string(LEGACY_REFRESH_TOKEN_FILE) else {
+        return;
+    };
+    let token = token.trim();
+    let result = open_store(args, DEFAULT_PROFILE).and_then(|store| {
+        if !token.is_empty() && store.load()?.is_none() {
+            store.save(token)?;
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to migrate {}: {}", LEGACY_REFRESH_TOKEN_FILE, e);
+        return;
+    }
+    match fs::remove_file(LEGACY_REFRESH_TOKEN_FILE) {
+        Ok(()) => println!("Migrated {} to the credential store", LEGACY_REFRESH_TOKEN_FILE),
+        Err(e) => eprintln!("Failed to remove {}: {}", LEGACY_REFRESH_TOKEN_FILE, e),
+    }
+}
+
+/// Reads an XDG base directory variable, using `$HOME/<fallback>` when it's unset or relative.
+fn xdg_dir(var: &str, fallback: &str) -> PathBuf {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .unwrap_or_else(|| {
+            env::var_os("HOME")
+                .map(PathBuf::from)
+                .unwrap_or_default()
+                .join(fallback)
+        })
+}
+
+/// Renames `from` to `to`, copying instead when they're on different filesystems.
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_recursive(from, to)?;
+    if from.is_dir() {
+        fs::remove_dir_all(from)
+    } else {
+        fs::remove_file(from)
+    }
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
@@ -0,0 +1,403 @@
+use std::{
+    fs,
+    future::Future,
+    io,
+    pin::pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures_util::future::{self, Either};
+use librespot_connect::{spirc::Spirc, state::ConnectStateConfig};
+use librespot_core::{authentication::Credentials, cache::Cache, Session, SessionConfig};
+use librespot_oauth::OAuthToken;
+use librespot_playback::{
+    audio_backend,
+    config::{AudioFormat, PlayerConfig},
+    mixer::{softmixer::SoftMixer, Mixer, MixerConfig, NoOpVolume},
+    player::Player,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{
+    api::{with_priority, Priority, RetryPolicy, SpotifyContext, SpotifyContextRef},
+    auth::{
+        get_token, AuthConfig, AuthError,
+        store::{open_store, CredentialStore, StoreError},
+    },
+    cli::Args,
+    library::{Follows, Library, Likes},
+    paths::paths,
+    player::new_dynamic_player,
+    queue::Queue,
+    radio::Radio,
+};
+
+#[cfg(test)]
+mod tests;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Whether `name` can name a profile. Names become file and directory names, so only ASCII
+/// letters, digits, `_` and `-` are allowed.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// How long to wait for Spirc to say goodbye to other devices when switching profiles.
+const SPIRC_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+/// How often to check whether the librespot session lost its connection.
+const CONNECTION_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Per-profile settings, stored in `$XDG_CONFIG_HOME/despot/profiles/<name>.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileSettings {
+    /// Name shown to other Spotify Connect devices.
+    pub device_name: Option<String>,
+}
+
+/// A named Spotify account with its own credentials, caches and Connect device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub settings: ProfileSettings,
+}
+
+impl Profile {
+    /// Loads the profile `name`. Unknown profiles start with default settings.
+    pub fn load(name: &str) -> Self {
+        let settings = match fs::read_to_string(paths().profile_settings(name)) {
+            Ok(settings) => toml::from_str(&settings).unwrap_or_else(|e| {
+                eprintln!("Invalid settings for profile {}: {}", name, e);
+                ProfileSettings::default()
+            }),
+            Err(_) => ProfileSettings::default(),
+        };
+        Self {
+            name: name.to_string(),
+            settings,
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = paths().profile_settings(&self.name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let settings = toml::to_string_pretty(&self.settings)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, settings)
+    }
+
+    /// Names of all known profiles, sorted, always including the default one.
+    pub fn list() -> Vec<String> {
+        let mut names = vec![DEFAULT_PROFILE.to_string()];
+        let settings = fs::read_dir(paths().config.join("profiles"))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                (path.extension()? == "toml")
+                    .then(|| path.file_stem()?.to_str().map(str::to_string))
+                    .flatten()
+            });
+        let states = fs::read_dir(paths().profiles_state())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().to_str().map(str::to_string));
+        names.extend(settings.chain(states).filter(|name| is_valid_name(name)));
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    pub fn device_name(&self) -> String {
+        match &self.settings.device_name {
+            Some(name) => name.clone(),
+            None if self.name == DEFAULT_PROFILE => "Despot".to_string(),
+            None => format!("Despot ({})", self.name),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("failed to open credential store: {0}")]
+    Store(#[from] StoreError),
+    #[error("login failed: {0}")]
+    Auth(#[from] AuthError),
+    #[error("failed to start spotify connect: {0}")]
+    Connect(#[from] librespot_core::Error),
+}
+
+/// A profile that logged in, ready for [`ProfileSession::start`].
+pub struct LoggedIn {
+    profile: Profile,
+    token: OAuthToken,
+    auth_config: AuthConfig,
+    store: Arc<dyn CredentialStore>,
+}
+
+/// How waiting for something that can be given up on ended, see [`unless_switched`].
+#[derive(Debug, PartialEq)]
+pub enum Switchable<T> {
+    Done(T),
+    /// Another profile was picked in the meantime.
+    Switched(String),
+}
+
+/// Waits for `login` of the profile `current`, unless another profile arrives on `switches`
+/// first. Picking `current` again keeps waiting.
+pub async fn unless_switched<T>(
+    current: &str,
+    login: impl Future<Output = T>,
+    switches: &mut mpsc::UnboundedReceiver<String>,
+) -> Switchable<T> {
+    let switched = async {
+        loop {
+            match switches.recv().await {
+                Some(name) if name != current => return name,
+                Some(_) => {}
+                // nothing can be picked anymore
+                None => future::pending::<()>().await,
+            }
+        }
+    };
+    match future::select(pin!(login), pin!(switched)).await {
+        Either::Left((done, _)) => Switchable::Done(done),
+        Either::Right((name, _)) => Switchable::Switched(name),
+    }
+}
+
+/// Everything that's running on behalf of a logged in profile.
+pub struct ProfileSession {
+    pub profile: Profile,
+    pub context: SpotifyContextRef,
+    pub library: Library,
+    pub likes: Likes,
+    pub follows: Follows,
+    pub queue: Queue,
+    pub radio: Radio,
+    auth_config: AuthConfig,
+    store: Arc<dyn CredentialStore>,
+    session: Session,
+    spirc: Spirc,
+    spirc_task: JoinHandle<()>,
+    player_task: JoinHandle<()>,
+    queue_task: JoinHandle<()>,
+    radio_task: JoinHandle<()>,
+    token_refresh_task: JoinHandle<()>,
+    sync_task: JoinHandle<()>,
+    connection_task: JoinHandle<()>,
+}
+
+impl ProfileSession {
+    /// Logs `profile` in, which may wait for the user to log in through the browser or on
+    /// stdin. That can't be interrupted, so when this is dropped the login still finishes in
+    /// the background, and its refresh token is stored for `profile` as usual.
+    pub async fn log_in(profile: Profile, args: &Args) -> Result<LoggedIn, SessionError> {
+        let auth_config = AuthConfig::from_args(args);
+        let store = open_store(args, &profile.name)?;
+        let token = tokio::task::spawn_blocking({
+            let store = store.clone();
+            let auth_config = auth_config.clone();
+            move || get_token(&*store, &auth_config)
+        })
+        .await
+        .expect("login task panicked")?;
+        Ok(LoggedIn {
+            profile,
+            token,
+            auth_config,
+            store,
+        })
+    }
+
+    /// Starts the librespot session, player and Connect device of a logged in profile.
+    /// `disconnected` is called once the session lost its connection, see
+    /// [`ProfileSession::reconnection`]. Must be called within the tokio runtime.
+    pub async fn start(
+        logged_in: LoggedIn,
+        args: &Args,
+        disconnected: impl FnOnce() + Send + 'static,
+    ) -> Result<Self, SessionError> {
+        let LoggedIn {
+            profile,
+            token,
+            auth_config,
+            store,
+        } = logged_in;
+
+        let cache = match Cache::new(
+            None,
+            Some(paths().volume_cache(&profile.name)),
+            Some(paths().audio_cache(&profile.name)),
+            None,
+        ) {
+            Ok(cache) => Some(cache),
+            Err(e) => {
+                eprintln!("Failed to create cache: {}", e);
+                None
+            }
+        };
+        let session_config = SessionConfig {
+            client_id: auth_config.client_id.clone(),
+            ..Default::default()
+        };
+        let player_config = PlayerConfig::default();
+        let audio_format = AudioFormat::default();
+        let credentials = Credentials::with_access_token(&token.access_token);
+        let default_connect_config = ConnectStateConfig::default();
+        let connect_config = ConnectStateConfig {
+            name: profile.device_name(),
+            device_type: librespot_core::config::DeviceType::Computer,
+            volume_steps: 256,
+            initial_volume: cache
+                .as_ref()
+                .and_then(Cache::volume)
+                .map(Into::into)
+                .unwrap_or(default_connect_config.initial_volume),
+            ..Default::default()
+        };
+        let backend = audio_backend::find(None).unwrap();
+
+        let session = Session::new(session_config, cache);
+
+        let player = Player::new(
+            player_config,
+            session.clone(),
+            Box::new(NoOpVolume),
+            move || backend(None, audio_format),
+        );
+
+        let dynplayer = new_dynamic_player(player.clone());
+        let context = SpotifyContextRef::new(SpotifyContext::new(
+            session.clone(),
+            token,
+            auth_config,
+            store,
+            RetryPolicy::from_args(args),
+            dynplayer.clone(),
+        ));
+
+        let (spirc, spirc_task) = Spirc::new(
+            connect_config,
+            session.clone(),
+            credentials,
+            player,
+            Arc::new(SoftMixer::open(MixerConfig::default())),
+        )
+        .await?;
+        let spirc_task = tokio::spawn(spirc_task);
+        let player_task = tokio::spawn(async move { dynplayer.run().await });
+        let queue = Queue::new(context.clone());
+        let queue_task = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.run().await }
+        });
+        let radio = Radio::new(context.clone(), queue.clone());
+        let radio_task = tokio::spawn({
+            let radio = radio.clone();
+            async move { radio.run().await }
+        });
+        let token_refresh_task = tokio::spawn({
+            let context = context.clone();
+            async move { context.run_token_refresh().await }
+        });
+
+        let connection_task = tokio::spawn({
+            let session = session.clone();
+            async move {
+                while !session.is_invalid() {
+                    tokio::time::sleep(CONNECTION_CHECK_INTERVAL).await;
+                }
+                disconnected();
+            }
+        });
+
+        let library = tokio::task::spawn_blocking({
+            let name = profile.name.clone();
+            move || Library::open(&name)
+        })
+        .await
+        .expect("opening the library panicked");
+        let likes = Likes::new(context.clone(), &library);
+        let follows = Follows::new(context.clone(), &library);
+        let sync_task = tokio::spawn({
+            let library = library.clone();
+            let follows = follows.clone();
+            let context = context.clone();
+            async move {
+                library.sync(&context).await;
+                with_priority(Priority::Prefetch, None, follows.sync()).await;
+            }
+        });
+
+        Ok(Self {
+            profile,
+            context,
+            library,
+            likes,
+            follows,
+            queue,
+            radio,
+            auth_config,
+            store,
+            session,
+            spirc,
+            spirc_task,
+            player_task,
+            queue_task,
+            radio_task,
+            token_refresh_task,
+            sync_task,
+            connection_task,
+        })
+    }
+
+    /// Logs the profile in again with the current access token, to start over after the
+    /// session lost its connection. `None` if that token expired, a new login is needed then.
+    pub async fn reconnection(&self) -> Option<LoggedIn> {
+        let token = self.context.current_token().await;
+        if token.expires_at <= Instant::now() {
+            return None;
+        }
+        Some(LoggedIn {
+            profile: self.profile.clone(),
+            token,
+            auth_config: self.auth_config.clone(),
+            store: self.store.clone(),
+        })
+    }
+
+    /// Stops playback and disconnects the profile from Spotify.
+    pub async fn shutdown(self) {
+        // shutting down invalidates the session, which isn't losing the connection
+        self.connection_task.abort();
+        self.context.player.player.stop();
+        if let Err(e) = self.spirc.shutdown() {
+            eprintln!("Failed to shut down spirc: {}", e);
+        }
+        let spirc_abort = self.spirc_task.abort_handle();
+        if tokio::time::timeout(SPIRC_SHUTDOWN_TIMEOUT, self.spirc_task)
+            .await
+            .is_err()
+        {
+            spirc_abort.abort();
+        }
+        self.player_task.abort();
+        self.queue_task.abort();
+        self.radio_task.abort();
+        self.token_refresh_task.abort();
+        self.sync_task.abort();
+        self.session.shutdown();
+    }
+}
@@ -0,0 +1,92 @@
+use std::{sync::mpsc as std_mpsc, time::Duration};
+
+use futures_util::future;
+use tokio::{runtime, sync::mpsc};
+
+use super::{unless_switched, Switchable};
+
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn a_finished_login_is_returned() {
+    let (_tx, mut switches) = mpsc::unbounded_channel::<String>();
+
+    let result = block_on(unless_switched("default", future::ready(1), &mut switches));
+
+    assert_eq!(result, Switchable::Done(1));
+}
+
+#[test]
+fn picking_another_profile_gives_up_on_a_waiting_login() {
+    let (tx, mut switches) = mpsc::unbounded_channel();
+    tx.send("work".to_string()).unwrap();
+
+    let result = block_on(unless_switched(
+        "default",
+        future::pending::<()>(),
+        &mut switches,
+    ));
+
+    assert_eq!(result, Switchable::Switched("work".to_string()));
+}
+
+#[test]
+fn a_blocked_login_thread_does_not_hold_up_the_switch() {
+    let (tx, mut switches) = mpsc::unbounded_channel();
+    // stands in for a login waiting on the browser or stdin
+    let (release, blocked) = std_mpsc::channel::<()>();
+
+    let result = block_on(async {
+        let login = tokio::task::spawn_blocking(move || blocked.recv().is_ok());
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            tx.send("work".to_string()).unwrap();
+        });
+        let result = unless_switched("default", login, &mut switches).await;
+        // the runtime waits for its blocking threads when it shuts down
+        release.send(()).unwrap();
+        result
+    });
+
+    assert!(matches!(result, Switchable::Switched(name) if name == "work"));
+}
+
+#[test]
+fn picking_the_same_profile_keeps_waiting() {
+    let (tx, mut switches) = mpsc::unbounded_channel();
+    tx.send("default".to_string()).unwrap();
+
+    let result = block_on(unless_switched(
+        "default",
+        async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            1
+        },
+        &mut switches,
+    ));
+
+    assert_eq!(result, Switchable::Done(1));
+}
+
+#[test]
+fn a_closed_channel_keeps_waiting_for_the_login() {
+    let (tx, mut switches) = mpsc::unbounded_channel::<String>();
+    drop(tx);
+
+    let result = block_on(unless_switched(
+        "default",
+        async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            1
+        },
+        &mut switches,
+    ));
+
+    assert_eq!(result, Switchable::Done(1));
+}
@@ -0,0 +1,410 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use cushy::value::{Destination, Dynamic, Source};
+use futures_util::{future, StreamExt};
+use librespot_protocol::connect::ClusterUpdate;
+use rspotify::model::{EpisodeId, PlayableId, PlayableItem, TrackId};
+use rspotify::prelude::*;
+use tokio::sync::mpsc;
+
+use crate::{
+    api::{with_priority, ApiError, ApiErrorKind, Priority, ProvidedTrack, SpotifyContextRef},
+    nodebug::NoDebug,
+    rt::tokio_runtime,
+};
+
+/// Provider of the tracks the user queued, which play before the rest.
+const QUEUED: &str = "queue";
+/// Provider of the tracks the Web API tells about, which could be queued or not.
+const UNKNOWN: &str = "context";
+/// The most tracks or episodes looked up by a single request.
+const LOOKUP_BATCH: usize = 50;
+
+/// What's playing on the active device and what comes after it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueueState {
+    /// Uri of what's playing.
+    pub current: Option<String>,
+    /// Everything that plays next, the tracks the user queued first.
+    pub next_tracks: Vec<ProvidedTrack>,
+    prev_tracks: Vec<ProvidedTrack>,
+    revision: String,
+    /// The Connect device that's playing. `None` if the queue is only known from the Web API,
+    /// which can't change it.
+    device_id: Option<String>,
+}
+
+impl QueueState {
+    /// Positions in `next_tracks` of the tracks the user queued.
+    pub fn queued(&self) -> Vec<usize> {
+        self.positions(|track| track.provider == QUEUED)
+    }
+
+    /// Positions in `next_tracks` of the rest of what's playing and what autoplay picked.
+    pub fn upcoming(&self) -> Vec<usize> {
+        self.positions(|track| track.provider != QUEUED)
+    }
+
+    /// Whether tracks can be removed and moved, which needs Connect.
+    pub fn editable(&self) -> bool {
+        self.device_id.is_some()
+    }
+
+    fn positions(&self, filter: impl Fn(&ProvidedTrack) -> bool) -> Vec<usize> {
+        self.next_tracks
+            .iter()
+            .enumerate()
+            // markers between the context and autoplay aren't tracks
+            .filter(|(_, track)| playable_id(&track.uri).is_some() && filter(track))
+            .map(|(position, _)| position)
+            .collect()
+    }
+}
+
+/// The play queue of the active device, as Spotify Connect tells it, so it's the same as
+/// other devices show. Until Connect tells about a change, it's taken from the Web API.
+#[derive(Debug, Clone)]
+pub struct Queue {
+    pub state: Dynamic<QueueState>,
+    /// Tracks and episodes of the queue by uri, looked up as they show up in it.
+    items: Dynamic<HashMap<String, PlayableItem>>,
+    /// Uris being looked up.
+    requested: Arc<Mutex<HashSet<String>>>,
+    /// Error of the last failed change, the change was undone.
+    pub error: Dynamic<Option<ApiError>>,
+
+    context: NoDebug<SpotifyContextRef>,
+}
+
+impl Queue {
+    pub fn new(context: SpotifyContextRef) -> Self {
+        Self {
+            state: Default::default(),
+            items: Default::default(),
+            requested: Default::default(),
+            error: Default::default(),
+            context: context.into(),
+        }
+    }
+
+    /// The track or episode `uri`, `None` while it's looked up.
+    pub fn item(&self, uri: &str) -> Dynamic<Option<PlayableItem>> {
+        let uri = uri.to_string();
+        self.items.map_each(move |items| items.get(&uri).cloned())
+    }
+
+    /// Follows the queue of the active device. Run this only once per queue.
+    pub async fn run(&self) {
+        let mut updates = match self.context.connect_cluster() {
+            Ok(updates) => Some(Box::pin(updates)),
+            Err(e) => {
+                eprintln!("Failed to follow the Connect state: {}", e);
+                None
+            }
+        };
+        // the Web API doesn't tell about changes, it's asked again whenever the track changes
+        let (track_tx, mut track_changed) = mpsc::unbounded_channel();
+        self.context
+            .player
+            .track
+            .for_each(move |_| {
+                let _ = track_tx.send(());
+            })
+            .persist();
+        self.refresh().await;
+
+        loop {
+            let update = async {
+                match updates.as_mut() {
+                    Some(updates) => updates.next().await,
+                    None => future::pending().await,
+                }
+            };
+            tokio::select! {
+                update = update => match update {
+                    Some(Ok(update)) => self.update(&update),
+                    Some(Err(e)) => eprintln!("Invalid Connect state: {}", e),
+                    None => {
+                        eprintln!("Connect state updates stopped");
+                        updates = None;
+                    }
+                },
+                Some(()) = track_changed.recv() => {
+                    if !self.state.map_ref(QueueState::editable) {
+                        self.refresh().await;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds `uri` to the end of the tracks the user queued.
+    pub fn add(&self, uri: String) {
+        self.extend(vec![uri]);
+    }
+
+    /// Adds `uris` to the end of the tracks the user queued, one after the other so they
+    /// keep their order.
+    pub fn extend(&self, uris: Vec<String>) {
+        let items = uris
+            .into_iter()
+            .filter_map(|uri| Some((playable_id(&uri)?, uri)))
+            .collect::<Vec<_>>();
+        if items.is_empty() {
+            return;
+        }
+        let device_id = self.state.map_ref(|state| state.device_id.clone());
+        let queue = self.clone();
+        tokio_runtime().spawn(with_priority(Priority::UserAction, None, async move {
+            for (id, uri) in items {
+                let result = match &device_id {
+                    Some(device_id) => queue.context.connect_add_to_queue(device_id, &uri).await,
+                    None => queue.context.add_to_queue(id).await,
+                };
+                match result {
+                    Ok(()) => {}
+                    Err(e) if e.kind == ApiErrorKind::Cancelled => return,
+                    Err(e) => {
+                        eprintln!("Failed to add {} to the queue: {}", uri, e);
+                        queue.error.set(Some(e));
+                        return;
+                    }
+                }
+            }
+            // Connect tells about the change itself
+            if device_id.is_none() {
+                queue.refresh().await;
+            }
+        }));
+    }
+
+    /// Queues `uri` to play right after what's playing, before what was queued already.
+    /// Without Connect, it can only go to the end of the queue.
+    pub fn play_next(&self, uri: String) {
+        if playable_id(&uri).is_none() {
+            return;
+        }
+        if !self.state.map_ref(QueueState::editable) {
+            self.add(uri);
+            return;
+        }
+        self.change(move |next_tracks| {
+            let uid = queued_uid(next_tracks);
+            next_tracks.insert(
+                0,
+                ProvidedTrack {
+                    uri,
+                    uid,
+                    provider: QUEUED.to_string(),
+                    metadata: HashMap::new(),
+                },
+            );
+        });
+    }
+
+    /// Removes the track at `position` in the next tracks.
+    pub fn remove(&self, position: usize) {
+        self.change(move |next_tracks| {
+            if position < next_tracks.len() {
+                next_tracks.remove(position);
+            }
+        });
+    }
+
+    /// Moves the track at `from` in the next tracks to `to`, counted before it's moved.
+    pub fn reorder(&self, from: usize, to: usize) {
+        self.change(move |next_tracks| {
+            if from < next_tracks.len() && to < next_tracks.len() {
+                let track = next_tracks.remove(from);
+                next_tracks.insert(to, track);
+            }
+        });
+    }
+
+    /// Changes the next tracks of the Connect device that's playing, showing the change right
+    /// away and undoing it if it fails.
+    fn change(&self, change: impl FnOnce(&mut Vec<ProvidedTrack>)) {
+        let previous = self.state.get();
+        let Some(device_id) = previous.device_id.clone() else {
+            return;
+        };
+        let mut changed = previous.clone();
+        change(&mut changed.next_tracks);
+        if changed == previous {
+            return;
+        }
+        self.state.set(changed.clone());
+
+        let queue = self.clone();
+        tokio_runtime().spawn(with_priority(Priority::UserAction, None, async move {
+            let result = queue
+                .context
+                .connect_set_queue(
+                    &device_id,
+                    &changed.next_tracks,
+                    &changed.prev_tracks,
+                    &changed.revision,
+                )
+                .await;
+            if let Err(e) = result {
+                eprintln!("Failed to change the queue: {}", e);
+                // unless Connect told about a newer one in the meantime
+                queue.state.map_mut(|mut state| {
+                    if *state == changed {
+                        *state = previous;
+                    }
+                });
+                queue.error.set(Some(e));
+            }
+        }));
+    }
+
+    fn update(&self, update: &ClusterUpdate) {
+        let cluster = &update.cluster;
+        let player = &cluster.player_state;
+        self.set_state(QueueState {
+            current: player
+                .track
+                .as_ref()
+                .map(|track| track.uri.clone())
+                .filter(|uri| !uri.is_empty()),
+            next_tracks: player.next_tracks.iter().map(ProvidedTrack::from).collect(),
+            prev_tracks: player.prev_tracks.iter().map(ProvidedTrack::from).collect(),
+            revision: player.queue_revision.clone(),
+            device_id: Some(cluster.active_device_id.clone()).filter(|id| !id.is_empty()),
+        });
+    }
+
+    /// Takes the queue from the Web API, which doesn't tell the queued tracks from the rest.
+    async fn refresh(&self) {
+        let queue = with_priority(Priority::Visible, None, self.context.current_user_queue());
+        match queue.await {
+            Ok(queue) => {
+                let items = queue
+                    .currently_playing
+                    .iter()
+                    .chain(&queue.queue)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                self.found(items);
+                self.set_state(QueueState {
+                    current: queue.currently_playing.as_ref().and_then(playable_uri),
+                    next_tracks: queue
+                        .queue
+                        .iter()
+                        .filter_map(playable_uri)
+                        .map(|uri| ProvidedTrack {
+                            uri,
+                            uid: String::new(),
+                            provider: UNKNOWN.to_string(),
+                            metadata: HashMap::new(),
+                        })
+                        .collect(),
+                    ..Default::default()
+                });
+            }
+            Err(e) if e.kind == ApiErrorKind::Cancelled => {}
+            Err(e) => eprintln!("Failed to load the queue: {}", e),
+        }
+    }
+
+    fn set_state(&self, state: QueueState) {
+        self.look_up(
+            state
+                .current
+                .iter()
+                .chain(state.next_tracks.iter().map(|track| &track.uri))
+                .cloned()
+                .collect(),
+        );
+        self.state.set(state);
+    }
+
+    /// Looks up the tracks and episodes of `uris` that aren't known yet.
+    fn look_up(&self, uris: Vec<String>) {
+        let uris = {
+            let known = self.items.map_ref(|items| {
+                uris.iter()
+                    .filter(|uri| items.contains_key(*uri))
+                    .cloned()
+                    .collect::<HashSet<_>>()
+            });
+            let mut requested = self.requested.lock().unwrap();
+            uris.into_iter()
+                .filter(|uri| !known.contains(uri) && playable_id(uri).is_some())
+                .filter(|uri| requested.insert(uri.clone()))
+                .collect::<Vec<_>>()
+        };
+        if uris.is_empty() {
+            return;
+        }
+
+        let queue = self.clone();
+        tokio_runtime().spawn(with_priority(Priority::Visible, None, async move {
+            let tracks = uris
+                .iter()
+                .filter_map(|uri| TrackId::from_uri(uri).ok())
+                .collect::<Vec<_>>();
+            let episodes = uris
+                .iter()
+                .filter_map(|uri| EpisodeId::from_uri(uri).ok())
+                .collect::<Vec<_>>();
+            for ids in tracks.chunks(LOOKUP_BATCH) {
+                match queue.context.tracks(ids).await {
+                    Ok(tracks) => queue.found(tracks.into_iter().map(PlayableItem::Track)),
+                    Err(e) => eprintln!("Failed to look up queued tracks: {}", e),
+                }
+            }
+            for ids in episodes.chunks(LOOKUP_BATCH) {
+                match queue.context.episodes(ids).await {
+                    Ok(episodes) => queue.found(episodes.into_iter().map(PlayableItem::Episode)),
+                    Err(e) => eprintln!("Failed to look up queued episodes: {}", e),
+                }
+            }
+            // failed ones are looked up again when the queue changes
+            let mut requested = queue.requested.lock().unwrap();
+            for uri in &uris {
+                requested.remove(uri);
+            }
+        }));
+    }
+
+    fn found(&self, items: impl IntoIterator<Item = PlayableItem>) {
+        self.items.map_mut(|mut known| {
+            for item in items {
+                if let Some(uri) = playable_uri(&item) {
+                    known.insert(uri, item);
+                }
+            }
+        });
+    }
+}
+
+/// The track or episode `uri`, `None` for anything else like local files.
+fn playable_id(uri: &str) -> Option<PlayableId<'static>> {
+    match TrackId::from_uri(uri) {
+        Ok(id) => Some(PlayableId::Track(id.into_static())),
+        Err(_) => EpisodeId::from_uri(uri)
+            .ok()
+            .map(|id| PlayableId::Episode(id.into_static())),
+    }
+}
+
+fn playable_uri(item: &PlayableItem) -> Option<String> {
+    match item {
+        PlayableItem::Track(track) => track.id.as_ref().map(|id| id.uri()),
+        PlayableItem::Episode(episode) => Some(episode.id.uri()),
+    }
+}
+
+/// A uid for a newly queued track, numbered on from the ones queued already like Spotify does.
+fn queued_uid(next_tracks: &[ProvidedTrack]) -> String {
+    let last = next_tracks
+        .iter()
+        .filter_map(|track| track.uid.strip_prefix('q')?.parse::<u64>().ok())
+        .max();
+    format!("q{}", last.map_or(0, |last| last + 1))
+}
@@ -0,0 +1,345 @@
+use std::{
+    collections::HashSet,
+    iter,
+    sync::{Arc, Mutex},
+};
+
+use cushy::value::{Destination, Dynamic, Source};
+use rspotify::model::{ArtistId, PlayableId, PlayableItem, PlaylistId, TrackId};
+use rspotify::prelude::*;
+use tokio::sync::mpsc;
+
+use crate::{
+    api::{with_priority, ApiError, ApiErrorKind, Priority, SpotifyContextRef},
+    nodebug::NoDebug,
+    queue::{Queue, QueueState},
+    rt::tokio_runtime,
+};
+
+/// Tracks picked at a time.
+const BATCH: usize = 20;
+/// More tracks are picked once fewer of the radio's than this are left to play.
+const REFILL_BELOW: usize = 5;
+/// The most seeds recommendations take.
+const MAX_SEEDS: usize = 5;
+/// Tracks of the playing radio that seed the next ones besides what it's based on.
+const RECENT_SEEDS: usize = 2;
+
+/// What a radio plays tracks like.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RadioSeed {
+    Track {
+        id: TrackId<'static>,
+        name: String,
+    },
+    Artist {
+        id: ArtistId<'static>,
+        name: String,
+    },
+    Playlist {
+        id: PlaylistId<'static>,
+        name: String,
+    },
+}
+
+impl RadioSeed {
+    pub fn name(&self) -> &str {
+        match self {
+            RadioSeed::Track { name, .. }
+            | RadioSeed::Artist { name, .. }
+            | RadioSeed::Playlist { name, .. } => name,
+        }
+    }
+
+    fn uri(&self) -> String {
+        match self {
+            RadioSeed::Track { id, .. } => id.uri(),
+            RadioSeed::Artist { id, .. } => id.uri(),
+            RadioSeed::Playlist { id, .. } => id.uri(),
+        }
+    }
+}
+
+/// The radio that's playing.
+#[derive(Debug, Clone)]
+struct Station {
+    seed: RadioSeed,
+    /// Some of the seed playlist's tracks, recommendations don't take playlists.
+    seed_tracks: Vec<TrackId<'static>>,
+    /// Uris of every track the radio picked, so none is picked twice.
+    picked: HashSet<String>,
+    /// Uris of the radio's tracks that played, the latest last.
+    played: Vec<String>,
+    /// Uris of tracks the user queued while the radio played, which don't stop it.
+    queued: HashSet<String>,
+    /// Whether one of its tracks played yet. Until then, what's playing is what played
+    /// before the radio.
+    started: bool,
+    /// What played when tracks were last picked. They're picked at most once per track, as
+    /// Connect tells about them only a while after they were queued.
+    refilled_at: Option<String>,
+}
+
+/// Endless playback of tracks like a track, artist or playlist, picked by Spotify's
+/// recommendations or, where those aren't available, by autoplay. A few are queued at a time
+/// as the queue drains, until something else plays.
+#[derive(Debug, Clone)]
+pub struct Radio {
+    /// What the playing radio is based on, `None` while none plays.
+    pub seed: Dynamic<Option<RadioSeed>>,
+    station: Arc<Mutex<Option<Station>>>,
+    /// Error of the last failed start or refill.
+    pub error: Dynamic<Option<ApiError>>,
+
+    context: NoDebug<SpotifyContextRef>,
+    queue: Queue,
+}
+
+impl Radio {
+    pub fn new(context: SpotifyContextRef, queue: Queue) -> Self {
+        Self {
+            seed: Default::default(),
+            station: Default::default(),
+            error: Default::default(),
+            context: context.into(),
+            queue,
+        }
+    }
+
+    /// Plays tracks like `seed` instead of what's playing. A track's radio starts with the
+    /// track itself.
+    pub fn start(&self, seed: RadioSeed) {
+        let radio = self.clone();
+        tokio_runtime().spawn(with_priority(Priority::UserAction, None, async move {
+            match radio.play(seed).await {
+                Ok(()) => {}
+                Err(e) if e.kind == ApiErrorKind::Cancelled => {}
+                Err(e) => {
+                    eprintln!("Failed to start the radio: {}", e);
+                    radio.error.set(Some(e));
+                }
+            }
+        }));
+    }
+
+    /// Stops picking tracks. The ones queued already still play.
+    pub fn stop(&self) {
+        *self.station.lock().unwrap() = None;
+        self.seed.set(None);
+    }
+
+    /// Follows the queue, refilling it while a radio plays. Run this only once per radio.
+    pub async fn run(&self) {
+        let (state_tx, mut states) = mpsc::unbounded_channel();
+        self.queue
+            .state
+            .for_each(move |state: &QueueState| {
+                let _ = state_tx.send(state.clone());
+            })
+            .persist();
+        while let Some(state) = states.recv().await {
+            self.follow(&state);
+        }
+    }
+
+    async fn play(&self, seed: RadioSeed) -> Result<(), ApiError> {
+        let seed_tracks = match &seed {
+            RadioSeed::Playlist { id, .. } => self.playlist_tracks(id.clone()).await?,
+            RadioSeed::Track { .. } | RadioSeed::Artist { .. } => Vec::new(),
+        };
+        let mut station = Station {
+            seed: seed.clone(),
+            seed_tracks,
+            picked: HashSet::new(),
+            played: Vec::new(),
+            queued: HashSet::new(),
+            started: false,
+            refilled_at: None,
+        };
+        let mut uris = match &seed {
+            RadioSeed::Track { id, .. } => vec![id.uri()],
+            RadioSeed::Artist { .. } | RadioSeed::Playlist { .. } => Vec::new(),
+        };
+        station.picked.extend(uris.iter().cloned());
+        uris.extend(self.pick(&station).await?);
+        station.picked.extend(uris.iter().cloned());
+
+        let ids = uris
+            .iter()
+            .filter_map(|uri| TrackId::from_uri(uri).ok())
+            .map(|id| PlayableId::Track(id.into_static()))
+            .collect();
+        // before it plays, so the first track already counts as the radio's
+        *self.station.lock().unwrap() = Some(station);
+        self.seed.set(Some(seed));
+        if let Err(e) = self.context.play_uris(ids, None).await {
+            self.stop();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Keeps track of what the radio played, stopping it once something else plays and
+    /// picking more tracks when few are left.
+    fn follow(&self, state: &QueueState) {
+        let mut station = self.station.lock().unwrap();
+        let Some(playing) = station.as_mut() else {
+            return;
+        };
+        for position in state.queued() {
+            let uri = &state.next_tracks[position].uri;
+            if !playing.picked.contains(uri) {
+                playing.queued.insert(uri.clone());
+            }
+        }
+        if let Some(current) = &state.current {
+            if playing.picked.contains(current) {
+                playing.started = true;
+                if playing.played.last() != Some(current) {
+                    playing.played.push(current.clone());
+                }
+            } else if playing.started && !playing.queued.contains(current) {
+                *station = None;
+                drop(station);
+                self.seed.set(None);
+                return;
+            }
+        }
+
+        let left = state
+            .next_tracks
+            .iter()
+            .filter(|track| playing.picked.contains(&track.uri))
+            .count();
+        if !playing.started || left >= REFILL_BELOW || playing.refilled_at == state.current {
+            return;
+        }
+        playing.refilled_at = state.current.clone();
+        let snapshot = playing.clone();
+        drop(station);
+
+        let radio = self.clone();
+        tokio_runtime().spawn(with_priority(Priority::Visible, None, async move {
+            let picked = radio.pick(&snapshot).await;
+            let uris = {
+                let mut station = radio.station.lock().unwrap();
+                // stopped, or another radio started in the meantime
+                let Some(playing) = station
+                    .as_mut()
+                    .filter(|playing| playing.seed == snapshot.seed)
+                else {
+                    return;
+                };
+                match picked {
+                    Ok(uris) => {
+                        playing.picked.extend(uris.iter().cloned());
+                        uris
+                    }
+                    Err(e) => {
+                        if e.kind != ApiErrorKind::Cancelled {
+                            eprintln!("Failed to pick more radio tracks: {}", e);
+                            radio.error.set(Some(e));
+                        }
+                        return;
+                    }
+                }
+            };
+            radio.queue.extend(uris);
+        }));
+    }
+
+    /// Uris of the next tracks for `station`, like its seed and what it played lately,
+    /// leaving out the ones it picked before. Autoplay picks them if recommendations are
+    /// unavailable or have nothing new.
+    async fn pick(&self, station: &Station) -> Result<Vec<String>, ApiError> {
+        let recent = station
+            .played
+            .iter()
+            .rev()
+            .filter_map(|uri| TrackId::from_uri(uri).ok())
+            .map(TrackId::into_static)
+            .take(RECENT_SEEDS);
+        let (artists, tracks) = match &station.seed {
+            RadioSeed::Track { id, .. } => (
+                Vec::new(),
+                iter::once(id.clone())
+                    .chain(recent.filter(|recent| recent != id))
+                    .collect::<Vec<_>>(),
+            ),
+            RadioSeed::Artist { id, .. } => (vec![id.clone()], recent.collect()),
+            RadioSeed::Playlist { .. } => (
+                Vec::new(),
+                recent
+                    .chain(station.seed_tracks.iter().cloned())
+                    .take(MAX_SEEDS)
+                    .collect(),
+            ),
+        };
+
+        let recommended = match self
+            .context
+            .recommendations(&artists, &tracks, Some(BATCH as u32))
+            .await
+        {
+            Ok(tracks) => tracks
+                .into_iter()
+                .filter_map(|track| track.id)
+                .map(|id| id.uri())
+                .collect(),
+            Err(e) if matches!(e.kind, ApiErrorKind::NotFound | ApiErrorKind::Forbidden) => {
+                Vec::new()
+            }
+            Err(e) => return Err(e),
+        };
+        let recommended = unpicked(recommended, &station.picked);
+        if !recommended.is_empty() {
+            return Ok(recommended);
+        }
+
+        let recent = station
+            .played
+            .iter()
+            .rev()
+            .take(MAX_SEEDS)
+            .rev()
+            .cloned()
+            .collect::<Vec<_>>();
+        let autoplay = self
+            .context
+            .autoplay_tracks(&station.seed.uri(), &recent)
+            .await?;
+        let autoplay = unpicked(autoplay, &station.picked);
+        if autoplay.is_empty() {
+            return Err(ApiError::new("autoplay_tracks", ApiErrorKind::NotFound));
+        }
+        Ok(autoplay)
+    }
+
+    /// Up to [`MAX_SEEDS`] tracks spread over the start of the playlist.
+    async fn playlist_tracks(
+        &self,
+        id: PlaylistId<'static>,
+    ) -> Result<Vec<TrackId<'static>>, ApiError> {
+        let page = self.context.playlist_items(id, Some(50), Some(0)).await?;
+        let tracks = page
+            .items
+            .into_iter()
+            .filter_map(|item| match item.track? {
+                PlayableItem::Track(track) => track.id,
+                PlayableItem::Episode(_) => None,
+            })
+            .collect::<Vec<_>>();
+        let step = tracks.len().div_ceil(MAX_SEEDS).max(1);
+        Ok(tracks.into_iter().step_by(step).take(MAX_SEEDS).collect())
+    }
+}
+
+/// The first [`BATCH`] tracks of `uris` that weren't picked before, each once.
+fn unpicked(uris: Vec<String>, picked: &HashSet<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    uris.into_iter()
+        .filter(|uri| TrackId::from_uri(uri).is_ok())
+        .filter(|uri| !picked.contains(uri) && seen.insert(uri.clone()))
+        .take(BATCH)
+        .collect()
+}
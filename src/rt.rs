@@ -1,4 +1,9 @@
-use tokio::runtime;
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use tokio::{runtime, task::AbortHandle};
 
 pub(crate) fn tokio_runtime() -> &'static runtime::Handle {
     use std::sync::OnceLock;
@@ -23,3 +28,33 @@ pub(crate) fn tokio_runtime() -> &'static runtime::Handle {
         handle
     })
 }
+
+/// Tasks spawned on behalf of a widget. They are aborted once the last clone is dropped
+/// together with the widget, which also cancels their pending requests.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WidgetTasks(Arc<AbortOnDrop>);
+
+#[derive(Debug, Default)]
+struct AbortOnDrop(Mutex<Vec<AbortHandle>>);
+
+impl WidgetTasks {
+    /// Spawns `future`, returning a handle to abort it before the widget goes away.
+    pub fn spawn<F>(&self, future: F) -> AbortHandle
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handle = tokio_runtime().spawn(future).abort_handle();
+        let mut tasks = self.0 .0.lock().unwrap();
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(handle.clone());
+        handle
+    }
+}
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        for task in self.0.get_mut().unwrap().drain(..) {
+            task.abort();
+        }
+    }
+}
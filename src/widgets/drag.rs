@@ -0,0 +1,107 @@
+use cushy::{
+    context::EventContext,
+    figures::{
+        units::{Lp, Px},
+        Point, ScreenScale,
+    },
+    kludgine::app::winit::event::MouseButton,
+    widget::{EventHandling, MakeWidget, WidgetRef, WrapperWidget, HANDLED, IGNORED},
+    window::DeviceId,
+};
+
+use crate::nodebug::NoDebug;
+
+/// Wraps the handle of a list row, which moves the row up or down by as many rows as it's
+/// dragged by. The rows all have to be `row_height` high.
+#[derive(Debug)]
+pub struct DragHandle {
+    child: WidgetRef,
+    row_height: Lp,
+    /// Where it was pressed.
+    start: Option<Px>,
+    on_drag: NoDebug<Box<dyn FnMut(Option<isize>) + Send>>,
+    on_drop: NoDebug<Box<dyn FnMut(isize) + Send>>,
+}
+
+impl DragHandle {
+    /// Calls `on_drop` with the number of rows it was dragged by, negative ones being up.
+    /// While it's dragged, `on_drag` is called with the number of rows so far, and with
+    /// `None` once it's let go.
+    pub fn new(
+        child: impl MakeWidget,
+        row_height: Lp,
+        on_drag: impl FnMut(Option<isize>) + Send + 'static,
+        on_drop: impl FnMut(isize) + Send + 'static,
+    ) -> Self {
+        Self {
+            child: WidgetRef::new(child),
+            row_height,
+            start: None,
+            on_drag: NoDebug::from(Box::new(on_drag) as Box<dyn FnMut(Option<isize>) + Send>),
+            on_drop: NoDebug::from(Box::new(on_drop) as Box<dyn FnMut(isize) + Send>),
+        }
+    }
+
+    fn rows(&self, location: Point<Px>, context: &EventContext<'_>) -> Option<isize> {
+        let start = self.start?;
+        let row_height = self.row_height.into_px(context.kludgine.scale()).get();
+        if row_height <= 0 {
+            return Some(0);
+        }
+        let moved = (location.y - start).get() as f32 / row_height as f32;
+        Some(moved.round() as isize)
+    }
+}
+
+impl WrapperWidget for DragHandle {
+    fn child_mut(&mut self) -> &mut WidgetRef {
+        &mut self.child
+    }
+
+    fn hit_test(&mut self, _location: Point<Px>, _context: &mut EventContext<'_>) -> bool {
+        true
+    }
+
+    fn mouse_down(
+        &mut self,
+        location: Point<Px>,
+        _device_id: DeviceId,
+        button: MouseButton,
+        _context: &mut EventContext<'_>,
+    ) -> EventHandling {
+        if button != MouseButton::Left {
+            return IGNORED;
+        }
+        self.start = Some(location.y);
+        (*self.on_drag)(Some(0));
+        HANDLED
+    }
+
+    fn mouse_drag(
+        &mut self,
+        location: Point<Px>,
+        _device_id: DeviceId,
+        _button: MouseButton,
+        context: &mut EventContext<'_>,
+    ) {
+        if let Some(rows) = self.rows(location, context) {
+            (*self.on_drag)(Some(rows));
+        }
+    }
+
+    fn mouse_up(
+        &mut self,
+        location: Option<Point<Px>>,
+        _device_id: DeviceId,
+        _button: MouseButton,
+        context: &mut EventContext<'_>,
+    ) {
+        // released outside the window, where it wasn't dropped anywhere
+        let rows = location.and_then(|location| self.rows(location, context));
+        self.start = None;
+        (*self.on_drag)(None);
+        if let Some(rows) = rows.filter(|rows| *rows != 0) {
+            (*self.on_drop)(rows);
+        }
+    }
+}
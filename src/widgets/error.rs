@@ -0,0 +1,56 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use cushy::{
+    value::{Destination, Dynamic, Source},
+    widget::MakeWidget,
+    widgets::{label::Displayable, Space},
+};
+
+use crate::{api::ApiError, rt::tokio_runtime};
+
+/// How long [`error_toast`] shows an error.
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+/// Shows what went wrong while there's an error, and nothing otherwise.
+pub fn error_banner(error: Dynamic<Option<ApiError>>) -> impl MakeWidget {
+    error.map_each(|error| match error {
+        Some(error) => error_message(error).make_widget(),
+        None => Space::clear().make_widget(),
+    })
+}
+
+/// Like [`error_banner`], for errors of actions that were undone. Each error is cleared
+/// again after a few seconds.
+pub fn error_toast(error: Dynamic<Option<ApiError>>) -> impl MakeWidget {
+    // counts the errors, so a newer one isn't cleared early
+    let shown = Arc::new(AtomicUsize::new(0));
+    let weak = error.downgrade();
+    error
+        .for_each(move |current| {
+            let generation = shown.fetch_add(1, Ordering::Relaxed) + 1;
+            if current.is_none() {
+                return;
+            }
+            let (shown, weak) = (shown.clone(), weak.clone());
+            tokio_runtime().spawn(async move {
+                tokio::time::sleep(TOAST_DURATION).await;
+                if shown.load(Ordering::Relaxed) == generation {
+                    if let Some(error) = weak.upgrade() {
+                        error.set(None);
+                    }
+                }
+            });
+        })
+        .persist();
+    error_banner(error)
+}
+
+pub fn error_message(error: &ApiError) -> impl MakeWidget {
+    error.user_message().into_label().centered().pad()
+}
@@ -0,0 +1,46 @@
+use cushy::{
+    value::Source,
+    widget::MakeWidget,
+    widgets::{button::ButtonKind, Space},
+};
+use rspotify::prelude::*;
+
+use crate::{
+    api::Interest,
+    library::{Followable, Follows},
+};
+
+/// "Follow" or "Following", toggling whether `item` is followed when clicked. Users are
+/// named, as their button sits next to something of theirs. Shows nothing until it's known
+/// whether `item` is followed, which is dropped once `interest` is gone.
+pub fn follow_button(
+    follows: &Follows,
+    item: Followable,
+    interest: Option<&Interest>,
+) -> impl MakeWidget {
+    let follows = follows.clone();
+    let name = match &item {
+        Followable::User(user) => {
+            let name = user.display_name.clone();
+            format!(" {}", name.unwrap_or_else(|| user.id.id().to_string()))
+        }
+        Followable::Artist(_) | Followable::Playlist(_) => String::new(),
+    };
+    follows.followed(&item, interest).map_each(move |followed| {
+        let Some(followed) = *followed else {
+            return Space::clear().make_widget();
+        };
+        let (follows, item) = (follows.clone(), item.clone());
+        let text = if followed { "Following" } else { "Follow" };
+        let kind = if followed {
+            ButtonKind::Solid
+        } else {
+            ButtonKind::Transparent
+        };
+        format!("{text}{name}")
+            .into_button()
+            .kind(kind)
+            .on_click(move |_| follows.set_followed(item.clone(), !followed))
+            .make_widget()
+    })
+}
@@ -0,0 +1,75 @@
+/// Turns the HTML Spotify uses in descriptions into plain text, dropping tags and decoding
+/// character references.
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(['<', '&']) {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with('<') {
+            match rest.find('>') {
+                Some(end) => {
+                    let tag = &rest[1..end];
+                    if is_line_break(tag) {
+                        text.push('\n');
+                    }
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    text.push_str(rest);
+                    rest = "";
+                }
+            }
+        } else {
+            match rest[1..].find(';').map(|end| &rest[1..end + 1]) {
+                Some(reference) if reference.len() <= 10 => match decode_reference(reference) {
+                    Some(c) => {
+                        text.push(c);
+                        rest = &rest[reference.len() + 2..];
+                    }
+                    None => {
+                        text.push('&');
+                        rest = &rest[1..];
+                    }
+                },
+                _ => {
+                    text.push('&');
+                    rest = &rest[1..];
+                }
+            }
+        }
+    }
+    text.push_str(rest);
+    text.trim().to_string()
+}
+
+fn is_line_break(tag: &str) -> bool {
+    let name = tag
+        .trim_start_matches('/')
+        .split(|c: char| c.is_whitespace() || c == '/')
+        .next()
+        .unwrap_or_default();
+    ["br", "p", "li"]
+        .iter()
+        .any(|line_break| name.eq_ignore_ascii_case(line_break))
+}
+
+/// Decodes the character reference `&reference;`.
+fn decode_reference(reference: &str) -> Option<char> {
+    match reference {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => {
+            let number = reference.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
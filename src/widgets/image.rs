@@ -13,7 +13,7 @@ use reqwest::Client;
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
 use tokio::task::JoinHandle;
 
-use crate::rt::tokio_runtime;
+use crate::{paths::paths, rt::tokio_runtime};
 
 pub trait ImageExt: MakeWidget {
     fn new_empty() -> Self;
@@ -34,7 +34,7 @@ static CLIENT: LazyLock<ClientWithMiddleware> = LazyLock::new(|| {
         .with(Cache(HttpCache {
             mode: CacheMode::Default,
             manager: CACacheManager {
-                path: "./cache/http-cacache".into(),
+                path: paths().http_cache(),
             },
             options: HttpCacheOptions::default(),
         }))
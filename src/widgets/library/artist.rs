@@ -0,0 +1,60 @@
+use cushy::figures::units::Lp;
+use cushy::figures::{Size, Zero};
+use cushy::styles::{Dimension, DimensionRange};
+use cushy::{
+    value::{Destination, Dynamic, Source},
+    widget::{MakeWidget, WidgetList},
+    widgets::{grid::Orientation, label::Displayable, Space, Stack},
+};
+use rspotify::model::FullArtist;
+
+use super::playlist::entry;
+use crate::{
+    library::Follows,
+    widgets::{ActivePage, SelectedPage},
+};
+
+fn artist_entry(artist: &FullArtist, selected_page: SelectedPage) -> impl MakeWidget {
+    let id = artist.id.clone();
+    let is_active = selected_page
+        .map_each(move |page| matches!(page, ActivePage::Artist(a) if a.id.as_ref() == Some(&id)));
+    let page = ActivePage::artist(artist);
+    entry(
+        artist.name.clone(),
+        Dynamic::new(artist.images.first().map(|image| image.url.clone())),
+        is_active,
+        move |_| {
+            selected_page.set(page.clone());
+        },
+    )
+}
+
+/// The artists the user follows, below the playlists. Nothing while there are none.
+pub fn followed_artists_widget(follows: &Follows, selected_page: SelectedPage) -> impl MakeWidget {
+    follows.artists.map_each(move |artists| {
+        if artists.is_empty() {
+            return Space::clear().make_widget();
+        }
+        let list = artists
+            .iter()
+            .map(|artist| artist_entry(artist, selected_page.clone()))
+            .collect::<WidgetList>();
+        "Followed artists"
+            .into_label()
+            .h5()
+            .align_left()
+            .pad()
+            .and(
+                Stack::new(Orientation::Row, list)
+                    .gutter(Dimension::ZERO)
+                    .vertical_scroll()
+                    .size(Size {
+                        width: Dimension::Lp(Lp::points(200)).into(),
+                        // leaves most of the sidebar to the playlists
+                        height: DimensionRange::from(..=Dimension::Lp(Lp::points(240))),
+                    }),
+            )
+            .into_rows()
+            .make_widget()
+    })
+}
@@ -1,3 +1,6 @@
 use rspotify::model::SimplifiedPlaylist;
 
+pub mod artist;
 pub mod playlist;
+pub mod profile;
+pub mod sync;
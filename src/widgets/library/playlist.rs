@@ -15,6 +15,9 @@ use cushy::{
 use rspotify::model::SimplifiedPlaylist;
 
 use crate::{
+    api::{with_priority, Priority, SpotifyContextRef},
+    library::{edit::create_playlist, Library},
+    rt::tokio_runtime,
     theme::{LIBRARY_BG, LIBRARY_BG_HOVER, LIBRARY_BG_SELECTED, LIBRARY_BG_SELECTED_HOVER},
     widgets::{image::ImageExt, ActivePage, SelectedPage},
 };
@@ -40,19 +43,27 @@ fn playlist_entry(
 }
 
 pub fn playlists_widget(
-    playlists: impl IntoValue<Vec<SimplifiedPlaylist>>,
+    context: SpotifyContextRef,
+    library: &Library,
     selected_page: SelectedPage,
 ) -> impl MakeWidget {
-    let playlists: Value<Vec<SimplifiedPlaylist>> = playlists.into_value();
+    let library = library.clone();
     Stack::new(
         Orientation::Row,
-        playlists.map_each(move |t| {
+        library.playlists.clone().map_each(move |t| {
             let mut list = t
                 .clone()
                 .into_iter()
                 .map(|playlist| playlist_entry(playlist, selected_page.clone()))
                 .collect::<WidgetList>();
+            list.insert(0, podcasts_entry(selected_page.clone()));
             list.insert(0, liked_songs_entry(selected_page.clone()));
+            list.insert(0, stats_entry(selected_page.clone()));
+            list.insert(
+                0,
+                new_playlist_entry(context.clone(), library.clone(), selected_page.clone()),
+            );
+            list.insert(0, search_entry(selected_page.clone()));
             list
         }),
     )
@@ -64,6 +75,56 @@ pub fn playlists_widget(
     })
 }
 
+fn search_entry(selected_page: SelectedPage) -> impl MakeWidget {
+    let is_active = selected_page.map_each(|page| matches!(page, ActivePage::Search));
+    entry("Search", Dynamic::new(None), is_active, move |_| {
+        selected_page.set(ActivePage::Search);
+    })
+}
+
+fn stats_entry(selected_page: SelectedPage) -> impl MakeWidget {
+    let is_active = selected_page.map_each(|page| matches!(page, ActivePage::Stats));
+    entry("Your stats", Dynamic::new(None), is_active, move |_| {
+        selected_page.set(ActivePage::Stats);
+    })
+}
+
+fn podcasts_entry(selected_page: SelectedPage) -> impl MakeWidget {
+    let is_active = selected_page.map_each(|page| {
+        matches!(
+            page,
+            ActivePage::Shows | ActivePage::Show(_) | ActivePage::Episode(_)
+        )
+    });
+    entry("Podcasts", Dynamic::new(None), is_active, move |_| {
+        selected_page.set(ActivePage::Shows);
+    })
+}
+
+/// Creates an empty playlist and opens it.
+fn new_playlist_entry(
+    context: SpotifyContextRef,
+    library: Library,
+    selected_page: SelectedPage,
+) -> impl MakeWidget {
+    entry(
+        "New playlist",
+        Dynamic::new(None),
+        Dynamic::new(false),
+        move |_| {
+            let (context, library, selected_page) =
+                (context.clone(), library.clone(), selected_page.clone());
+            let name = format!("My Playlist #{}", library.playlists.map_ref(Vec::len) + 1);
+            tokio_runtime().spawn(with_priority(Priority::UserAction, None, async move {
+                match create_playlist(&context, &library, &name).await {
+                    Ok(playlist) => selected_page.set(ActivePage::Playlist(playlist)),
+                    Err(e) => eprintln!("Failed to create playlist: {}", e),
+                }
+            }));
+        },
+    )
+}
+
 fn liked_songs_entry(selected_page: SelectedPage) -> impl MakeWidget {
     let is_active = selected_page.map_each(|page| matches!(page, ActivePage::LikedSongs));
     entry(
@@ -78,7 +139,7 @@ fn liked_songs_entry(selected_page: SelectedPage) -> impl MakeWidget {
     )
 }
 
-fn entry<F>(
+pub(super) fn entry<F>(
     text: impl IntoValue<String>,
     url: Dynamic<Option<String>>,
     is_active: Dynamic<bool>,
@@ -101,7 +162,7 @@ where
 }
 
 /// Returns `background` and `background_hover` colors for a library entry.
-fn get_colors(is_active: impl IntoValue<bool>) -> (Value<Color>, Value<Color>) {
+pub(super) fn get_colors(is_active: impl IntoValue<bool>) -> (Value<Color>, Value<Color>) {
     let is_active = is_active.into_value();
     let background = is_active.map_each(|active| {
         if *active {
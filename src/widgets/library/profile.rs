@@ -0,0 +1,41 @@
+use cushy::{
+    figures::Zero,
+    styles::Dimension,
+    value::{Destination, Dynamic, Source},
+    widget::{MakeWidget, WidgetList},
+    widgets::{
+        button::{ButtonBackground, ButtonHoverBackground},
+        grid::Orientation,
+        Stack,
+    },
+};
+
+use super::playlist::get_colors;
+
+/// Lists the known profiles, switching to a profile when it's clicked.
+pub fn profiles_widget(profiles: Vec<String>, active_profile: Dynamic<String>) -> impl MakeWidget {
+    Stack::new(
+        Orientation::Row,
+        profiles
+            .into_iter()
+            .map(|name| profile_entry(name, active_profile.clone()))
+            .collect::<WidgetList>(),
+    )
+    .gutter(Dimension::ZERO)
+}
+
+fn profile_entry(name: String, active_profile: Dynamic<String>) -> impl MakeWidget {
+    let is_active = active_profile.map_each({
+        let name = name.clone();
+        move |active| *active == name
+    });
+    let (background, background_hover) = get_colors(is_active);
+    name.clone()
+        .align_left()
+        .into_button()
+        .on_click(move |_| {
+            active_profile.set(name.clone());
+        })
+        .with(&ButtonBackground, background)
+        .with(&ButtonHoverBackground, background_hover)
+}
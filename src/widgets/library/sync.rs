@@ -0,0 +1,20 @@
+use cushy::{
+    value::{Dynamic, Source},
+    widget::MakeWidget,
+    widgets::{label::Displayable, Space},
+};
+
+use crate::library::SyncProgress;
+
+/// Shows what the library sync is doing while it runs.
+pub fn sync_status_widget(progress: Dynamic<SyncProgress>) -> impl MakeWidget {
+    progress.map_each(|progress| match progress {
+        SyncProgress::Idle => Space::clear().make_widget(),
+        progress => progress
+            .to_string()
+            .into_label()
+            .align_left()
+            .pad()
+            .make_widget(),
+    })
+}
@@ -0,0 +1,53 @@
+use cushy::{
+    styles::components::TextColor,
+    value::{Dynamic, Source},
+    widget::MakeWidget,
+    widgets::{button::ButtonKind, Space},
+};
+use rspotify::model::TrackId;
+
+use crate::{
+    api::Interest,
+    icons::{icon, FAVORITE},
+    library::Likes,
+    theme::TEXT_SPOTIFY,
+};
+
+/// Heart showing whether the track `id` is liked, toggling it when clicked. Shows nothing for
+/// what can't be liked, like episodes and local files. Checking it is dropped once `interest`
+/// is gone.
+pub fn like_button(
+    likes: &Likes,
+    id: Dynamic<Option<TrackId<'static>>>,
+    interest: Option<&Interest>,
+) -> impl MakeWidget {
+    let likes = likes.clone();
+    let interest = interest.cloned();
+    id.map_each(move |id| {
+        let Some(id) = id.clone() else {
+            return Space::clear().make_widget();
+        };
+        let likes = likes.clone();
+        likes
+            .liked(id.clone(), interest.as_ref())
+            .map_each(move |liked| {
+                let liked = *liked;
+                let heart = match liked {
+                    Some(true) => icon(FAVORITE).with(&TextColor, TEXT_SPOTIFY).make_widget(),
+                    _ => icon(FAVORITE).make_widget(),
+                };
+                let (likes, id) = (likes.clone(), id.clone());
+                heart
+                    .into_button()
+                    .kind(ButtonKind::Transparent)
+                    .on_click(move |_| {
+                        // unknown until checked, there's nothing to toggle yet
+                        if let Some(liked) = liked {
+                            likes.set_liked(id.clone(), !liked);
+                        }
+                    })
+                    .make_widget()
+            })
+            .make_widget()
+    })
+}
@@ -0,0 +1,51 @@
+use cushy::{
+    value::Destination,
+    widget::{MakeWidget, WidgetList},
+    widgets::{
+        button::{ButtonClick, ButtonKind},
+        label::{Displayable, LabelOverflow},
+        Button,
+    },
+};
+use rspotify::model::SimplifiedArtist;
+
+use crate::widgets::{ActivePage, SelectedPage};
+
+/// Text that does something when clicked, like opening the page of what it names.
+pub fn link<F>(text: String, on_click: F) -> Button
+where
+    F: FnMut(Option<ButtonClick>) + Send + 'static,
+{
+    text.into_label()
+        .overflow(LabelOverflow::Clip)
+        .into_button()
+        .kind(ButtonKind::Transparent)
+        .on_click(on_click)
+}
+
+/// Comma separated artist names, each linking to the artist's page. Artists without an id,
+/// like those of local files, are plain text.
+pub fn artist_links(artists: &[SimplifiedArtist], selected_page: SelectedPage) -> impl MakeWidget {
+    let mut links = WidgetList::new();
+    for (i, artist) in artists.iter().enumerate() {
+        if i > 0 {
+            links.push(",");
+        }
+        if artist.id.is_some() {
+            let artist = artist.clone();
+            let selected_page = selected_page.clone();
+            links.push(link(artist.name.clone(), move |_| {
+                selected_page.set(ActivePage::Artist(artist.clone()));
+            }));
+        } else {
+            links.push(
+                artist
+                    .name
+                    .clone()
+                    .into_label()
+                    .overflow(LabelOverflow::Clip),
+            );
+        }
+    }
+    links.into_columns()
+}
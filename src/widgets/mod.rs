@@ -1,18 +1,65 @@
 use cushy::value::Dynamic;
-use rspotify::model::{SimplifiedAlbum, SimplifiedPlaylist};
+use rspotify::model::{
+    FullArtist, SimplifiedAlbum, SimplifiedArtist, SimplifiedEpisode, SimplifiedPlaylist,
+    SimplifiedShow,
+};
 
+use crate::{
+    library::{Follows, Likes},
+    queue::Queue,
+    radio::Radio,
+};
+
+pub mod drag;
+pub mod error;
+pub mod follow;
+pub mod html;
 pub mod image;
 pub mod library;
+pub mod like;
+pub mod link;
 pub mod owned;
 pub mod pages;
 pub mod playback;
+pub mod radio;
+pub mod track;
 
-#[derive(PartialEq, Debug, Default)]
+#[derive(PartialEq, Debug, Default, Clone)]
 pub enum ActivePage {
     #[default]
     LikedSongs,
+    Search,
+    Stats,
     Playlist(SimplifiedPlaylist),
     Album(SimplifiedAlbum),
+    Artist(SimplifiedArtist),
+    /// The podcasts the user saved.
+    Shows,
+    Show(SimplifiedShow),
+    Episode(SimplifiedEpisode),
+}
+
+impl ActivePage {
+    /// The page of an artist from a response that has all of its details.
+    pub fn artist(artist: &FullArtist) -> Self {
+        ActivePage::Artist(SimplifiedArtist {
+            external_urls: artist.external_urls.clone(),
+            href: Some(artist.href.clone()),
+            id: Some(artist.id.clone()),
+            name: artist.name.clone(),
+        })
+    }
 }
 
 type SelectedPage = Dynamic<ActivePage>;
+
+/// What the pages and their rows act on besides the api: liking, following, queueing,
+/// starting radios and opening other pages.
+#[derive(Debug, Clone)]
+pub struct AppContext {
+    pub likes: Likes,
+    pub follows: Follows,
+    pub queue: Queue,
+    pub radio: Radio,
+    pub selected_page: SelectedPage,
+}
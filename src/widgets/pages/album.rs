@@ -0,0 +1,310 @@
+use chrono::TimeDelta;
+use cushy::{
+    figures::{units::Lp, Size, Zero},
+    styles::Dimension,
+    value::{Destination, Dynamic, Source},
+    widget::{MakeWidget, WidgetList},
+    widgets::{
+        button::ButtonKind,
+        grid::Orientation,
+        image::ImageCornerRadius,
+        label::{Displayable, LabelOverflow},
+        Image, Space, Stack,
+    },
+};
+use itertools::Itertools;
+use rspotify::model::{
+    AlbumId, FullAlbum, Offset, PlayContextId, SimplifiedAlbum, SimplifiedTrack,
+};
+
+use crate::{
+    api::{
+        paginate::extend_dynamic, with_priority, ApiError, ApiErrorKind, Interest, Priority,
+        SpotifyContextRef,
+    },
+    icons::{IntoIcon, PLAY},
+    nodebug::NoDebug,
+    rt::WidgetTasks,
+    widgets::{
+        error::error_banner,
+        image::ImageExt,
+        link::artist_links,
+        track::{format_total, track_row, TrackRow},
+        AppContext, SelectedPage,
+    },
+};
+
+#[derive(Debug)]
+pub struct AlbumPage {
+    album: SimplifiedAlbum,
+    /// Fetched separately for what tracks don't come with, like the label.
+    details: Dynamic<Option<FullAlbum>>,
+    /// Tracks in album order, filled in as pages arrive.
+    tracks: Dynamic<Vec<SimplifiedTrack>>,
+    /// Error of the last failed load or playback request.
+    error: Dynamic<Option<ApiError>>,
+
+    context: NoDebug<SpotifyContextRef>,
+    app: AppContext,
+    tasks: WidgetTasks,
+}
+
+impl AlbumPage {
+    pub fn new(context: SpotifyContextRef, album: SimplifiedAlbum, app: &AppContext) -> Self {
+        Self {
+            album,
+            details: Default::default(),
+            tracks: Default::default(),
+            error: Default::default(),
+            context: context.into(),
+            app: app.clone(),
+            tasks: Default::default(),
+        }
+    }
+
+    pub fn into_widget(self) -> impl MakeWidget {
+        let AlbumPage {
+            album,
+            details,
+            tracks,
+            error,
+            context,
+            app,
+            tasks,
+        } = self;
+        // local files have an album without an id, there's nothing to fetch for those
+        let Some(id) = album.id.clone() else {
+            return album
+                .name
+                .clone()
+                .into_label()
+                .h1()
+                .centered()
+                .make_widget();
+        };
+
+        tasks.spawn(with_priority(
+            Priority::Visible,
+            None,
+            load_details(context.clone(), id.clone(), details.clone(), error.clone()),
+        ));
+        tasks.spawn(with_priority(
+            Priority::Visible,
+            None,
+            load_tracks(context.clone(), id.clone(), tracks.clone(), error.clone()),
+        ));
+
+        let play = {
+            let context = context.clone();
+            let tasks = tasks.clone();
+            let error = error.clone();
+            move |offset: Option<Offset>| {
+                let context = context.clone();
+                let id = id.clone();
+                let error = error.clone();
+                tasks.spawn(with_priority(Priority::UserAction, None, async move {
+                    let result = context.play_context(PlayContextId::Album(id), offset).await;
+                    if let Err(e) = result {
+                        eprintln!("Failed to play album: {}", e);
+                        error.set(Some(e));
+                    }
+                }));
+            }
+        };
+
+        let rows = tracks.map_each({
+            let album = album.clone();
+            let play = play.clone();
+            let app = app.clone();
+            move |tracks| {
+                let discs = tracks.iter().map(|track| track.disc_number).dedup().count();
+                // held by these rows until they're replaced
+                let interest = Interest::default();
+                let mut rows = WidgetList::new();
+                let mut disc = None;
+                for track in tracks {
+                    if discs > 1 && disc != Some(track.disc_number) {
+                        disc = Some(track.disc_number);
+                        rows.push(
+                            format!("Disc {}", track.disc_number)
+                                .into_label()
+                                .align_left()
+                                .pad(),
+                        );
+                    }
+                    let row = TrackRow {
+                        // the collection column would link to this page
+                        album: None,
+                        ..TrackRow::from_album_track(track, &album)
+                    };
+                    let uri = row.uri.clone();
+                    let play = play.clone();
+                    rows.push(
+                        track_row(
+                            track.track_number as usize,
+                            Dynamic::new(Some(row)),
+                            None,
+                            &interest,
+                            &app,
+                        )
+                        .into_button()
+                        .kind(ButtonKind::Transparent)
+                        .on_click(move |_| {
+                            if let Some(uri) = &uri {
+                                play(Some(Offset::Uri(uri.clone())));
+                            }
+                        }),
+                    );
+                }
+                rows
+            }
+        });
+
+        header(&album, details.clone(), tracks, app.selected_page)
+            .and(
+                PLAY.into_icon()
+                    .and("Play album")
+                    .into_columns()
+                    .into_button()
+                    .on_click(move |_| play(None))
+                    .align_left()
+                    .pad(),
+            )
+            .and(error_banner(error))
+            .and(
+                Stack::new(Orientation::Row, rows)
+                    .gutter(Dimension::ZERO)
+                    .and(copyrights(details))
+                    .into_rows()
+                    .vertical_scroll()
+                    .expand(),
+            )
+            .into_rows()
+            .make_widget()
+    }
+}
+
+/// Cover, name, artists, release date, label and runtime of the album.
+fn header(
+    album: &SimplifiedAlbum,
+    details: Dynamic<Option<FullAlbum>>,
+    tracks: Dynamic<Vec<SimplifiedTrack>>,
+    selected_page: SelectedPage,
+) -> impl MakeWidget {
+    let release_date = album.release_date.clone();
+    let summary = (&details, &tracks).map_each(move |(details, tracks)| {
+        let mut summary = Vec::new();
+        if let Some(release_date) = details
+            .as_ref()
+            .map(|details| &details.release_date)
+            .or(release_date.as_ref())
+        {
+            summary.push(release_date.clone());
+        }
+        if let Some(details) = details {
+            let total = details.tracks.total as usize;
+            let songs = match total {
+                1 => "1 song".to_string(),
+                total => format!("{total} songs"),
+            };
+            if tracks.len() < total {
+                summary.push(songs);
+            } else {
+                let runtime = tracks.iter().map(|track| track.duration).sum::<TimeDelta>();
+                summary.push(format!("{songs}, {}", format_total(runtime)));
+            }
+        }
+        summary.join(" • ")
+    });
+    let label = details.map_each(|details| {
+        match details.as_ref().and_then(|details| details.label.clone()) {
+            Some(label) if !label.is_empty() => label.into_label().align_left().make_widget(),
+            _ => Space::clear().make_widget(),
+        }
+    });
+
+    Image::new_empty()
+        .with_url(Dynamic::new(
+            album.images.first().map(|image| image.url.clone()),
+        ))
+        .with(&ImageCornerRadius, Dimension::Lp(Lp::points(4)))
+        .size(Size::squared(Dimension::Lp(Lp::points(160))))
+        .and(
+            album
+                .album_type
+                .clone()
+                .unwrap_or_else(|| "album".to_string())
+                .to_uppercase()
+                .into_label()
+                .align_left()
+                .and(
+                    album
+                        .name
+                        .clone()
+                        .into_label()
+                        .overflow(LabelOverflow::Clip)
+                        .h1()
+                        .align_left(),
+                )
+                .and(artist_links(&album.artists, selected_page).align_left())
+                .and(summary.into_label().align_left())
+                .and(label.align_left())
+                .into_rows()
+                .align_left()
+                .expand(),
+        )
+        .into_columns()
+        .pad()
+}
+
+/// Copyright notices shown below the tracks.
+fn copyrights(details: Dynamic<Option<FullAlbum>>) -> impl MakeWidget {
+    details.map_each(|details| {
+        let copyrights = details
+            .iter()
+            .flat_map(|details| &details.copyrights)
+            .map(|copyright| copyright.text.clone())
+            .join("\n");
+        if copyrights.is_empty() {
+            Space::clear().make_widget()
+        } else {
+            copyrights.into_label().align_left().pad().make_widget()
+        }
+    })
+}
+
+async fn load_details(
+    context: SpotifyContextRef,
+    id: AlbumId<'static>,
+    details: Dynamic<Option<FullAlbum>>,
+    error: Dynamic<Option<ApiError>>,
+) {
+    match context.album(id).await {
+        Ok(album) => details.set(Some(album)),
+        Err(e) if e.kind == ApiErrorKind::Cancelled => {}
+        Err(e) => {
+            eprintln!("Failed to load album: {}", e);
+            error.set(Some(e));
+        }
+    }
+}
+
+async fn load_tracks(
+    context: SpotifyContextRef,
+    id: AlbumId<'static>,
+    tracks: Dynamic<Vec<SimplifiedTrack>>,
+    error: Dynamic<Option<ApiError>>,
+) {
+    let fetched = context.paginate(move |context, limit, offset| {
+        let id = id.clone();
+        async move { context.album_tracks(id, Some(limit), Some(offset)).await }
+    });
+    match extend_dynamic(fetched.items(), tracks).await {
+        Ok(()) => {}
+        Err(e) if e.kind == ApiErrorKind::Cancelled => {}
+        Err(e) => {
+            eprintln!("Failed to load album tracks: {}", e);
+            error.set(Some(e));
+        }
+    }
+}
@@ -0,0 +1,515 @@
+use std::pin::pin;
+
+use cushy::{
+    figures::{units::Lp, Size},
+    styles::{Dimension, DimensionRange},
+    value::{Destination, Dynamic, Source},
+    widget::{MakeWidget, WidgetList},
+    widgets::{
+        button::ButtonKind,
+        image::ImageCornerRadius,
+        label::{Displayable, LabelOverflow},
+        Button, Image, Space, Wrap,
+    },
+};
+use futures_util::StreamExt;
+use rspotify::model::{
+    AlbumType, ArtistId, FullArtist, FullTrack, Offset, PlayContextId, PlayableId, SimplifiedAlbum,
+    SimplifiedArtist,
+};
+use rspotify::prelude::*;
+
+use crate::{
+    api::{with_priority, ApiError, ApiErrorKind, Interest, Priority, SpotifyContextRef},
+    icons::{IntoIcon, PLAY},
+    library::Followable,
+    nodebug::NoDebug,
+    radio::RadioSeed,
+    rt::WidgetTasks,
+    widgets::{
+        error::error_banner,
+        follow::follow_button,
+        image::ImageExt,
+        radio::start_radio_button,
+        track::{track_image, track_row, TrackRow},
+        ActivePage, AppContext, SelectedPage,
+    },
+};
+
+const ALBUMS_PER_PAGE: u32 = 20;
+
+/// Groups of the discography, in the order they're shown.
+const DISCOGRAPHY: [(AlbumType, &str); 4] = [
+    (AlbumType::Album, "Albums"),
+    (AlbumType::Single, "Singles and EPs"),
+    (AlbumType::Compilation, "Compilations"),
+    (AlbumType::AppearsOn, "Appears on"),
+];
+
+#[derive(Debug)]
+pub struct ArtistPage {
+    artist: SimplifiedArtist,
+    details: Dynamic<Option<FullArtist>>,
+    top_tracks: Dynamic<Vec<FullTrack>>,
+    related_artists: Dynamic<Vec<FullArtist>>,
+    /// Error of the last failed load or playback request.
+    error: Dynamic<Option<ApiError>>,
+
+    context: NoDebug<SpotifyContextRef>,
+    app: AppContext,
+    tasks: WidgetTasks,
+}
+
+/// One group of the artist's albums, loaded a page at a time.
+#[derive(Debug, Clone)]
+struct Discography {
+    group: AlbumType,
+    albums: Dynamic<Vec<SimplifiedAlbum>>,
+    /// `None` until the first page arrived.
+    total: Dynamic<Option<usize>>,
+    loading: Dynamic<bool>,
+}
+
+impl ArtistPage {
+    pub fn new(context: SpotifyContextRef, artist: SimplifiedArtist, app: &AppContext) -> Self {
+        Self {
+            artist,
+            details: Default::default(),
+            top_tracks: Default::default(),
+            related_artists: Default::default(),
+            error: Default::default(),
+            context: context.into(),
+            app: app.clone(),
+            tasks: Default::default(),
+        }
+    }
+
+    pub fn into_widget(self) -> impl MakeWidget {
+        let ArtistPage {
+            artist,
+            details,
+            top_tracks,
+            related_artists,
+            error,
+            context,
+            app,
+            tasks,
+        } = self;
+        let Some(id) = artist.id.clone() else {
+            return artist
+                .name
+                .clone()
+                .into_label()
+                .h1()
+                .centered()
+                .make_widget();
+        };
+
+        tasks.spawn(with_priority(Priority::Visible, None, {
+            let (context, id, details, error) =
+                (context.clone(), id.clone(), details.clone(), error.clone());
+            async move {
+                match context.artist(id).await {
+                    Ok(artist) => details.set(Some(artist)),
+                    Err(e) => report(&error, "artist", e),
+                }
+            }
+        }));
+        tasks.spawn(with_priority(Priority::Visible, None, {
+            let (context, id, top_tracks, error) = (
+                context.clone(),
+                id.clone(),
+                top_tracks.clone(),
+                error.clone(),
+            );
+            async move {
+                match context.artist_top_tracks(id).await {
+                    Ok(tracks) => top_tracks.set(tracks),
+                    Err(e) => report(&error, "top tracks", e),
+                }
+            }
+        }));
+        // further down the page
+        tasks.spawn(with_priority(Priority::Prefetch, None, {
+            let (context, id, related_artists) =
+                (context.clone(), id.clone(), related_artists.clone());
+            async move {
+                match context.artist_related_artists(id).await {
+                    Ok(artists) => related_artists.set(artists),
+                    // not offered to every app, the section just stays hidden then
+                    Err(e) => eprintln!("Failed to load related artists: {}", e),
+                }
+            }
+        }));
+
+        let play = PLAY
+            .into_icon()
+            .and("Play")
+            .into_columns()
+            .into_button()
+            .on_click({
+                let (context, id, tasks, error) =
+                    (context.clone(), id.clone(), tasks.clone(), error.clone());
+                move |_| {
+                    let (context, id, error) = (context.clone(), id.clone(), error.clone());
+                    tasks.spawn(with_priority(Priority::UserAction, None, async move {
+                        if let Err(e) = context.play_context(PlayContextId::Artist(id), None).await
+                        {
+                            eprintln!("Failed to play artist: {}", e);
+                            error.set(Some(e));
+                        }
+                    }));
+                }
+            });
+
+        let radio_button = start_radio_button(
+            &app.radio,
+            RadioSeed::Artist {
+                id: id.clone(),
+                name: artist.name.clone(),
+            },
+        );
+
+        // following needs the whole artist, as the followed artists show its image
+        let follow = details.map_each({
+            let follows = app.follows.clone();
+            move |details| match details {
+                Some(artist) => {
+                    let interest = Interest::default();
+                    follow_button(
+                        &follows,
+                        Followable::Artist(artist.clone()),
+                        Some(&interest),
+                    )
+                    .make_widget()
+                }
+                None => Space::clear().make_widget(),
+            }
+        });
+
+        let mut sections = WidgetList::new();
+        for (group, title) in DISCOGRAPHY {
+            let discography = Discography::new(group);
+            discography.load_more(&context, &id, &tasks, &error);
+            sections.push(discography.into_widget(
+                title,
+                context.clone(),
+                id.clone(),
+                tasks.clone(),
+                error.clone(),
+                app.selected_page.clone(),
+            ));
+        }
+
+        header(&artist, details)
+            .and(
+                play.and(radio_button)
+                    .and(follow)
+                    .into_columns()
+                    .align_left()
+                    .pad(),
+            )
+            .and(error_banner(error.clone()))
+            .and("Popular".into_label().h3().align_left().pad())
+            .and(top_tracks_widget(
+                top_tracks,
+                context.clone(),
+                tasks.clone(),
+                error,
+                app.clone(),
+            ))
+            .and(sections.into_rows())
+            .and(related_artists_widget(related_artists, app.selected_page))
+            .into_rows()
+            .vertical_scroll()
+            .expand()
+            .make_widget()
+    }
+}
+
+impl Discography {
+    fn new(group: AlbumType) -> Self {
+        Self {
+            group,
+            albums: Default::default(),
+            total: Default::default(),
+            loading: Default::default(),
+        }
+    }
+
+    /// Fetches the next page of albums, unless one is loading already.
+    fn load_more(
+        &self,
+        context: &SpotifyContextRef,
+        id: &ArtistId<'static>,
+        tasks: &WidgetTasks,
+        error: &Dynamic<Option<ApiError>>,
+    ) {
+        if self.loading.replace(true).is_none() {
+            // already loading
+            return;
+        }
+        let start = self.albums.map_ref(|albums| albums.len() as u32);
+        let group = self.group;
+        let (context, id, error, discography) =
+            (context.clone(), id.clone(), error.clone(), self.clone());
+        tasks.spawn(with_priority(Priority::Visible, None, async move {
+            let mut pages = pin!(context
+                .paginate(move |context, limit, offset| {
+                    let id = id.clone();
+                    async move {
+                        context
+                            .artist_albums(id, group, Some(limit), Some(offset))
+                            .await
+                    }
+                })
+                .page_size(ALBUMS_PER_PAGE)
+                .range(start..start + ALBUMS_PER_PAGE)
+                .pages());
+            while let Some(page) = pages.next().await {
+                match page {
+                    Ok(page) => {
+                        discography.total.set(Some(page.total as usize));
+                        discography
+                            .albums
+                            .map_mut(|mut albums| albums.extend(page.items));
+                    }
+                    Err(e) => {
+                        report(&error, "discography", e);
+                        break;
+                    }
+                }
+            }
+            discography.loading.set(false);
+        }));
+    }
+
+    /// The section titled `title`, hidden if the artist has no albums of its group.
+    fn into_widget(
+        self,
+        title: &'static str,
+        context: SpotifyContextRef,
+        id: ArtistId<'static>,
+        tasks: WidgetTasks,
+        error: Dynamic<Option<ApiError>>,
+        selected_page: SelectedPage,
+    ) -> impl MakeWidget {
+        let cards = self.albums.map_each({
+            let selected_page = selected_page.clone();
+            move |albums| {
+                albums
+                    .iter()
+                    .map(|album| album_card(album, selected_page.clone()))
+                    .collect::<WidgetList>()
+            }
+        });
+        let more = (&self.albums, &self.total, &self.loading).map_each({
+            let discography = self.clone();
+            move |(albums, total, loading)| {
+                if *loading || total.map_or(true, |total| albums.len() >= total) {
+                    return Space::clear().make_widget();
+                }
+                let (discography, context, id, tasks, error) = (
+                    discography.clone(),
+                    context.clone(),
+                    id.clone(),
+                    tasks.clone(),
+                    error.clone(),
+                );
+                "Show more"
+                    .into_button()
+                    .on_click(move |_| {
+                        discography.load_more(&context, &id, &tasks, &error);
+                    })
+                    .align_left()
+                    .make_widget()
+            }
+        });
+        self.total.map_each(move |total| {
+            if *total == Some(0) {
+                return Space::clear().make_widget();
+            }
+            title
+                .into_label()
+                .h3()
+                .align_left()
+                .and(Wrap::new(cards.clone()).align_left())
+                .and(more.clone())
+                .into_rows()
+                .pad()
+                .make_widget()
+        })
+    }
+}
+
+/// Image, name, followers and genres of the artist.
+fn header(artist: &SimplifiedArtist, details: Dynamic<Option<FullArtist>>) -> impl MakeWidget {
+    let image_url = details.map_each(|details| {
+        details
+            .as_ref()
+            .and_then(|details| details.images.first())
+            .map(|image| image.url.clone())
+    });
+    let summary = details.map_each(|details| {
+        let Some(details) = details else {
+            return String::new();
+        };
+        let followers = match details.followers.total {
+            1 => "1 follower".to_string(),
+            total => format!("{total} followers"),
+        };
+        if details.genres.is_empty() {
+            followers
+        } else {
+            format!("{followers} • {}", details.genres.join(", "))
+        }
+    });
+
+    Image::new_empty()
+        .with_url(image_url)
+        .with(&ImageCornerRadius, Dimension::Lp(Lp::points(80)))
+        .size(Size::squared(Dimension::Lp(Lp::points(160))))
+        .and(
+            "ARTIST"
+                .into_label()
+                .align_left()
+                .and(
+                    artist
+                        .name
+                        .clone()
+                        .into_label()
+                        .overflow(LabelOverflow::Clip)
+                        .h1()
+                        .align_left(),
+                )
+                .and(summary.into_label().align_left())
+                .into_rows()
+                .align_left()
+                .expand(),
+        )
+        .into_columns()
+        .pad()
+}
+
+/// The artist's popular tracks, played as a list of their own.
+fn top_tracks_widget(
+    top_tracks: Dynamic<Vec<FullTrack>>,
+    context: SpotifyContextRef,
+    tasks: WidgetTasks,
+    error: Dynamic<Option<ApiError>>,
+    app: AppContext,
+) -> impl MakeWidget {
+    top_tracks.map_each(move |tracks| {
+        let ids: Vec<PlayableId<'static>> = tracks
+            .iter()
+            .filter_map(|track| Some(PlayableId::Track(track.id.clone()?)))
+            .collect();
+        let interest = Interest::default();
+        let mut rows = WidgetList::new();
+        for (i, track) in tracks.iter().enumerate() {
+            let row = Dynamic::new(Some(TrackRow::from_track(track)));
+            let image = track_image(&row);
+            let uri = track.id.as_ref().map(|id| id.uri());
+            let (ids, context, tasks, error) =
+                (ids.clone(), context.clone(), tasks.clone(), error.clone());
+            rows.push(
+                track_row(i + 1, row, Some(image), &interest, &app)
+                    .into_button()
+                    .kind(ButtonKind::Transparent)
+                    .on_click(move |_| {
+                        let Some(uri) = uri.clone() else {
+                            return;
+                        };
+                        let (ids, context, error) = (ids.clone(), context.clone(), error.clone());
+                        tasks.spawn(with_priority(Priority::UserAction, None, async move {
+                            if let Err(e) = context.play_uris(ids, Some(Offset::Uri(uri))).await {
+                                eprintln!("Failed to play top tracks: {}", e);
+                                error.set(Some(e));
+                            }
+                        }));
+                    }),
+            );
+        }
+        rows.into_rows().make_widget()
+    })
+}
+
+fn related_artists_widget(
+    related_artists: Dynamic<Vec<FullArtist>>,
+    selected_page: SelectedPage,
+) -> impl MakeWidget {
+    related_artists.map_each(move |artists| {
+        if artists.is_empty() {
+            return Space::clear().make_widget();
+        }
+        let cards = artists
+            .iter()
+            .map(|artist| {
+                let url = artist.images.first().map(|image| image.url.clone());
+                let page = ActivePage::artist(artist);
+                let selected_page = selected_page.clone();
+                card(url, artist.name.clone(), String::new(), Lp::points(60))
+                    .on_click(move |_| {
+                        selected_page.set(page.clone());
+                    })
+                    .make_widget()
+            })
+            .collect::<WidgetList>();
+        "Fans also like"
+            .into_label()
+            .h3()
+            .align_left()
+            .and(Wrap::new(cards).align_left())
+            .into_rows()
+            .pad()
+            .make_widget()
+    })
+}
+
+fn album_card(album: &SimplifiedAlbum, selected_page: SelectedPage) -> impl MakeWidget {
+    let year = album
+        .release_date
+        .as_deref()
+        .map(|date| date.chars().take(4).collect())
+        .unwrap_or_default();
+    let album = album.clone();
+    card(
+        album.images.first().map(|image| image.url.clone()),
+        album.name.clone(),
+        year,
+        Lp::points(4),
+    )
+    .on_click(move |_| {
+        selected_page.set(ActivePage::Album(album.clone()));
+    })
+}
+
+/// A clickable cover with a title and subtitle below it.
+pub(super) fn card(
+    image_url: Option<String>,
+    title: String,
+    subtitle: String,
+    corner_radius: Lp,
+) -> Button {
+    Image::new_empty()
+        .with_url(Dynamic::new(image_url))
+        .with(&ImageCornerRadius, Dimension::Lp(corner_radius))
+        .size(Size::squared(Dimension::Lp(Lp::points(120))))
+        .and(title.into_label().overflow(LabelOverflow::Clip))
+        .and(subtitle.into_label().overflow(LabelOverflow::Clip))
+        .into_rows()
+        .size(Size {
+            width: Dimension::Lp(Lp::points(140)).into(),
+            height: DimensionRange::default(),
+        })
+        .into_button()
+        .kind(ButtonKind::Transparent)
+}
+
+/// Shows `e` unless the request was only dropped because the page went away.
+fn report(error: &Dynamic<Option<ApiError>>, what: &str, e: ApiError) {
+    if e.kind != ApiErrorKind::Cancelled {
+        eprintln!("Failed to load {}: {}", what, e);
+        error.set(Some(e));
+    }
+}
@@ -0,0 +1,239 @@
+use chrono::{NaiveDate, TimeDelta};
+use cushy::{
+    figures::{units::Lp, Size},
+    styles::{components::TextColor, Dimension},
+    value::{Destination, Dynamic, Source},
+    widget::MakeWidget,
+    widgets::{
+        image::ImageCornerRadius,
+        label::{Displayable, LabelOverflow},
+        Image, Space,
+    },
+};
+use rspotify::model::{EpisodeId, FullEpisode, ResumePoint, ShowId, SimplifiedEpisode};
+
+use crate::{
+    api::{with_priority, ApiError, ApiErrorKind, Priority, SpotifyContextRef},
+    icons::{icon, IntoIcon, CHECK_CIRCLE, PLAY},
+    nodebug::NoDebug,
+    rt::WidgetTasks,
+    theme::TEXT_SPOTIFY,
+    widgets::{
+        error::error_banner, html::html_to_text, image::ImageExt, link::link, track::format_total,
+        ActivePage, AppContext, SelectedPage,
+    },
+};
+
+#[derive(Debug)]
+pub struct EpisodePage {
+    episode: SimplifiedEpisode,
+    /// Fetched for the show, which the episode is played in, and the latest resume point.
+    details: Dynamic<Option<FullEpisode>>,
+    /// Error of the last failed load or playback request.
+    error: Dynamic<Option<ApiError>>,
+
+    context: NoDebug<SpotifyContextRef>,
+    selected_page: SelectedPage,
+    tasks: WidgetTasks,
+}
+
+impl EpisodePage {
+    pub fn new(context: SpotifyContextRef, episode: SimplifiedEpisode, app: &AppContext) -> Self {
+        Self {
+            episode,
+            details: Default::default(),
+            error: Default::default(),
+            context: context.into(),
+            selected_page: app.selected_page.clone(),
+            tasks: Default::default(),
+        }
+    }
+
+    pub fn into_widget(self) -> impl MakeWidget {
+        let EpisodePage {
+            episode,
+            details,
+            error,
+            context,
+            selected_page,
+            tasks,
+        } = self;
+
+        tasks.spawn(with_priority(Priority::Visible, None, {
+            let (context, id, details, error) = (
+                context.clone(),
+                episode.id.clone(),
+                details.clone(),
+                error.clone(),
+            );
+            async move {
+                match context.episode(id).await {
+                    Ok(episode) => details.set(Some(episode)),
+                    Err(e) if e.kind == ApiErrorKind::Cancelled => {}
+                    Err(e) => {
+                        eprintln!("Failed to load episode: {}", e);
+                        error.set(Some(e));
+                    }
+                }
+            }
+        }));
+
+        // playing needs the show
+        let play_button = details.map_each({
+            let (context, tasks, error) = (context.clone(), tasks.clone(), error.clone());
+            move |details| {
+                let Some(details) = details else {
+                    return Space::clear().make_widget();
+                };
+                let position = resume_position(details.resume_point.as_ref());
+                let (context, tasks, error) = (context.clone(), tasks.clone(), error.clone());
+                let (show, id) = (details.show.id.clone(), details.id.clone());
+                PLAY.into_icon()
+                    .and(if position.is_some() { "Resume" } else { "Play" })
+                    .into_columns()
+                    .into_button()
+                    .on_click(move |_| {
+                        play(&context, &tasks, &error, show.clone(), id.clone(), position)
+                    })
+                    .align_left()
+                    .pad()
+                    .make_widget()
+            }
+        });
+
+        let description = html_to_text(&episode.html_description);
+        header(&episode, details, selected_page)
+            .and(play_button)
+            .and(error_banner(error))
+            .and(
+                description
+                    .into_label()
+                    .align_left()
+                    .pad()
+                    .vertical_scroll()
+                    .expand(),
+            )
+            .into_rows()
+    }
+}
+
+/// Cover, name, show, release date and progress of the episode.
+fn header(
+    episode: &SimplifiedEpisode,
+    details: Dynamic<Option<FullEpisode>>,
+    selected_page: SelectedPage,
+) -> impl MakeWidget {
+    let show = details.map_each(move |details| match details {
+        Some(details) => {
+            let (show, selected_page) = (details.show.clone(), selected_page.clone());
+            link(show.name.clone(), move |_| {
+                selected_page.set(ActivePage::Show(show.clone()));
+            })
+            .align_left()
+            .make_widget()
+        }
+        None => Space::clear().make_widget(),
+    });
+    let summary = details.map_each({
+        let episode = episode.clone();
+        move |details| match details {
+            // has the latest resume point
+            Some(details) => episode_summary(
+                &details.release_date,
+                details.duration,
+                details.resume_point.as_ref(),
+            ),
+            None => episode_summary(
+                &episode.release_date,
+                episode.duration,
+                episode.resume_point.as_ref(),
+            ),
+        }
+    });
+
+    Image::new_empty()
+        .with_url(Dynamic::new(
+            episode.images.first().map(|image| image.url.clone()),
+        ))
+        .with(&ImageCornerRadius, Dimension::Lp(Lp::points(4)))
+        .size(Size::squared(Dimension::Lp(Lp::points(160))))
+        .and(
+            "EPISODE"
+                .into_label()
+                .align_left()
+                .and(
+                    episode
+                        .name
+                        .clone()
+                        .into_label()
+                        .overflow(LabelOverflow::Clip)
+                        .h1()
+                        .align_left(),
+                )
+                .and(show)
+                .and(summary.into_label().align_left())
+                .into_rows()
+                .align_left()
+                .expand(),
+        )
+        .into_columns()
+        .pad()
+}
+
+/// Where to resume an episode from, `None` to start at the beginning, also once it was
+/// played to the end.
+pub(super) fn resume_position(resume_point: Option<&ResumePoint>) -> Option<TimeDelta> {
+    resume_point
+        .filter(|point| !point.fully_played && point.resume_position > TimeDelta::zero())
+        .map(|point| point.resume_position)
+}
+
+/// Release date and length of an episode, or how much of it is left if the user started
+/// it, e.g. `Mar 3, 2024 • 12 min 3 sec left`.
+pub(super) fn episode_summary(
+    release_date: &str,
+    duration: TimeDelta,
+    resume_point: Option<&ResumePoint>,
+) -> String {
+    // some only have the year or month
+    let date = NaiveDate::parse_from_str(release_date, "%Y-%m-%d")
+        .map(|date| date.format("%b %-d, %Y").to_string())
+        .unwrap_or_else(|_| release_date.to_string());
+    let length = match resume_point {
+        Some(point) if point.fully_played => "Played".to_string(),
+        _ => match resume_position(resume_point) {
+            Some(position) => format!("{} left", format_total(duration - position)),
+            None => format_total(duration),
+        },
+    };
+    format!("{date} • {length}")
+}
+
+/// Check mark for an episode that was played to the end, nothing otherwise.
+pub(super) fn played_marker(resume_point: Option<&ResumePoint>) -> impl MakeWidget {
+    if resume_point.is_some_and(|point| point.fully_played) {
+        icon(CHECK_CIRCLE)
+            .with(&TextColor, TEXT_SPOTIFY)
+            .make_widget()
+    } else {
+        Space::clear().make_widget()
+    }
+}
+
+/// Plays the episode `id` of `show` from `position`.
+pub(super) fn play(
+    context: &SpotifyContextRef,
+    tasks: &WidgetTasks,
+    error: &Dynamic<Option<ApiError>>,
+    show: ShowId<'static>,
+    id: EpisodeId<'static>,
+    position: Option<TimeDelta>,
+) {
+    let (context, error) = (context.clone(), error.clone());
+    tasks.spawn(with_priority(Priority::UserAction, None, async move {
+        if let Err(e) = context.play_episode(show, id, position).await {
+            eprintln!("Failed to play episode: {}", e);
+            error.set(Some(e));
+        }
+    }));
+}
@@ -1,2 +1,10 @@
-
-pub mod liked;
\ No newline at end of file
+pub mod album;
+pub mod artist;
+pub mod episode;
+pub mod liked;
+pub mod playlist;
+mod playlist_edit;
+pub mod search;
+pub mod show;
+pub mod shows;
+pub mod stats;
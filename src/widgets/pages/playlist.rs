@@ -0,0 +1,458 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use chrono::TimeDelta;
+use cushy::{
+    figures::{units::Lp, Size},
+    styles::Dimension,
+    value::{Destination, Dynamic, Source},
+    widget::{MakeWidget, WidgetInstance},
+    widgets::{
+        button::ButtonKind,
+        image::ImageCornerRadius,
+        label::{Displayable, LabelOverflow},
+        Image, Space, VirtualList,
+    },
+};
+use rspotify::model::{
+    FullPlaylist, Offset, PlayContextId, PlayableItem, PlaylistItem, SimplifiedPlaylist,
+};
+use rspotify::prelude::*;
+
+use crate::{
+    api::{
+        paginate::{extend_dynamic, MAX_PAGE_SIZE},
+        with_priority, ApiError, ApiErrorKind, Interest, Priority, RowInterests, SpotifyContextRef,
+    },
+    icons::{IntoIcon, ADD, EDIT},
+    library::{Followable, Library, PlaylistEditor},
+    nodebug::NoDebug,
+    radio::RadioSeed,
+    rt::WidgetTasks,
+    widgets::{
+        error::error_banner,
+        follow::follow_button,
+        html::html_to_text,
+        image::ImageExt,
+        radio::start_radio_button,
+        track::{format_total, track_image, track_row, TrackRow},
+        AppContext,
+    },
+};
+
+use super::playlist_edit::{add_songs, details_form, row_controls, EditStatus};
+
+#[derive(Debug)]
+pub struct PlaylistPage {
+    playlist: SimplifiedPlaylist,
+    /// Fetched separately for what the library doesn't have, like the description.
+    details: Dynamic<Option<FullPlaylist>>,
+    /// Items in playlist order, filled in as pages arrive.
+    items: Dynamic<Vec<PlaylistItem>>,
+    total_items: Dynamic<usize>,
+    /// Error of the last failed load or playback request.
+    error: Dynamic<Option<ApiError>>,
+
+    track_images: Arc<Mutex<HashMap<usize, WidgetInstance>>>,
+    context: NoDebug<SpotifyContextRef>,
+    library: NoDebug<Library>,
+    app: AppContext,
+    tasks: WidgetTasks,
+}
+
+impl PlaylistPage {
+    /// Shows `playlist`, with its items from `library` if they're up to date and fetched
+    /// otherwise.
+    pub fn new(
+        context: SpotifyContextRef,
+        library: &Library,
+        playlist: SimplifiedPlaylist,
+        app: &AppContext,
+    ) -> Self {
+        Self {
+            total_items: Dynamic::new(playlist.tracks.total as usize),
+            playlist,
+            details: Default::default(),
+            items: Default::default(),
+            error: Default::default(),
+            track_images: Default::default(),
+            context: context.into(),
+            library: library.clone().into(),
+            app: app.clone(),
+            tasks: Default::default(),
+        }
+    }
+
+    pub fn into_widget(self) -> impl MakeWidget {
+        let PlaylistPage {
+            playlist,
+            details,
+            items,
+            total_items,
+            error,
+            track_images,
+            context,
+            library,
+            app,
+            tasks,
+        } = self;
+        let current = Dynamic::new(playlist.clone());
+        let editor = PlaylistEditor::new(
+            (*context).clone(),
+            (*library).clone(),
+            current.clone(),
+            items.clone(),
+        );
+        let status = EditStatus {
+            error: error.clone(),
+            notice: Dynamic::default(),
+        };
+        current
+            .for_each({
+                let total_items = total_items.clone();
+                move |playlist| total_items.set(playlist.tracks.total as usize)
+            })
+            .persist();
+
+        tasks.spawn(with_priority(
+            Priority::Visible,
+            None,
+            load_details(
+                context.clone(),
+                playlist.clone(),
+                details.clone(),
+                error.clone(),
+            ),
+        ));
+        tasks.spawn(with_priority(
+            Priority::Visible,
+            None,
+            load_items(
+                context.clone(),
+                (*library).clone(),
+                playlist.clone(),
+                items.clone(),
+                total_items.clone(),
+                error.clone(),
+            ),
+        ));
+
+        // `None` until it's known
+        let owned = Dynamic::new(None);
+        tasks.spawn(with_priority(
+            Priority::Visible,
+            None,
+            load_owned(context.clone(), playlist.clone(), owned.clone()),
+        ));
+        // changing the items needs all of them
+        let editable = (&owned, &items, &total_items)
+            .map_each(|(owned, items, total)| *owned == Some(true) && items.len() == *total);
+        // the user's own playlists are always followed
+        let follow = owned.map_each({
+            let playlist = playlist.clone();
+            let follows = app.follows.clone();
+            move |owned| {
+                if *owned != Some(false) {
+                    return Space::clear().make_widget();
+                }
+                let interest = Interest::default();
+                follow_button(
+                    &follows,
+                    Followable::Playlist(playlist.clone()),
+                    Some(&interest),
+                )
+                .and(follow_button(
+                    &follows,
+                    Followable::User(playlist.owner.clone()),
+                    Some(&interest),
+                ))
+                .into_columns()
+                .make_widget()
+            }
+        });
+        let radio_button = start_radio_button(
+            &app.radio,
+            RadioSeed::Playlist {
+                id: playlist.id.clone(),
+                name: playlist.name.clone(),
+            },
+        );
+        let moving = Dynamic::<Option<(usize, usize)>>::default();
+
+        let header = header(
+            &playlist,
+            current.map_each(|playlist| playlist.name.clone()),
+            details.clone(),
+            items.clone(),
+            total_items.clone(),
+        );
+        let editing = Dynamic::new(false);
+        let adding = Dynamic::new(false);
+        let actions = editable.map_each({
+            let (editing, adding) = (editing.clone(), adding.clone());
+            move |editable| {
+                if !*editable {
+                    return Space::clear().make_widget();
+                }
+                let (editing, adding) = (editing.clone(), adding.clone());
+                EDIT.into_icon()
+                    .and("Edit details")
+                    .into_columns()
+                    .into_button()
+                    .on_click(move |_| editing.toggle())
+                    .and(
+                        ADD.into_icon()
+                            .and("Add songs")
+                            .into_columns()
+                            .into_button()
+                            .on_click(move |_| adding.toggle()),
+                    )
+                    .into_columns()
+                    .align_left()
+                    .pad()
+                    .make_widget()
+            }
+        });
+        let form = editing.map_each({
+            // the form closes itself, which mustn't keep `editing` alive
+            let editing = editing.downgrade();
+            let (editor, details, status) = (editor.clone(), details.clone(), status.clone());
+            move |open| {
+                let Some(editing) = editing.upgrade().filter(|_| *open) else {
+                    return Space::clear().make_widget();
+                };
+                let description = details.map_ref(|details| {
+                    details
+                        .as_ref()
+                        .and_then(|details| details.description.as_deref())
+                        .map(html_to_text)
+                        .unwrap_or_default()
+                });
+                details_form(&editor, &details, description, &editing, &status).make_widget()
+            }
+        });
+        let add = adding.map_each({
+            let (editor, context, status) = (editor.clone(), context.clone(), status.clone());
+            move |open| {
+                if *open {
+                    add_songs(&editor, &context, &status).make_widget()
+                } else {
+                    Space::clear().make_widget()
+                }
+            }
+        });
+        let moving_label = moving.map_each(|moving| {
+            moving
+                .map(|(from, to)| format!("Move song {} to position {}", from + 1, to + 1))
+                .unwrap_or_default()
+        });
+
+        let notice = status.notice();
+
+        let list_error = error.clone();
+        let interests = RowInterests::new(MAX_PAGE_SIZE as usize);
+        let list = VirtualList::new(
+            total_items.map_each(|total| (*total).max(1)),
+            move |index| {
+                let row = items
+                    .map_each(move |items| items.get(index).and_then(TrackRow::from_playlist_item));
+                let image = track_images
+                    .lock()
+                    .unwrap()
+                    .entry(index)
+                    .or_insert_with(|| track_image(&row))
+                    .clone();
+                let (handle, remove) = row_controls(&editor, index, &editable, &moving, &status);
+                let interest = interests.for_row(index);
+                let row_button = track_row(index + 1, row.clone(), Some(image), &interest, &app)
+                    .into_button()
+                    .kind(ButtonKind::Transparent)
+                    .on_click({
+                        let context = context.clone();
+                        let playlist_id = playlist.id.clone();
+                        let error = list_error.clone();
+                        let tasks = tasks.clone();
+                        move |_| {
+                            let Some(uri) = row.map_ref(|row| row.as_ref()?.uri.clone()) else {
+                                // local files only play on the device they're on
+                                return;
+                            };
+                            let context = context.clone();
+                            let playlist_id = playlist_id.clone();
+                            let error = error.clone();
+                            tasks.spawn(with_priority(Priority::UserAction, None, async move {
+                                let result = context
+                                    .play_context(
+                                        PlayContextId::Playlist(playlist_id),
+                                        Some(Offset::Uri(uri)),
+                                    )
+                                    .await;
+                                if let Err(e) = result {
+                                    eprintln!("Failed to play playlist: {}", e);
+                                    error.set(Some(e));
+                                }
+                            }));
+                        }
+                    });
+                handle
+                    .and(row_button.expand_horizontally())
+                    .and(remove)
+                    .into_columns()
+            },
+        )
+        .expand_horizontally();
+
+        header
+            .and(radio_button.and(follow).into_columns().align_left().pad())
+            .and(actions)
+            .and(form)
+            .and(add)
+            .and(error_banner(error))
+            .and(notice)
+            .and(moving_label.into_label().align_left())
+            .and(list.expand())
+            .into_rows()
+    }
+}
+
+/// Cover, name, owner, description and size of the playlist.
+fn header(
+    playlist: &SimplifiedPlaylist,
+    name: Dynamic<String>,
+    details: Dynamic<Option<FullPlaylist>>,
+    items: Dynamic<Vec<PlaylistItem>>,
+    total_items: Dynamic<usize>,
+) -> impl MakeWidget {
+    let owner = playlist
+        .owner
+        .display_name
+        .clone()
+        .unwrap_or_else(|| playlist.owner.id.id().to_string());
+    let duration = items.map_each(|items| {
+        items
+            .iter()
+            .filter_map(|item| match item.track.as_ref()? {
+                PlayableItem::Track(track) => Some(track.duration),
+                PlayableItem::Episode(episode) => Some(episode.duration),
+            })
+            .sum::<TimeDelta>()
+    });
+    let summary = (&total_items, &items, &duration).map_each(move |(total, items, duration)| {
+        let songs = match total {
+            1 => "1 song".to_string(),
+            total => format!("{total} songs"),
+        };
+        if items.len() < *total {
+            format!("{owner} • {songs}")
+        } else {
+            format!("{owner} • {songs}, {}", format_total(*duration))
+        }
+    });
+
+    Image::new_empty()
+        .with_url(Dynamic::new(
+            playlist.images.first().map(|image| image.url.clone()),
+        ))
+        .with(&ImageCornerRadius, Dimension::Lp(Lp::points(4)))
+        .size(Size::squared(Dimension::Lp(Lp::points(160))))
+        .and(
+            name.into_label()
+                .overflow(LabelOverflow::Clip)
+                .h1()
+                .align_left()
+                .and(
+                    details
+                        .map_each(|details| {
+                            let description = details
+                                .as_ref()
+                                .and_then(|details| details.description.as_deref())
+                                .map(html_to_text)
+                                .unwrap_or_default();
+                            if description.is_empty() {
+                                Space::clear().make_widget()
+                            } else {
+                                description.into_label().align_left().make_widget()
+                            }
+                        })
+                        .align_left(),
+                )
+                .and(summary.into_label().align_left())
+                .into_rows()
+                .align_left()
+                .expand(),
+        )
+        .into_columns()
+        .pad()
+}
+
+/// Whether the current user can change `playlist`, as its owner or a collaborator.
+async fn load_owned(
+    context: SpotifyContextRef,
+    playlist: SimplifiedPlaylist,
+    owned: Dynamic<Option<bool>>,
+) {
+    if playlist.collaborative {
+        owned.set(Some(true));
+        return;
+    }
+    match context.current_user().await {
+        Ok(user) => owned.set(Some(user.id == playlist.owner.id)),
+        Err(e) if e.kind == ApiErrorKind::Cancelled => {}
+        Err(e) => eprintln!("Failed to load the current user: {}", e),
+    }
+}
+
+async fn load_details(
+    context: SpotifyContextRef,
+    playlist: SimplifiedPlaylist,
+    details: Dynamic<Option<FullPlaylist>>,
+    error: Dynamic<Option<ApiError>>,
+) {
+    match context.playlist(playlist.id.as_ref()).await {
+        Ok(playlist) => details.set(Some(playlist)),
+        Err(e) if e.kind == ApiErrorKind::Cancelled => {}
+        Err(e) => {
+            eprintln!("Failed to load playlist {}: {}", playlist.name, e);
+            error.set(Some(e));
+        }
+    }
+}
+
+/// Shows the stored items if they're from the current snapshot, fetches and stores them
+/// otherwise.
+async fn load_items(
+    context: SpotifyContextRef,
+    library: Library,
+    playlist: SimplifiedPlaylist,
+    items: Dynamic<Vec<PlaylistItem>>,
+    total_items: Dynamic<usize>,
+    error: Dynamic<Option<ApiError>>,
+) {
+    if let Some(stored) = library.playlist_items(&playlist).await {
+        total_items.set(stored.len());
+        items.set(stored);
+        return;
+    }
+
+    let id = playlist.id.clone();
+    let fetched = context.paginate(move |context, limit, offset| {
+        let id = id.clone();
+        async move { context.playlist_items(id, Some(limit), Some(offset)).await }
+    });
+    match extend_dynamic(fetched.items(), items.clone()).await {
+        Ok(()) => {
+            let items = items.get();
+            total_items.set(items.len());
+            if items.len() == playlist.tracks.total as usize {
+                library.store_playlist_items(&playlist, items).await;
+            }
+        }
+        Err(e) if e.kind == ApiErrorKind::Cancelled => {}
+        Err(e) => {
+            eprintln!("Failed to load items of playlist {}: {}", playlist.name, e);
+            error.set(Some(e));
+        }
+    }
+}
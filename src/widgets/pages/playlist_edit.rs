@@ -0,0 +1,346 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use cushy::{
+    figures::units::Lp,
+    value::{Destination, Dynamic, Source},
+    widget::{MakeWidget, WidgetList},
+    widgets::{
+        button::ButtonKind,
+        input::InputValue,
+        label::{Displayable, LabelOverflow},
+        Space,
+    },
+};
+use itertools::Itertools;
+use rspotify::model::{FullPlaylist, FullTrack, PlayableId, PlayableItem, SearchType};
+use tokio::task::AbortHandle;
+
+use crate::{
+    api::{with_priority, ApiError, ApiErrorKind, Priority, SearchItem, SpotifyContextRef},
+    icons::{IntoIcon, DELETE, DRAG_INDICATOR},
+    library::{EditError, PlaylistEditor},
+    rt::{tokio_runtime, WidgetTasks},
+    widgets::drag::DragHandle,
+};
+
+/// How long typing has to pause before searching for songs to add.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+const SEARCH_LIMIT: u32 = 10;
+
+/// Where an edit failed. Conflicts aren't errors of a request, the playlist was reloaded.
+#[derive(Debug, Clone, Default)]
+pub(super) struct EditStatus {
+    pub error: Dynamic<Option<ApiError>>,
+    pub notice: Dynamic<Option<String>>,
+}
+
+impl EditStatus {
+    /// Runs `edit`, showing why it failed. Runs on after the page is closed, so the stored
+    /// playlist doesn't end up half changed.
+    pub fn spawn(&self, edit: impl Future<Output = Result<(), EditError>> + Send + 'static) {
+        let status = self.clone();
+        tokio_runtime().spawn(with_priority(Priority::UserAction, None, async move {
+            match edit.await {
+                Ok(()) => status.notice.set(None),
+                Err(EditError::Api(e)) if e.kind == ApiErrorKind::Cancelled => {}
+                Err(EditError::Api(e)) => {
+                    eprintln!("Failed to edit playlist: {}", e);
+                    status.error.set(Some(e));
+                }
+                Err(EditError::Conflict) => status.notice.set(Some(
+                    "The playlist was changed somewhere else and has been reloaded, try again."
+                        .to_string(),
+                )),
+            }
+        }));
+    }
+
+    pub fn notice(&self) -> impl MakeWidget {
+        self.notice.map_each(|notice| match notice {
+            Some(notice) => notice.clone().into_label().centered().pad().make_widget(),
+            None => Space::clear().make_widget(),
+        })
+    }
+}
+
+/// Handle to drag the row at `index` to another position, and a button removing it. Nothing
+/// unless `editable`.
+pub(super) fn row_controls(
+    editor: &PlaylistEditor,
+    index: usize,
+    editable: &Dynamic<bool>,
+    moving: &Dynamic<Option<(usize, usize)>>,
+    status: &EditStatus,
+) -> (impl MakeWidget, impl MakeWidget) {
+    let handle = editable.map_each({
+        let (editor, moving, status) = (editor.clone(), moving.clone(), status.clone());
+        move |editable| {
+            if !*editable {
+                return Space::clear().make_widget();
+            }
+            let target = {
+                let items = editor.items.clone();
+                move |rows: isize| {
+                    let last = items.map_ref(Vec::len).saturating_sub(1) as isize;
+                    (index as isize + rows).clamp(0, last) as usize
+                }
+            };
+            let (editor, moving, status) = (editor.clone(), moving.clone(), status.clone());
+            DragHandle::new(
+                DRAG_INDICATOR.into_icon().centered(),
+                // the height of a track row
+                Lp::points(60),
+                {
+                    let target = target.clone();
+                    move |rows: Option<isize>| {
+                        moving.set(rows.map(|rows| (index, target(rows))));
+                    }
+                },
+                move |rows| {
+                    let editor = editor.clone();
+                    let to = target(rows);
+                    status.spawn(async move { editor.reorder(index, to).await });
+                },
+            )
+            .make_widget()
+        }
+    });
+    let remove = editable.map_each({
+        let (editor, status) = (editor.clone(), status.clone());
+        move |editable| {
+            if !*editable {
+                return Space::clear().make_widget();
+            }
+            let (editor, status) = (editor.clone(), status.clone());
+            DELETE
+                .into_iconbtn()
+                .on_click(move |_| {
+                    let editor = editor.clone();
+                    status.spawn(async move { editor.remove(index).await });
+                })
+                .make_widget()
+        }
+    });
+    (handle, remove)
+}
+
+/// Form for the name and description of the playlist, shown while `open`.
+pub(super) fn details_form(
+    editor: &PlaylistEditor,
+    details: &Dynamic<Option<FullPlaylist>>,
+    description: String,
+    open: &Dynamic<bool>,
+    status: &EditStatus,
+) -> impl MakeWidget {
+    let name = Dynamic::new(editor.playlist.map_ref(|playlist| playlist.name.clone()));
+    let description = Dynamic::new(description);
+    let saving = Dynamic::new(false);
+
+    let save = {
+        let (editor, details, open, status, saving) = (
+            editor.clone(),
+            details.clone(),
+            open.clone(),
+            status.clone(),
+            saving.clone(),
+        );
+        let (name, description) = (name.clone(), description.clone());
+        move |_| {
+            let (name, description) = (name.get().trim().to_string(), description.get());
+            if name.is_empty() || saving.replace(true).is_none() {
+                return;
+            }
+            let (editor, details, open, saving) = (
+                editor.clone(),
+                details.clone(),
+                open.clone(),
+                saving.clone(),
+            );
+            status.spawn(async move {
+                let result = editor.change_details(&name, &description).await;
+                saving.set(false);
+                result?;
+                details.map_mut(|mut details| {
+                    if let Some(details) = details.as_mut() {
+                        details.name = name;
+                        details.description = Some(description);
+                    }
+                });
+                open.set(false);
+                Ok(())
+            });
+        }
+    };
+
+    "Name"
+        .align_left()
+        .and(name.into_input())
+        .and("Description".align_left())
+        .and(description.into_input())
+        .and(
+            "Save"
+                .into_button()
+                .on_click(save)
+                .and(
+                    "Cancel"
+                        .into_button()
+                        .kind(ButtonKind::Transparent)
+                        .on_click({
+                            let open = open.clone();
+                            move |_| open.set(false)
+                        }),
+                )
+                .into_columns()
+                .align_left(),
+        )
+        .into_rows()
+        .pad()
+}
+
+/// Search for songs to add at the end of the playlist, warning about ones it has already.
+pub(super) fn add_songs(
+    editor: &PlaylistEditor,
+    context: &SpotifyContextRef,
+    status: &EditStatus,
+) -> impl MakeWidget {
+    let query = Dynamic::<String>::default();
+    let results = Dynamic::<Vec<FullTrack>>::default();
+    // a song that's in the playlist already, until it's decided whether to add it anyway
+    let duplicate = Dynamic::<Option<FullTrack>>::default();
+    let tasks = WidgetTasks::default();
+
+    let search: Arc<Mutex<Option<AbortHandle>>> = Default::default();
+    query
+        .for_each({
+            let (context, results, status, tasks) = (
+                context.clone(),
+                results.clone(),
+                status.clone(),
+                tasks.clone(),
+            );
+            move |query| {
+                if let Some(search) = search.lock().unwrap().take() {
+                    search.abort();
+                }
+                let query = query.trim().to_string();
+                if query.is_empty() {
+                    results.set(Vec::new());
+                    return;
+                }
+                let (context, results, error) =
+                    (context.clone(), results.clone(), status.error.clone());
+                let handle = tasks.spawn(async move {
+                    tokio::time::sleep(DEBOUNCE).await;
+                    let found = with_priority(
+                        Priority::UserAction,
+                        None,
+                        context.search(&query, SearchType::Track, Some(SEARCH_LIMIT), Some(0)),
+                    )
+                    .await;
+                    match found {
+                        Ok(page) => results.set(
+                            page.items
+                                .into_iter()
+                                .filter_map(|item| match item {
+                                    SearchItem::Track(track) => Some(track),
+                                    _ => None,
+                                })
+                                .collect(),
+                        ),
+                        Err(e) if e.kind == ApiErrorKind::Cancelled => {}
+                        Err(e) => {
+                            eprintln!("Failed to search songs to add: {}", e);
+                            error.set(Some(e));
+                        }
+                    }
+                });
+                *search.lock().unwrap() = Some(handle);
+            }
+        })
+        .persist();
+
+    let add = {
+        let (editor, status) = (editor.clone(), status.clone());
+        move |track: FullTrack| {
+            let editor = editor.clone();
+            status.spawn(async move { editor.add(vec![PlayableItem::Track(track)]).await });
+        }
+    };
+
+    let rows = results.map_each({
+        let (editor, duplicate, add) = (editor.clone(), duplicate.clone(), add.clone());
+        move |results| {
+            results
+                .iter()
+                .map(|track| {
+                    let (editor, duplicate, add) = (editor.clone(), duplicate.clone(), add.clone());
+                    let track = track.clone();
+                    format!(
+                        "{} • {}",
+                        track.name,
+                        track.artists.iter().map(|artist| &artist.name).join(", ")
+                    )
+                    .into_label()
+                    .overflow(LabelOverflow::Clip)
+                    .align_left()
+                    .expand()
+                    .and("Add".into_button().on_click(move |_| {
+                        let Some(id) = track.id.clone() else {
+                            return;
+                        };
+                        if editor.positions_of(&PlayableId::Track(id)).is_empty() {
+                            add(track.clone());
+                        } else {
+                            duplicate.set(Some(track.clone()));
+                        }
+                    }))
+                    .into_columns()
+                    .pad()
+                })
+                .collect::<WidgetList>()
+                .into_rows()
+                .make_widget()
+        }
+    });
+
+    let warning = duplicate.map_each({
+        let duplicate = duplicate.clone();
+        move |track| {
+            let Some(track) = track.clone() else {
+                return Space::clear().make_widget();
+            };
+            let (add, duplicate, dismiss) = (add.clone(), duplicate.clone(), duplicate.clone());
+            format!("\"{}\" is already in this playlist.", track.name)
+                .into_label()
+                .align_left()
+                .expand()
+                .and("Add anyway".into_button().on_click(move |_| {
+                    duplicate.set(None);
+                    add(track.clone());
+                }))
+                .and(
+                    "Don't add"
+                        .into_button()
+                        .kind(ButtonKind::Transparent)
+                        .on_click(move |_| dismiss.set(None)),
+                )
+                .into_columns()
+                .pad()
+                .make_widget()
+        }
+    });
+
+    "Add songs"
+        .into_label()
+        .h3()
+        .align_left()
+        .and(query.into_input().placeholder("Search for songs to add"))
+        .and(warning)
+        .and(rows)
+        .into_rows()
+        .pad()
+}
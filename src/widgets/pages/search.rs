@@ -0,0 +1,616 @@
+use std::{
+    pin::pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use cushy::{
+    figures::{units::Lp, Size},
+    kludgine::app::winit::keyboard::{ModifiersState, NamedKey},
+    styles::Dimension,
+    value::{Destination, Dynamic, Source},
+    widget::{MakeWidget, WidgetList, HANDLED},
+    widgets::{
+        button::ButtonKind,
+        image::ImageCornerRadius,
+        input::InputValue,
+        label::{Displayable, LabelOverflow},
+        Image, Space,
+    },
+};
+use futures_util::StreamExt;
+use rspotify::model::{Offset, PlayContextId, PlayableId, SearchType};
+use rspotify::prelude::*;
+use tokio::task::AbortHandle;
+
+use crate::{
+    api::{
+        with_priority, ApiError, ApiErrorKind, Interest, Priority, SearchItem, SearchResults,
+        SpotifyContextRef,
+    },
+    nodebug::NoDebug,
+    rt::WidgetTasks,
+    widgets::{
+        error::error_banner,
+        image::ImageExt,
+        link::artist_links,
+        track::{track_image, track_row, TrackRow},
+        ActivePage, AppContext,
+    },
+};
+
+/// How long typing has to pause before searching.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// Results of each type shown before "Show all".
+const OVERVIEW_LIMIT: u32 = 5;
+const PER_PAGE: u32 = 50;
+/// Spotify doesn't return results past this offset.
+const MAX_RESULTS: u32 = 1000;
+
+#[derive(Debug)]
+pub struct SearchPage {
+    query: Dynamic<String>,
+    results: Dynamic<Option<SearchResults>>,
+    /// Waiting for typing to pause, or for the results.
+    searching: Dynamic<bool>,
+    all: AllResults,
+    /// Position of the keyboard selection among the shown results.
+    selected: Dynamic<Option<usize>>,
+    /// Error of the last failed search.
+    error: Dynamic<Option<ApiError>>,
+
+    context: NoDebug<SpotifyContextRef>,
+    app: AppContext,
+    tasks: WidgetTasks,
+}
+
+/// Every result of one type, loaded a page at a time, instead of the overview.
+#[derive(Debug, Clone, Default)]
+struct AllResults {
+    kind: Dynamic<Option<SearchType>>,
+    query: Dynamic<String>,
+    items: Dynamic<Vec<SearchItem>>,
+    /// `None` until the first page arrived.
+    total: Dynamic<Option<usize>>,
+    loading: Dynamic<bool>,
+    load: Arc<Mutex<Option<AbortHandle>>>,
+}
+
+/// A group of shown results. Items are numbered across sections for the keyboard selection.
+#[derive(Debug, Clone, PartialEq)]
+struct Section {
+    title: &'static str,
+    /// Set if there are more results than shown.
+    show_all: Option<SearchType>,
+    items: Vec<(usize, SearchItem)>,
+}
+
+/// What selecting a result does, shared by clicks and the keyboard.
+#[derive(Debug, Clone)]
+struct Actions {
+    context: NoDebug<SpotifyContextRef>,
+    app: AppContext,
+    error: Dynamic<Option<ApiError>>,
+    tasks: WidgetTasks,
+}
+
+impl SearchPage {
+    pub fn new(context: SpotifyContextRef, app: &AppContext) -> Self {
+        Self {
+            query: Default::default(),
+            results: Default::default(),
+            searching: Default::default(),
+            all: Default::default(),
+            selected: Default::default(),
+            error: Default::default(),
+            context: context.into(),
+            app: app.clone(),
+            tasks: Default::default(),
+        }
+    }
+
+    pub fn into_widget(self) -> impl MakeWidget {
+        let SearchPage {
+            query,
+            results,
+            searching,
+            all,
+            selected,
+            error,
+            context,
+            app,
+            tasks,
+        } = self;
+        let actions = Actions {
+            context: (*context).clone().into(),
+            app,
+            error: error.clone(),
+            tasks: tasks.clone(),
+        };
+
+        // every keystroke restarts the wait, and drops a search that's still running
+        let search: Arc<Mutex<Option<AbortHandle>>> = Default::default();
+        query
+            .for_each({
+                let (results, searching, all, selected, error) = (
+                    results.clone(),
+                    searching.clone(),
+                    all.clone(),
+                    selected.clone(),
+                    error.clone(),
+                );
+                let context = (*context).clone();
+                move |query| {
+                    if let Some(search) = search.lock().unwrap().take() {
+                        search.abort();
+                    }
+                    all.close();
+                    selected.set(None);
+                    let query = query.trim().to_string();
+                    if query.is_empty() {
+                        searching.set(false);
+                        results.set(None);
+                        return;
+                    }
+                    searching.set(true);
+                    let (context, results, searching, error) = (
+                        context.clone(),
+                        results.clone(),
+                        searching.clone(),
+                        error.clone(),
+                    );
+                    let handle = tasks.spawn(async move {
+                        tokio::time::sleep(DEBOUNCE).await;
+                        let fetched = with_priority(
+                            Priority::UserAction,
+                            None,
+                            SearchResults::fetch(&context, query, OVERVIEW_LIMIT),
+                        )
+                        .await;
+                        searching.set(false);
+                        match fetched {
+                            Ok(fetched) => {
+                                error.set(None);
+                                results.set(Some(fetched));
+                            }
+                            Err(e) if e.kind == ApiErrorKind::Cancelled => {}
+                            Err(e) => {
+                                eprintln!("Failed to search: {}", e);
+                                error.set(Some(e));
+                            }
+                        }
+                    });
+                    *search.lock().unwrap() = Some(handle);
+                }
+            })
+            .persist();
+
+        let sections = (&results, &all.kind, &all.items).map_each(sections);
+        let entries = sections.map_each(|sections| {
+            sections
+                .iter()
+                .flat_map(|section| section.items.iter().map(|(_, item)| item.clone()))
+                .collect::<Vec<_>>()
+        });
+
+        let list = sections.map_each({
+            let (actions, all, query, selected) = (
+                actions.clone(),
+                all.clone(),
+                query.clone(),
+                selected.clone(),
+            );
+            move |sections| {
+                // held by these rows until they're replaced
+                let interest = Interest::default();
+                let mut rows = WidgetList::new();
+                for section in sections {
+                    let title = section.title.into_label().h3().align_left();
+                    match section.show_all {
+                        Some(kind) => {
+                            let (all, actions, query) =
+                                (all.clone(), actions.clone(), query.clone());
+                            rows.push(
+                                title
+                                    .and(Space::clear().expand_horizontally())
+                                    .and(
+                                        "Show all"
+                                            .into_button()
+                                            .kind(ButtonKind::Transparent)
+                                            .on_click(move |_| {
+                                                all.open(
+                                                    kind,
+                                                    query.get().trim().to_string(),
+                                                    &actions,
+                                                );
+                                            }),
+                                    )
+                                    .into_columns()
+                                    .pad(),
+                            );
+                        }
+                        None => rows.push(title.pad()),
+                    }
+                    for (number, (index, item)) in section.items.iter().enumerate() {
+                        rows.push(item_row(
+                            number + 1,
+                            *index,
+                            item,
+                            &selected,
+                            &interest,
+                            &actions,
+                        ));
+                    }
+                }
+                rows.into_rows().make_widget()
+            }
+        });
+
+        let back = all.kind.map_each({
+            let all = all.clone();
+            move |kind| match kind {
+                Some(_) => {
+                    let all = all.clone();
+                    "Back to all results"
+                        .into_button()
+                        .on_click(move |_| all.close())
+                        .align_left()
+                        .pad()
+                        .make_widget()
+                }
+                None => Space::clear().make_widget(),
+            }
+        });
+        let more = (&all.kind, &all.items, &all.total, &all.loading).map_each({
+            let (all, actions) = (all.clone(), actions.clone());
+            move |(kind, items, total, loading)| {
+                let Some(total) = total.filter(|_| kind.is_some() && !*loading) else {
+                    return Space::clear().make_widget();
+                };
+                if items.len() >= total.min(MAX_RESULTS as usize) {
+                    return Space::clear().make_widget();
+                }
+                let (all, actions) = (all.clone(), actions.clone());
+                "Load more"
+                    .into_button()
+                    .on_click(move |_| all.load(&actions))
+                    .align_left()
+                    .pad()
+                    .make_widget()
+            }
+        });
+        let status = (&searching, &query, &results).map_each(|(searching, query, results)| {
+            if *searching {
+                "Searching...".to_string()
+            } else if !query.trim().is_empty()
+                && results.as_ref().is_some_and(|results| {
+                    results.pages.iter().all(|(_, page)| page.items.is_empty())
+                })
+            {
+                format!("No results found for \"{}\"", query.trim())
+            } else {
+                String::new()
+            }
+        });
+
+        query
+            .clone()
+            .into_input()
+            .placeholder("What do you want to listen to?")
+            .pad()
+            .and(status.into_label().align_left().pad())
+            .and(error_banner(error))
+            .and(back)
+            .and(list.and(more).into_rows().vertical_scroll().expand())
+            .into_rows()
+            .with_repeating_shortcut(NamedKey::ArrowDown, ModifiersState::empty(), {
+                let (entries, selected) = (entries.clone(), selected.clone());
+                move |_| {
+                    let count = entries.map_ref(Vec::len);
+                    selected.map_mut(|mut selected| {
+                        *selected = match *selected {
+                            _ if count == 0 => None,
+                            Some(index) => Some((index + 1).min(count - 1)),
+                            None => Some(0),
+                        };
+                    });
+                    HANDLED
+                }
+            })
+            .with_repeating_shortcut(NamedKey::ArrowUp, ModifiersState::empty(), {
+                let selected = selected.clone();
+                move |_| {
+                    selected.map_mut(|mut selected| {
+                        *selected = selected.and_then(|index| index.checked_sub(1));
+                    });
+                    HANDLED
+                }
+            })
+            .with_shortcut(NamedKey::Enter, ModifiersState::empty(), move |_| {
+                let index = selected.get().unwrap_or(0);
+                if let Some(item) = entries.map_ref(|entries| entries.get(index).cloned()) {
+                    actions.open(&item);
+                }
+                HANDLED
+            })
+    }
+}
+
+/// The overview of `results`, or every result of one type once "Show all" was clicked.
+fn sections(
+    (results, all_kind, all_items): (
+        &Option<SearchResults>,
+        &Option<SearchType>,
+        &Vec<SearchItem>,
+    ),
+) -> Vec<Section> {
+    let Some(results) = results else {
+        return Vec::new();
+    };
+    if let Some(kind) = all_kind {
+        return vec![Section {
+            title: type_title(*kind),
+            show_all: None,
+            items: all_items.iter().cloned().enumerate().collect(),
+        }];
+    }
+
+    let mut sections = Vec::new();
+    let mut index = 0;
+    let mut numbered = |items: &[SearchItem]| {
+        items
+            .iter()
+            .map(|item| {
+                index += 1;
+                (index - 1, item.clone())
+            })
+            .collect::<Vec<_>>()
+    };
+    if let Some(top) = results.top_result() {
+        sections.push(Section {
+            title: "Top result",
+            show_all: None,
+            items: numbered(std::slice::from_ref(top)),
+        });
+    }
+    for (kind, page) in &results.pages {
+        if page.items.is_empty() {
+            continue;
+        }
+        sections.push(Section {
+            title: type_title(*kind),
+            show_all: (page.total as usize > page.items.len()).then_some(*kind),
+            items: numbered(&page.items),
+        });
+    }
+    sections
+}
+
+fn type_title(kind: SearchType) -> &'static str {
+    match kind {
+        SearchType::Track => "Songs",
+        SearchType::Artist => "Artists",
+        SearchType::Album => "Albums",
+        SearchType::Playlist => "Playlists",
+        SearchType::Show => "Podcasts",
+        SearchType::Episode => "Episodes",
+        _ => "Results",
+    }
+}
+
+/// The row of the result at `index` of all shown ones. Songs are track rows numbered with
+/// `number`, like in any other list.
+fn item_row(
+    number: usize,
+    index: usize,
+    item: &SearchItem,
+    selected: &Dynamic<Option<usize>>,
+    interest: &Interest,
+    actions: &Actions,
+) -> impl MakeWidget {
+    let kind = selected.map_each(move |selected| {
+        if *selected == Some(index) {
+            ButtonKind::Solid
+        } else {
+            ButtonKind::Transparent
+        }
+    });
+    // albums link their artists after the type
+    let (image_url, subtitle, artists) = match item {
+        SearchItem::Track(track) => {
+            let row = Dynamic::new(Some(TrackRow::from_track(track)));
+            let image = track_image(&row);
+            let (item, actions) = (item.clone(), actions.clone());
+            return track_row(number, row, Some(image), interest, &actions.app)
+                .into_button()
+                .kind(kind)
+                .on_click(move |_| actions.open(&item))
+                .expand_horizontally()
+                .make_widget();
+        }
+        SearchItem::Artist(artist) => (artist.images.first(), "Artist".to_string(), &[][..]),
+        SearchItem::Album(album) => (
+            album.images.first(),
+            "Album •".to_string(),
+            album.artists.as_slice(),
+        ),
+        SearchItem::Playlist(playlist) => (
+            playlist.images.first(),
+            format!(
+                "Playlist • {}",
+                playlist.owner.display_name.as_deref().unwrap_or_default()
+            ),
+            &[][..],
+        ),
+        SearchItem::Show(show) => (
+            show.images.first(),
+            format!("Podcast • {}", show.publisher),
+            &[][..],
+        ),
+        SearchItem::Episode(episode) => (
+            episode.images.first(),
+            format!("Episode • {}", episode.release_date),
+            &[][..],
+        ),
+    };
+    let subtitle = subtitle
+        .into_label()
+        .overflow(LabelOverflow::Clip)
+        .and(artist_links(artists, actions.app.selected_page.clone()))
+        .into_columns();
+    let corner_radius = match item {
+        SearchItem::Artist(_) => Lp::points(24),
+        _ => Lp::points(4),
+    };
+    let (item, actions) = (item.clone(), actions.clone());
+
+    Image::new_empty()
+        .with_url(Dynamic::new(image_url.map(|image| image.url.clone())))
+        .with(&ImageCornerRadius, Dimension::Lp(corner_radius))
+        .size(Size::squared(Dimension::Lp(Lp::points(48))))
+        .and(
+            item.name()
+                .to_string()
+                .into_label()
+                .overflow(LabelOverflow::Clip)
+                .align_left()
+                .and(subtitle.align_left())
+                .into_rows()
+                .align_left()
+                .expand(),
+        )
+        .into_columns()
+        .into_button()
+        .kind(kind)
+        .on_click(move |_| actions.open(&item))
+        .expand_horizontally()
+        .make_widget()
+}
+
+impl AllResults {
+    /// Shows every result of `kind` for `query`, starting with the first page.
+    fn open(&self, kind: SearchType, query: String, actions: &Actions) {
+        self.close();
+        self.kind.set(Some(kind));
+        self.query.set(query);
+        self.load(actions);
+    }
+
+    /// Goes back to the overview.
+    fn close(&self) {
+        if let Some(load) = self.load.lock().unwrap().take() {
+            load.abort();
+        }
+        self.kind.set(None);
+        self.items.set(Vec::new());
+        self.total.set(None);
+        self.loading.set(false);
+    }
+
+    /// Loads the next page of results.
+    fn load(&self, actions: &Actions) {
+        let Some(kind) = self.kind.get() else {
+            return;
+        };
+        if self.loading.replace(true).is_none() {
+            // already loading
+            return;
+        }
+        let query = self.query.get();
+        let start = self.items.map_ref(|items| items.len() as u32);
+        let end = (start + PER_PAGE).min(MAX_RESULTS);
+        let (context, error, all) = (
+            (*actions.context).clone(),
+            actions.error.clone(),
+            self.clone(),
+        );
+        let handle = actions
+            .tasks
+            .spawn(with_priority(Priority::Visible, None, async move {
+                let mut pages = pin!(context
+                    .paginate(move |context, limit, offset| {
+                        let query = query.clone();
+                        async move {
+                            context
+                                .search(&query, kind, Some(limit), Some(offset))
+                                .await
+                        }
+                    })
+                    .page_size(PER_PAGE)
+                    .range(start..end)
+                    .pages());
+                while let Some(page) = pages.next().await {
+                    match page {
+                        Ok(page) => {
+                            all.total.set(Some(page.total as usize));
+                            all.items.map_mut(|mut items| items.extend(page.items));
+                        }
+                        Err(e) => {
+                            if e.kind != ApiErrorKind::Cancelled {
+                                eprintln!("Failed to load search results: {}", e);
+                                error.set(Some(e));
+                            }
+                            break;
+                        }
+                    }
+                }
+                all.loading.set(false);
+            }));
+        *self.load.lock().unwrap() = Some(handle);
+    }
+}
+
+impl Actions {
+    /// Plays tracks, opens the page of everything else.
+    fn open(&self, item: &SearchItem) {
+        match item {
+            SearchItem::Track(track) => {
+                let Some(id) = track.id.clone() else {
+                    return;
+                };
+                // in its album, so playback goes on from there
+                match track.album.id.clone() {
+                    Some(album) => {
+                        self.play(PlayContextId::Album(album), Some(Offset::Uri(id.uri())))
+                    }
+                    None => self.play_uris(vec![PlayableId::Track(id)]),
+                }
+            }
+            SearchItem::Artist(artist) => self.app.selected_page.set(ActivePage::artist(artist)),
+            SearchItem::Album(album) => {
+                self.app.selected_page.set(ActivePage::Album(album.clone()))
+            }
+            SearchItem::Playlist(playlist) => self
+                .app
+                .selected_page
+                .set(ActivePage::Playlist(playlist.clone())),
+            SearchItem::Show(show) => self.app.selected_page.set(ActivePage::Show(show.clone())),
+            SearchItem::Episode(episode) => self
+                .app
+                .selected_page
+                .set(ActivePage::Episode(episode.clone())),
+        }
+    }
+
+    fn play(&self, context: PlayContextId<'static>, offset: Option<Offset>) {
+        let (api, error) = ((*self.context).clone(), self.error.clone());
+        self.tasks
+            .spawn(with_priority(Priority::UserAction, None, async move {
+                if let Err(e) = api.play_context(context, offset).await {
+                    eprintln!("Failed to play search result: {}", e);
+                    error.set(Some(e));
+                }
+            }));
+    }
+
+    fn play_uris(&self, uris: Vec<PlayableId<'static>>) {
+        let (api, error) = ((*self.context).clone(), self.error.clone());
+        self.tasks
+            .spawn(with_priority(Priority::UserAction, None, async move {
+                if let Err(e) = api.play_uris(uris, None).await {
+                    eprintln!("Failed to play search result: {}", e);
+                    error.set(Some(e));
+                }
+            }));
+    }
+}
@@ -0,0 +1,283 @@
+use std::pin::pin;
+
+use cushy::{
+    figures::{units::Lp, Size, Zero},
+    styles::Dimension,
+    value::{Destination, Dynamic, Source},
+    widget::{MakeWidget, WidgetList},
+    widgets::{
+        button::ButtonKind,
+        grid::Orientation,
+        image::ImageCornerRadius,
+        label::{Displayable, LabelOverflow},
+        Image, Space, Stack,
+    },
+};
+use futures_util::StreamExt;
+use rspotify::model::{ShowId, SimplifiedEpisode, SimplifiedShow};
+
+use super::episode::{episode_summary, play, played_marker, resume_position};
+use crate::{
+    api::{with_priority, ApiError, ApiErrorKind, Priority, SpotifyContextRef},
+    icons::{IntoIcon, PLAY},
+    nodebug::NoDebug,
+    rt::WidgetTasks,
+    widgets::{error::error_banner, image::ImageExt, ActivePage, AppContext, SelectedPage},
+};
+
+const EPISODES_PER_PAGE: u32 = 50;
+
+#[derive(Debug)]
+pub struct ShowPage {
+    show: SimplifiedShow,
+    episodes: Episodes,
+    /// Error of the last failed load or playback request.
+    error: Dynamic<Option<ApiError>>,
+
+    context: NoDebug<SpotifyContextRef>,
+    selected_page: SelectedPage,
+    tasks: WidgetTasks,
+}
+
+/// The show's episodes, newest first, loaded a page at a time.
+#[derive(Debug, Clone, Default)]
+struct Episodes {
+    episodes: Dynamic<Vec<SimplifiedEpisode>>,
+    /// `None` until the first page arrived.
+    total: Dynamic<Option<usize>>,
+    loading: Dynamic<bool>,
+}
+
+impl ShowPage {
+    pub fn new(context: SpotifyContextRef, show: SimplifiedShow, app: &AppContext) -> Self {
+        Self {
+            show,
+            episodes: Default::default(),
+            error: Default::default(),
+            context: context.into(),
+            selected_page: app.selected_page.clone(),
+            tasks: Default::default(),
+        }
+    }
+
+    pub fn into_widget(self) -> impl MakeWidget {
+        let ShowPage {
+            show,
+            episodes,
+            error,
+            context,
+            selected_page,
+            tasks,
+        } = self;
+        episodes.load_more(&context, &show.id, &tasks, &error);
+
+        let rows = episodes.episodes.map_each({
+            let (context, id, tasks, error) = (
+                context.clone(),
+                show.id.clone(),
+                tasks.clone(),
+                error.clone(),
+            );
+            move |episodes| {
+                episodes
+                    .iter()
+                    .map(|episode| {
+                        episode_row(
+                            episode,
+                            &context,
+                            &id,
+                            &tasks,
+                            &error,
+                            selected_page.clone(),
+                        )
+                    })
+                    .collect::<WidgetList>()
+            }
+        });
+        let more = (&episodes.episodes, &episodes.total, &episodes.loading).map_each({
+            let (episodes, context, id, tasks, error) = (
+                episodes.clone(),
+                context.clone(),
+                show.id.clone(),
+                tasks.clone(),
+                error.clone(),
+            );
+            move |(loaded, total, loading)| {
+                if *loading || total.map_or(true, |total| loaded.len() >= total) {
+                    return Space::clear().make_widget();
+                }
+                let (episodes, context, id, tasks, error) = (
+                    episodes.clone(),
+                    context.clone(),
+                    id.clone(),
+                    tasks.clone(),
+                    error.clone(),
+                );
+                "Show more"
+                    .into_button()
+                    .on_click(move |_| episodes.load_more(&context, &id, &tasks, &error))
+                    .align_left()
+                    .pad()
+                    .make_widget()
+            }
+        });
+
+        header(&show, episodes.total.clone())
+            .and(error_banner(error))
+            .and(
+                Stack::new(Orientation::Row, rows)
+                    .gutter(Dimension::ZERO)
+                    .and(more)
+                    .into_rows()
+                    .vertical_scroll()
+                    .expand(),
+            )
+            .into_rows()
+    }
+}
+
+impl Episodes {
+    /// Fetches the next page of episodes, unless one is loading already.
+    fn load_more(
+        &self,
+        context: &SpotifyContextRef,
+        id: &ShowId<'static>,
+        tasks: &WidgetTasks,
+        error: &Dynamic<Option<ApiError>>,
+    ) {
+        if self.loading.replace(true).is_none() {
+            // already loading
+            return;
+        }
+        let start = self.episodes.map_ref(|episodes| episodes.len() as u32);
+        let (context, id, error, episodes) =
+            (context.clone(), id.clone(), error.clone(), self.clone());
+        tasks.spawn(with_priority(Priority::Visible, None, async move {
+            let mut pages = pin!(context
+                .paginate(move |context, limit, offset| {
+                    let id = id.clone();
+                    async move { context.show_episodes(id, Some(limit), Some(offset)).await }
+                })
+                .page_size(EPISODES_PER_PAGE)
+                .range(start..start + EPISODES_PER_PAGE)
+                .pages());
+            while let Some(page) = pages.next().await {
+                match page {
+                    Ok(page) => {
+                        episodes.total.set(Some(page.total as usize));
+                        episodes
+                            .episodes
+                            .map_mut(|mut episodes| episodes.extend(page.items));
+                    }
+                    Err(e) => {
+                        if e.kind != ApiErrorKind::Cancelled {
+                            eprintln!("Failed to load episodes: {}", e);
+                            error.set(Some(e));
+                        }
+                        break;
+                    }
+                }
+            }
+            episodes.loading.set(false);
+        }));
+    }
+}
+
+/// Cover, name, publisher, description and number of episodes of the show.
+fn header(show: &SimplifiedShow, total: Dynamic<Option<usize>>) -> impl MakeWidget {
+    let publisher = show.publisher.clone();
+    let summary = total.map_each(move |total| match total {
+        Some(1) => format!("{publisher} • 1 episode"),
+        Some(total) => format!("{publisher} • {total} episodes"),
+        None => publisher.clone(),
+    });
+
+    Image::new_empty()
+        .with_url(Dynamic::new(
+            show.images.first().map(|image| image.url.clone()),
+        ))
+        .with(&ImageCornerRadius, Dimension::Lp(Lp::points(4)))
+        .size(Size::squared(Dimension::Lp(Lp::points(160))))
+        .and(
+            "PODCAST"
+                .into_label()
+                .align_left()
+                .and(
+                    show.name
+                        .clone()
+                        .into_label()
+                        .overflow(LabelOverflow::Clip)
+                        .h1()
+                        .align_left(),
+                )
+                .and(summary.into_label().align_left())
+                .and(show.description.clone().into_label().align_left())
+                .into_rows()
+                .align_left()
+                .expand(),
+        )
+        .into_columns()
+        .pad()
+}
+
+/// Cover, name, release date and progress of the episode, opening its page when clicked,
+/// with a button playing it from where the user stopped.
+fn episode_row(
+    episode: &SimplifiedEpisode,
+    context: &SpotifyContextRef,
+    show: &ShowId<'static>,
+    tasks: &WidgetTasks,
+    error: &Dynamic<Option<ApiError>>,
+    selected_page: SelectedPage,
+) -> impl MakeWidget {
+    let position = resume_position(episode.resume_point.as_ref());
+    let (context, show, id, tasks, error) = (
+        context.clone(),
+        show.clone(),
+        episode.id.clone(),
+        tasks.clone(),
+        error.clone(),
+    );
+    let play_button = PLAY
+        .into_iconbtn()
+        .on_click(move |_| play(&context, &tasks, &error, show.clone(), id.clone(), position));
+
+    let page = ActivePage::Episode(episode.clone());
+    Image::new_empty()
+        .with_url(Dynamic::new(
+            episode.images.first().map(|image| image.url.clone()),
+        ))
+        .with(&ImageCornerRadius, Dimension::Lp(Lp::points(4)))
+        .size(Size::squared(Dimension::Lp(Lp::points(56))))
+        .and(
+            episode
+                .name
+                .clone()
+                .into_label()
+                .overflow(LabelOverflow::Clip)
+                .align_left()
+                .and(
+                    played_marker(episode.resume_point.as_ref())
+                        .and(
+                            episode_summary(
+                                &episode.release_date,
+                                episode.duration,
+                                episode.resume_point.as_ref(),
+                            )
+                            .into_label()
+                            .align_left(),
+                        )
+                        .into_columns()
+                        .align_left(),
+                )
+                .into_rows()
+                .expand(),
+        )
+        .and(play_button)
+        .into_columns()
+        .into_button()
+        .kind(ButtonKind::Transparent)
+        .on_click(move |_| {
+            selected_page.set(page.clone());
+        })
+}
@@ -0,0 +1,117 @@
+use cushy::{
+    figures::units::Lp,
+    value::{Destination, Dynamic, Source},
+    widget::{MakeWidget, WidgetList},
+    widgets::{label::Displayable, Wrap},
+};
+use rspotify::model::Show;
+
+use super::artist::card;
+use crate::{
+    api::{
+        paginate::extend_dynamic, with_priority, ApiError, ApiErrorKind, Priority,
+        SpotifyContextRef,
+    },
+    nodebug::NoDebug,
+    rt::WidgetTasks,
+    widgets::{error::error_banner, ActivePage, AppContext, SelectedPage},
+};
+
+/// The podcasts the user saved, most recently saved first.
+#[derive(Debug)]
+pub struct ShowsPage {
+    shows: Dynamic<Vec<Show>>,
+    /// All of them arrived.
+    loaded: Dynamic<bool>,
+    error: Dynamic<Option<ApiError>>,
+
+    context: NoDebug<SpotifyContextRef>,
+    selected_page: SelectedPage,
+    tasks: WidgetTasks,
+}
+
+impl ShowsPage {
+    pub fn new(context: SpotifyContextRef, app: &AppContext) -> Self {
+        Self {
+            shows: Default::default(),
+            loaded: Default::default(),
+            error: Default::default(),
+            context: context.into(),
+            selected_page: app.selected_page.clone(),
+            tasks: Default::default(),
+        }
+    }
+
+    pub fn into_widget(self) -> impl MakeWidget {
+        let ShowsPage {
+            shows,
+            loaded,
+            error,
+            context,
+            selected_page,
+            tasks,
+        } = self;
+
+        tasks.spawn(with_priority(Priority::Visible, None, {
+            let (context, shows, loaded, error) = (
+                (*context).clone(),
+                shows.clone(),
+                loaded.clone(),
+                error.clone(),
+            );
+            async move {
+                let saved = context
+                    .paginate(|context, limit, offset| async move {
+                        context
+                            .current_user_saved_shows(Some(limit), Some(offset))
+                            .await
+                    })
+                    .items();
+                match extend_dynamic(saved, shows).await {
+                    Ok(()) => loaded.set(true),
+                    Err(e) if e.kind == ApiErrorKind::Cancelled => {}
+                    Err(e) => {
+                        eprintln!("Failed to load saved podcasts: {}", e);
+                        error.set(Some(e));
+                    }
+                }
+            }
+        }));
+
+        let cards = (&shows, &loaded).map_each(move |(shows, loaded)| {
+            if shows.is_empty() && *loaded {
+                return "Podcasts you save show up here."
+                    .into_label()
+                    .align_left()
+                    .make_widget();
+            }
+            let cards = shows
+                .iter()
+                .map(|saved| {
+                    let show = saved.show.clone();
+                    let selected_page = selected_page.clone();
+                    card(
+                        show.images.first().map(|image| image.url.clone()),
+                        show.name.clone(),
+                        show.publisher.clone(),
+                        Lp::points(4),
+                    )
+                    .on_click(move |_| {
+                        selected_page.set(ActivePage::Show(show.clone()));
+                    })
+                    .make_widget()
+                })
+                .collect::<WidgetList>();
+            Wrap::new(cards).align_left().make_widget()
+        });
+
+        "Podcasts"
+            .into_label()
+            .h1()
+            .align_left()
+            .pad()
+            .and(error_banner(error))
+            .and(cards.pad().vertical_scroll().expand())
+            .into_rows()
+    }
+}
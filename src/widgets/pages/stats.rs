@@ -0,0 +1,418 @@
+use chrono::{Datelike, Local, NaiveDate};
+use cushy::{
+    figures::{units::Lp, Size},
+    styles::{Dimension, DimensionRange},
+    value::{Destination, Dynamic, Source},
+    widget::{MakeWidget, WidgetInstance, WidgetList},
+    widgets::{
+        button::ButtonKind,
+        image::ImageCornerRadius,
+        label::{Displayable, LabelOverflow},
+        Image,
+    },
+};
+use itertools::Itertools;
+use rspotify::model::{
+    ArtistId, FullArtist, FullTrack, Offset, PlayContextId, PlayHistory, PlayableId, TimeRange,
+};
+use rspotify::prelude::*;
+
+use crate::{
+    api::{with_priority, ApiError, ApiErrorKind, Interest, Priority, SpotifyContextRef},
+    icons::{IntoIcon, PLAY},
+    nodebug::NoDebug,
+    rt::WidgetTasks,
+    widgets::{
+        error::error_banner,
+        image::ImageExt,
+        track::{track_image, track_row, TrackRow},
+        ActivePage, AppContext,
+    },
+};
+
+/// The periods Spotify ranks the top tracks and artists over.
+const RANGES: [(TimeRange, &str); 3] = [
+    (TimeRange::ShortTerm, "Last 4 weeks"),
+    (TimeRange::MediumTerm, "Last 6 months"),
+    (TimeRange::LongTerm, "All time"),
+];
+/// How many of the top tracks and artists are shown.
+const TOP_LIMIT: u32 = 50;
+
+/// The user's top tracks and artists, and what they played last.
+#[derive(Debug)]
+pub struct StatsPage {
+    /// Index into [`RANGES`] of the period shown.
+    range: Dynamic<usize>,
+    /// The top tracks and artists of each of [`RANGES`].
+    top: [Top; 3],
+    /// `None` until it's loaded.
+    recently_played: Dynamic<Option<Vec<PlayHistory>>>,
+    /// Error of the last failed load or playback request.
+    error: Dynamic<Option<ApiError>>,
+
+    context: NoDebug<SpotifyContextRef>,
+    app: AppContext,
+    tasks: WidgetTasks,
+}
+
+/// Top tracks and artists over one period, `None` until they're loaded.
+#[derive(Debug, Clone, Default)]
+struct Top {
+    tracks: Dynamic<Option<Vec<FullTrack>>>,
+    artists: Dynamic<Option<Vec<FullArtist>>>,
+}
+
+/// What the entries of the page need to be shown and played.
+#[derive(Debug, Clone)]
+struct Entries {
+    context: NoDebug<SpotifyContextRef>,
+    app: AppContext,
+    error: Dynamic<Option<ApiError>>,
+    tasks: WidgetTasks,
+}
+
+impl StatsPage {
+    pub fn new(context: SpotifyContextRef, app: &AppContext) -> Self {
+        Self {
+            range: Default::default(),
+            top: Default::default(),
+            recently_played: Default::default(),
+            error: Default::default(),
+            context: context.into(),
+            app: app.clone(),
+            tasks: Default::default(),
+        }
+    }
+
+    pub fn into_widget(self) -> impl MakeWidget {
+        let StatsPage {
+            range,
+            top,
+            recently_played,
+            error,
+            context,
+            app,
+            tasks,
+        } = self;
+
+        for (index, top) in top.iter().enumerate() {
+            // the other periods are likely to be looked at next
+            let priority = if index == range.get() {
+                Priority::Visible
+            } else {
+                Priority::Prefetch
+            };
+            tasks.spawn(with_priority(
+                priority,
+                None,
+                load_top(
+                    (*context).clone(),
+                    RANGES[index].0,
+                    top.clone(),
+                    error.clone(),
+                ),
+            ));
+        }
+        tasks.spawn(with_priority(
+            Priority::Visible,
+            None,
+            load_recently_played((*context).clone(), recently_played.clone(), error.clone()),
+        ));
+
+        let entries = Entries {
+            context,
+            app,
+            error: error.clone(),
+            tasks,
+        };
+        let top = range.map_each({
+            let entries = entries.clone();
+            move |range| {
+                let top = &top[*range];
+                "Top tracks"
+                    .into_label()
+                    .h3()
+                    .align_left()
+                    .and(top.tracks.map_each({
+                        let entries = entries.clone();
+                        move |tracks| match tracks {
+                            Some(tracks) if tracks.is_empty() => {
+                                "Play some music to see your top tracks here."
+                                    .align_left()
+                                    .make_widget()
+                            }
+                            Some(tracks) => entries
+                                .tracks(tracks, 1, &playable_ids(tracks))
+                                .into_iter()
+                                .collect::<WidgetList>()
+                                .into_rows()
+                                .make_widget(),
+                            None => "Loading...".align_left().make_widget(),
+                        }
+                    }))
+                    .and("Top artists".into_label().h3().align_left())
+                    .and(top.artists.map_each({
+                        let entries = entries.clone();
+                        move |artists| match artists {
+                            Some(artists) => artists
+                                .iter()
+                                .enumerate()
+                                .map(|(index, artist)| entries.artist(index + 1, artist))
+                                .collect::<WidgetList>()
+                                .into_rows()
+                                .make_widget(),
+                            None => "Loading...".align_left().make_widget(),
+                        }
+                    }))
+                    .into_rows()
+                    .make_widget()
+            }
+        });
+        let recently_played = recently_played.map_each(move |played| match played {
+            Some(played) => recently_played_widget(played, &entries).make_widget(),
+            None => "Loading...".align_left().make_widget(),
+        });
+
+        "Your stats"
+            .into_label()
+            .h1()
+            .align_left()
+            .and(range_picker(&range))
+            .and(error_banner(error))
+            .and(top)
+            .and("Recently played".into_label().h3().align_left())
+            .and(recently_played)
+            .into_rows()
+            .pad()
+            .vertical_scroll()
+            .expand()
+    }
+}
+
+impl Entries {
+    /// Rows of `tracks`, numbered from `first`. Each plays `ids` starting at itself.
+    fn tracks(
+        &self,
+        tracks: &[FullTrack],
+        first: usize,
+        ids: &[PlayableId<'static>],
+    ) -> Vec<WidgetInstance> {
+        let interest = Interest::default();
+        tracks
+            .iter()
+            .enumerate()
+            .map(|(index, track)| {
+                let row = Dynamic::new(Some(TrackRow::from_track(track)));
+                let image = track_image(&row);
+                let uri = track.id.as_ref().map(|id| id.uri());
+                let (entries, ids) = (self.clone(), ids.to_vec());
+                track_row(first + index, row, Some(image), &interest, &self.app)
+                    .into_button()
+                    .kind(ButtonKind::Transparent)
+                    .on_click(move |_| {
+                        if let Some(uri) = uri.clone() {
+                            entries.play_tracks(ids.clone(), uri);
+                        }
+                    })
+                    .make_widget()
+            })
+            .collect()
+    }
+
+    /// A row of `artist`, opening its page, with a button playing it.
+    fn artist(&self, number: usize, artist: &FullArtist) -> impl MakeWidget {
+        let page = ActivePage::artist(artist);
+        let (selected_page, entries, id) = (
+            self.app.selected_page.clone(),
+            self.clone(),
+            artist.id.clone(),
+        );
+        let genres = artist.genres.iter().take(3).join(", ");
+        number
+            .to_string()
+            .align_right()
+            .size(Size {
+                width: Dimension::Lp(Lp::points(30)).into(),
+                height: DimensionRange::default(),
+            })
+            .and(
+                Image::new_empty()
+                    .with_url(Dynamic::new(
+                        artist.images.first().map(|image| image.url.clone()),
+                    ))
+                    .with(&ImageCornerRadius, Dimension::Lp(Lp::points(20)))
+                    .size(Size::squared(Dimension::Lp(Lp::points(40)))),
+            )
+            .and(
+                artist
+                    .name
+                    .clone()
+                    .into_label()
+                    .overflow(LabelOverflow::Clip)
+                    .align_left()
+                    .and(
+                        genres
+                            .into_label()
+                            .overflow(LabelOverflow::Clip)
+                            .align_left(),
+                    )
+                    .into_rows()
+                    .align_left()
+                    .expand(),
+            )
+            .and(
+                PLAY.into_iconbtn()
+                    .on_click(move |_| entries.play_artist(id.clone())),
+            )
+            .into_columns()
+            .size(Size {
+                width: DimensionRange::default(),
+                height: Dimension::Lp(Lp::points(60)).into(),
+            })
+            .into_button()
+            .kind(ButtonKind::Transparent)
+            .on_click(move |_| selected_page.set(page.clone()))
+    }
+
+    /// Plays `ids` as a list of their own, starting at `uri`.
+    fn play_tracks(&self, ids: Vec<PlayableId<'static>>, uri: String) {
+        let (context, error) = ((*self.context).clone(), self.error.clone());
+        self.tasks
+            .spawn(with_priority(Priority::UserAction, None, async move {
+                if let Err(e) = context.play_uris(ids, Some(Offset::Uri(uri))).await {
+                    eprintln!("Failed to play tracks: {}", e);
+                    error.set(Some(e));
+                }
+            }));
+    }
+
+    fn play_artist(&self, id: ArtistId<'static>) {
+        let (context, error) = ((*self.context).clone(), self.error.clone());
+        self.tasks
+            .spawn(with_priority(Priority::UserAction, None, async move {
+                if let Err(e) = context.play_context(PlayContextId::Artist(id), None).await {
+                    eprintln!("Failed to play artist: {}", e);
+                    error.set(Some(e));
+                }
+            }));
+    }
+}
+
+/// Buttons choosing which of [`RANGES`] the top tracks and artists are over.
+fn range_picker(range: &Dynamic<usize>) -> impl MakeWidget {
+    RANGES
+        .iter()
+        .enumerate()
+        .map(|(index, &(_, label))| {
+            let kind = range.map_each(move |range| {
+                if *range == index {
+                    ButtonKind::Solid
+                } else {
+                    ButtonKind::Transparent
+                }
+            });
+            let range = range.clone();
+            label
+                .into_button()
+                .kind(kind)
+                .on_click(move |_| range.set(index))
+        })
+        .collect::<WidgetList>()
+        .into_columns()
+        .align_left()
+}
+
+/// What was played last, under a heading for each day.
+fn recently_played_widget(played: &[PlayHistory], entries: &Entries) -> impl MakeWidget {
+    if played.is_empty() {
+        return "Nothing played recently.".align_left().make_widget();
+    }
+    // playing one of them plays the rest after it, but only once each
+    let ids = playable_ids(
+        &played
+            .iter()
+            .map(|played| played.track.clone())
+            .unique_by(|track| track.id.clone())
+            .collect::<Vec<_>>(),
+    );
+    let today = Local::now().date_naive();
+    let mut rows = WidgetList::new();
+    let mut first = 1;
+    for (day, group) in &played
+        .iter()
+        .group_by(|played| played.played_at.with_timezone(&Local).date_naive())
+    {
+        let tracks = group.map(|played| played.track.clone()).collect::<Vec<_>>();
+        rows.push(day_label(day, today).into_label().h5().align_left());
+        for row in entries.tracks(&tracks, first, &ids) {
+            rows.push(row);
+        }
+        first += tracks.len();
+    }
+    rows.into_rows().make_widget()
+}
+
+/// `day` as it's shown above what was played on it.
+fn day_label(day: NaiveDate, today: NaiveDate) -> String {
+    if day == today {
+        "Today".to_string()
+    } else if today.pred_opt() == Some(day) {
+        "Yesterday".to_string()
+    } else if day.year() == today.year() {
+        day.format("%A, %B %-e").to_string()
+    } else {
+        day.format("%A, %B %-e, %Y").to_string()
+    }
+}
+
+fn playable_ids(tracks: &[FullTrack]) -> Vec<PlayableId<'static>> {
+    tracks
+        .iter()
+        .filter_map(|track| Some(PlayableId::Track(track.id.clone()?)))
+        .collect()
+}
+
+async fn load_top(
+    context: SpotifyContextRef,
+    range: TimeRange,
+    top: Top,
+    error: Dynamic<Option<ApiError>>,
+) {
+    match context
+        .current_user_top_tracks(range, Some(TOP_LIMIT), Some(0))
+        .await
+    {
+        Ok(page) => top.tracks.set(Some(page.items)),
+        Err(e) => {
+            report(&error, "top tracks", e);
+            return;
+        }
+    }
+    match context
+        .current_user_top_artists(range, Some(TOP_LIMIT), Some(0))
+        .await
+    {
+        Ok(page) => top.artists.set(Some(page.items)),
+        Err(e) => report(&error, "top artists", e),
+    }
+}
+
+async fn load_recently_played(
+    context: SpotifyContextRef,
+    recently_played: Dynamic<Option<Vec<PlayHistory>>>,
+    error: Dynamic<Option<ApiError>>,
+) {
+    match context.current_user_recently_played(Some(50)).await {
+        Ok(page) => recently_played.set(Some(page.items)),
+        Err(e) => report(&error, "recently played tracks", e),
+    }
+}
+
+fn report(error: &Dynamic<Option<ApiError>>, what: &str, e: ApiError) {
+    if e.kind == ApiErrorKind::Cancelled {
+        return;
+    }
+    eprintln!("Failed to load {}: {}", what, e);
+    error.set(Some(e));
+}
@@ -1,33 +1,67 @@
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
 use cushy::{
     figures::{units::Lp, Size},
-    styles::{Dimension, DimensionRange},
-    value::{Dynamic, Source},
+    styles::{components::TextColor, Dimension, DimensionRange},
+    value::{Destination, Dynamic, Source},
     widget::MakeWidget,
     widgets::{
+        button::ButtonKind,
         image::ImageCornerRadius,
         label::{Displayable, LabelOverflow},
-        Button, Image, Label, Slider,
+        Button, Image, Label, Slider, Space,
     },
 };
-use itertools::Itertools;
+use librespot_core::SpotifyId;
 use librespot_metadata::audio::UniqueFields;
+use rspotify::model::{ArtistId, EpisodeId, SimplifiedArtist, TrackId};
+use rspotify::prelude::*;
 
 use crate::{
-    icons::{icon, iconbtn, IntoIcon, PAUSE, PLAY, REPEAT, SHUFFLE, SKIP_NEXT, SKIP_PREVIOUS},
+    api::{with_priority, Priority, SpotifyContextRef},
+    icons::{
+        icon, iconbtn, IntoIcon, PAUSE, PLAY, QUEUE_MUSIC, REPEAT, SHUFFLE, SKIP_NEXT,
+        SKIP_PREVIOUS,
+    },
     player::{DynamicPlayer, PlayerState},
-    widgets::image::ImageExt,
+    rt::tokio_runtime,
+    theme::TEXT_SPOTIFY,
+    widgets::{
+        image::ImageExt,
+        like::like_button,
+        link::{artist_links, link},
+        ActivePage, AppContext, SelectedPage,
+    },
 };
 
-pub fn bar(player: DynamicPlayer) -> impl MakeWidget {
-    meta(player).size(Size {
+/// `queue_open` is toggled by the queue button.
+pub fn bar(
+    context: SpotifyContextRef,
+    app: &AppContext,
+    queue_open: Dynamic<bool>,
+) -> impl MakeWidget {
+    meta(context, app, queue_open).size(Size {
         width: DimensionRange::default(),
         height: Dimension::Lp(Lp::inches_f(1.)).into(),
     })
 }
 
-fn meta(player: DynamicPlayer) -> impl MakeWidget {
+fn meta(
+    context: SpotifyContextRef,
+    app: &AppContext,
+    queue_open: Dynamic<bool>,
+) -> impl MakeWidget {
+    let player = context.player.clone();
+    let selected_page = app.selected_page.clone();
+    let like = like_button(
+        &app.likes,
+        player.track.map_each(|track| {
+            let uri = &track.as_ref()?.uri;
+            TrackId::from_uri(uri).ok().map(TrackId::into_static)
+        }),
+        // the current track is always shown
+        None,
+    );
     Image::new_empty()
         .with_url(player.track.map_each(|track| {
             track
@@ -42,7 +76,7 @@ fn meta(player: DynamicPlayer) -> impl MakeWidget {
         .and(
             player
                 .track
-                .map_each(|track| {
+                .map_each(move |track| {
                     track
                         .as_ref()
                         .map(|track| {
@@ -51,45 +85,116 @@ fn meta(player: DynamicPlayer) -> impl MakeWidget {
                                 .clone()
                                 .into_label()
                                 .overflow(LabelOverflow::Clip)
-                                .and(
-                                    match &track.unique_fields {
-                                        UniqueFields::Track {
-                                            artists,
-                                            album,
-                                            album_artists,
-                                            popularity,
-                                            number,
-                                            disc_number,
-                                        } => artists
+                                .and(match &track.unique_fields {
+                                    UniqueFields::Track { artists, album, .. } => artist_links(
+                                        &artists
                                             .iter()
-                                            .map(|artist| artist.name.clone())
-                                            .join(", "),
-                                        UniqueFields::Episode {
-                                            description,
-                                            publish_time,
-                                            show_name,
-                                        } => show_name.clone(),
-                                    }
-                                    .into_label()
-                                    .overflow(LabelOverflow::Clip),
-                                )
+                                            .map(|artist| {
+                                                simplified_artist(artist.id, &artist.name)
+                                            })
+                                            .collect::<Vec<_>>(),
+                                        selected_page.clone(),
+                                    )
+                                    .and("•")
+                                    .and(album_link(
+                                        album.clone(),
+                                        track.uri.clone(),
+                                        context.clone(),
+                                        selected_page.clone(),
+                                    ))
+                                    .into_columns()
+                                    .make_widget(),
+                                    UniqueFields::Episode { show_name, .. } => show_link(
+                                        show_name.clone(),
+                                        track.uri.clone(),
+                                        context.clone(),
+                                        selected_page.clone(),
+                                    )
+                                    .make_widget(),
+                                })
                                 .into_rows()
                                 .make_widget()
                         })
                         .unwrap_or(Label::<String>::new("No track found").make_widget())
                 })
                 .align_left()
-                .pad()
-                .expand(),
+                .pad(),
         )
+        .and(like.centered())
+        .and(Space::clear().expand())
         .into_columns()
         .align_left()
         .expand()
         .and(controls(player.clone()).expand())
-        .and(vol(player).align_right().expand())
+        .and(
+            queue_button(queue_open)
+                .and(vol(player))
+                .into_columns()
+                .align_right()
+                .expand(),
+        )
         .into_columns()
 }
 
+/// The Web API's version of an artist librespot knows, so it can be linked.
+fn simplified_artist(id: SpotifyId, name: &str) -> SimplifiedArtist {
+    SimplifiedArtist {
+        external_urls: HashMap::new(),
+        href: None,
+        id: id
+            .to_base62()
+            .ok()
+            .and_then(|id| ArtistId::from_id(id).ok()),
+        name: name.to_string(),
+    }
+}
+
+/// Opens the album of the playing track, which first has to be looked up by the track.
+fn album_link(
+    album: String,
+    uri: String,
+    context: SpotifyContextRef,
+    selected_page: SelectedPage,
+) -> impl MakeWidget {
+    link(album, move |_| {
+        let Ok(id) = TrackId::from_uri(&uri) else {
+            return;
+        };
+        let id = id.into_static();
+        let context = context.clone();
+        let selected_page = selected_page.clone();
+        tokio_runtime().spawn(with_priority(Priority::UserAction, None, async move {
+            match context.track(id).await {
+                Ok(track) => selected_page.set(ActivePage::Album(track.album)),
+                Err(e) => eprintln!("Failed to open album: {}", e),
+            }
+        }));
+    })
+}
+
+/// The show of the episode `uri`, opening its page once the episode is loaded.
+fn show_link(
+    show: String,
+    uri: String,
+    context: SpotifyContextRef,
+    selected_page: SelectedPage,
+) -> impl MakeWidget {
+    link(show, move |_| {
+        let Ok(id) = EpisodeId::from_uri(&uri) else {
+            return;
+        };
+        let id = id.into_static();
+        let context = context.clone();
+        let selected_page = selected_page.clone();
+        tokio_runtime().spawn(with_priority(Priority::UserAction, None, async move {
+            match context.episode(id).await {
+                Ok(episode) => selected_page.set(ActivePage::Show(episode.show)),
+                Err(e) => eprintln!("Failed to open show: {}", e),
+            }
+        }));
+    })
+}
+
 fn controls(player: DynamicPlayer) -> impl MakeWidget {
     iconbtn(SHUFFLE)
         .and(iconbtn(SKIP_PREVIOUS))
@@ -162,6 +267,31 @@ fn format_time(time: Duration) -> String {
     format!("{}:{:02}", minutes.round(), seconds.round())
 }
 
+/// Opens and closes the queue panel, highlighted while it's open.
+fn queue_button(queue_open: Dynamic<bool>) -> impl MakeWidget {
+    // the button toggles it, which mustn't keep it alive
+    let weak = queue_open.downgrade();
+    queue_open.map_each(move |open| {
+        let label = if *open {
+            icon(QUEUE_MUSIC)
+                .with(&TextColor, TEXT_SPOTIFY)
+                .make_widget()
+        } else {
+            icon(QUEUE_MUSIC).make_widget()
+        };
+        let weak = weak.clone();
+        label
+            .into_button()
+            .kind(ButtonKind::Transparent)
+            .on_click(move |_| {
+                if let Some(queue_open) = weak.upgrade() {
+                    queue_open.toggle();
+                }
+            })
+            .make_widget()
+    })
+}
+
 fn vol(player: DynamicPlayer) -> impl MakeWidget {
     "vol control here".pad()
 }
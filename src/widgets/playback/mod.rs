@@ -1 +1,2 @@
 pub mod bar;
+pub mod queue;
@@ -0,0 +1,187 @@
+use cushy::{
+    figures::{units::Lp, Size},
+    styles::{Dimension, DimensionRange},
+    value::{Dynamic, Source},
+    widget::{MakeWidget, WidgetList},
+    widgets::{
+        label::{Displayable, LabelOverflow},
+        Space,
+    },
+};
+use rspotify::model::PlayableItem;
+
+use crate::{
+    icons::{IntoIcon, ADD_TO_QUEUE, DELETE, DRAG_INDICATOR, QUEUE_PLAY_NEXT, RADIO},
+    queue::{Queue, QueueState},
+    radio::Radio,
+    widgets::{
+        drag::DragHandle,
+        track::{track_image, TrackRow},
+    },
+};
+
+/// Buttons queueing the track or episode `uri` to play next, or after what's queued already.
+/// Nothing for local files.
+pub fn queue_buttons(queue: &Queue, uri: Dynamic<Option<String>>) -> impl MakeWidget {
+    let queue = queue.clone();
+    uri.map_each(move |uri| {
+        let Some(uri) = uri.clone() else {
+            return Space::clear().make_widget();
+        };
+        let (next, add) = (queue.clone(), queue.clone());
+        let next_uri = uri.clone();
+        QUEUE_PLAY_NEXT
+            .into_iconbtn()
+            .on_click(move |_| next.play_next(next_uri.clone()))
+            .and(
+                ADD_TO_QUEUE
+                    .into_iconbtn()
+                    .on_click(move |_| add.add(uri.clone())),
+            )
+            .into_columns()
+            .make_widget()
+    })
+}
+
+/// Side panel with what's playing, the tracks the user queued and the rest of what's
+/// playing after them, and the radio that's playing if there is one.
+pub fn queue_panel(queue: &Queue, radio: &Radio) -> impl MakeWidget {
+    let sections = queue.state.map_each({
+        let queue = queue.clone();
+        move |state| sections(&queue, state).make_widget()
+    });
+    "Queue"
+        .into_label()
+        .h3()
+        .align_left()
+        .and(radio_status(radio))
+        .and(sections.vertical_scroll().expand())
+        .into_rows()
+        .pad()
+        .size(Size {
+            width: Dimension::Lp(Lp::points(320)).into(),
+            height: DimensionRange::default(),
+        })
+}
+
+/// Which radio is playing, with a button stopping it.
+fn radio_status(radio: &Radio) -> impl MakeWidget {
+    let radio = radio.clone();
+    radio.seed.clone().map_each(move |seed| {
+        let Some(seed) = seed else {
+            return Space::clear().make_widget();
+        };
+        let radio = radio.clone();
+        RADIO
+            .into_icon()
+            .and(
+                format!("{} radio", seed.name())
+                    .into_label()
+                    .overflow(LabelOverflow::Clip)
+                    .align_left()
+                    .expand(),
+            )
+            .and("Stop".into_button().on_click(move |_| radio.stop()))
+            .into_columns()
+            .make_widget()
+    })
+}
+
+fn sections(queue: &Queue, state: &QueueState) -> impl MakeWidget {
+    let mut rows = WidgetList::new();
+    rows.push(heading("Now playing"));
+    match &state.current {
+        Some(uri) => rows.push(entry(queue, uri, Space::clear())),
+        None => rows.push("Nothing is playing".align_left()),
+    }
+
+    let editable = state.editable();
+    for (title, positions) in [
+        ("Next in queue", state.queued()),
+        ("Next up", state.upcoming()),
+    ] {
+        if positions.is_empty() {
+            continue;
+        }
+        rows.push(heading(title));
+        for (index, &position) in positions.iter().enumerate() {
+            let uri = &state.next_tracks[position].uri;
+            let controls = if editable {
+                controls(queue, &positions, index).make_widget()
+            } else {
+                Space::clear().make_widget()
+            };
+            rows.push(entry(queue, uri, controls));
+        }
+    }
+    rows.into_rows()
+}
+
+fn heading(title: &'static str) -> impl MakeWidget {
+    title.into_label().h5().align_left()
+}
+
+/// Handle to move the track at `index` of a section, whose tracks are at `positions` of the
+/// next tracks, within that section, and a button removing it.
+fn controls(queue: &Queue, positions: &[usize], index: usize) -> impl MakeWidget {
+    let position = positions[index];
+    let target = {
+        let positions = positions.to_vec();
+        move |rows: isize| {
+            let last = positions.len() as isize - 1;
+            positions[(index as isize + rows).clamp(0, last) as usize]
+        }
+    };
+    let (reorder, remove) = (queue.clone(), queue.clone());
+    DragHandle::new(
+        DRAG_INDICATOR.into_icon().centered(),
+        // the height of an entry
+        Lp::points(50),
+        |_| {},
+        move |rows| reorder.reorder(position, target(rows)),
+    )
+    .and(
+        DELETE
+            .into_iconbtn()
+            .on_click(move |_| remove.remove(position)),
+    )
+    .into_columns()
+}
+
+/// Cover, name and artists of the track or episode `uri`, with `controls` next to them.
+fn entry(queue: &Queue, uri: &str, controls: impl MakeWidget) -> impl MakeWidget {
+    let row = queue.item(uri).map_each(|item| match item.as_ref()? {
+        PlayableItem::Track(track) => Some(TrackRow::from_track(track)),
+        PlayableItem::Episode(episode) => Some(TrackRow::from_episode(episode)),
+    });
+    let image = track_image(&row);
+    image
+        .and(
+            row.map_each(|row| match row {
+                Some(row) => row
+                    .name
+                    .clone()
+                    .into_label()
+                    .overflow(LabelOverflow::Clip)
+                    .align_left()
+                    .and(
+                        row.subtitle
+                            .clone()
+                            .into_label()
+                            .overflow(LabelOverflow::Clip)
+                            .align_left(),
+                    )
+                    .into_rows()
+                    .make_widget(),
+                None => Space::primary().make_widget(),
+            })
+            .align_left()
+            .expand(),
+        )
+        .and(controls)
+        .into_columns()
+        .size(Size {
+            width: DimensionRange::default(),
+            height: Dimension::Lp(Lp::points(50)).into(),
+        })
+}
@@ -0,0 +1,37 @@
+use cushy::{
+    value::{Dynamic, Source},
+    widget::MakeWidget,
+    widgets::Space,
+};
+
+use crate::{
+    icons::{IntoIcon, RADIO},
+    radio::{Radio, RadioSeed},
+};
+
+/// Button starting a radio of `seed`, for track rows. Nothing while there's no seed, like for
+/// local files.
+pub fn radio_button(radio: &Radio, seed: Dynamic<Option<RadioSeed>>) -> impl MakeWidget {
+    let radio = radio.clone();
+    seed.map_each(move |seed| {
+        let Some(seed) = seed.clone() else {
+            return Space::clear().make_widget();
+        };
+        let radio = radio.clone();
+        RADIO
+            .into_iconbtn()
+            .on_click(move |_| radio.start(seed.clone()))
+            .make_widget()
+    })
+}
+
+/// "Radio" button starting a radio of `seed`, for page headers.
+pub fn start_radio_button(radio: &Radio, seed: RadioSeed) -> impl MakeWidget {
+    let radio = radio.clone();
+    RADIO
+        .into_icon()
+        .and("Radio")
+        .into_columns()
+        .into_button()
+        .on_click(move |_| radio.start(seed.clone()))
+}
@@ -0,0 +1,281 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use cushy::{
+    figures::{units::Lp, Size},
+    styles::{Dimension, DimensionRange, Edges},
+    value::{Destination, Dynamic, Source},
+    widget::{MakeWidget, WidgetInstance},
+    widgets::{
+        image::ImageCornerRadius,
+        label::{Displayable, LabelOverflow},
+        Image, Label, Space,
+    },
+};
+use itertools::Itertools;
+use rspotify::model::{
+    FullEpisode, FullTrack, PlayableItem, PlaylistItem, SavedTrack, SimplifiedAlbum,
+    SimplifiedArtist, SimplifiedTrack, TrackId,
+};
+use rspotify::prelude::*;
+
+use crate::{
+    api::Interest,
+    radio::RadioSeed,
+    widgets::{
+        image::ImageExt,
+        like::like_button,
+        link::{artist_links, link},
+        playback::queue::queue_buttons,
+        radio::radio_button,
+        ActivePage, AppContext,
+    },
+};
+
+/// What a row of a track list shows, the same for tracks and podcast episodes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackRow {
+    pub name: String,
+    /// Artists of a track, or the show of an episode.
+    pub subtitle: String,
+    /// Linked from the subtitle, empty for episodes.
+    pub artists: Vec<SimplifiedArtist>,
+    /// Album of a track, or the show of an episode.
+    pub collection: String,
+    /// Linked from the collection column, if it's a track's album.
+    pub album: Option<SimplifiedAlbum>,
+    pub image_url: Option<String>,
+    pub added_at: Option<DateTime<Utc>>,
+    pub duration: TimeDelta,
+    /// `None` for local files, which can't be played from here.
+    pub uri: Option<String>,
+}
+
+impl TrackRow {
+    pub fn from_track(track: &FullTrack) -> Self {
+        Self {
+            name: track.name.clone(),
+            subtitle: track
+                .artists
+                .iter()
+                .map(|artist| artist.name.clone())
+                .join(", "),
+            artists: track.artists.clone(),
+            collection: track.album.name.clone(),
+            album: track.album.id.is_some().then(|| track.album.clone()),
+            image_url: track.album.images.first().map(|image| image.url.clone()),
+            added_at: None,
+            duration: track.duration,
+            uri: track.id.as_ref().map(|id| id.uri()),
+        }
+    }
+
+    /// A track of `album`, whose tracks don't come with the album itself.
+    pub fn from_album_track(track: &SimplifiedTrack, album: &SimplifiedAlbum) -> Self {
+        Self {
+            name: track.name.clone(),
+            subtitle: track
+                .artists
+                .iter()
+                .map(|artist| artist.name.clone())
+                .join(", "),
+            artists: track.artists.clone(),
+            collection: album.name.clone(),
+            album: album.id.is_some().then(|| album.clone()),
+            image_url: album.images.first().map(|image| image.url.clone()),
+            added_at: None,
+            duration: track.duration,
+            uri: track.id.as_ref().map(|id| id.uri()),
+        }
+    }
+
+    pub fn from_saved_track(saved: &SavedTrack) -> Self {
+        Self {
+            added_at: Some(saved.added_at),
+            ..Self::from_track(&saved.track)
+        }
+    }
+
+    pub fn from_episode(episode: &FullEpisode) -> Self {
+        Self {
+            name: episode.name.clone(),
+            subtitle: episode.show.name.clone(),
+            artists: Vec::new(),
+            collection: episode.show.name.clone(),
+            album: None,
+            image_url: episode.images.first().map(|image| image.url.clone()),
+            added_at: None,
+            duration: episode.duration,
+            uri: Some(episode.id.uri()),
+        }
+    }
+
+    /// `None` for items that aren't available anymore.
+    pub fn from_playlist_item(item: &PlaylistItem) -> Option<Self> {
+        let mut row = match item.track.as_ref()? {
+            PlayableItem::Track(track) => Self::from_track(track),
+            PlayableItem::Episode(episode) => Self::from_episode(episode),
+        };
+        if item.is_local {
+            row.uri = None;
+        }
+        row.added_at = item.added_at;
+        Some(row)
+    }
+
+    /// `None` for episodes and local files.
+    pub fn track_id(&self) -> Option<TrackId<'static>> {
+        let uri = self.uri.as_deref()?;
+        TrackId::from_uri(uri).ok().map(TrackId::into_static)
+    }
+}
+
+/// Cover of the row's album or show. Should be created once per row index and reused, see
+/// [`ImageExt::load_url`].
+pub fn track_image(row: &Dynamic<Option<TrackRow>>) -> WidgetInstance {
+    Image::new_empty()
+        .with_url(row.map_each(|row| row.as_ref().and_then(|row| row.image_url.clone())))
+        .size(Size::squared(Dimension::Lp(Lp::points(40))))
+        .with(&ImageCornerRadius, Dimension::Lp(Lp::points(4)))
+        .make_widget()
+}
+
+/// The columns of a track list row, placeholders until `row` is loaded. `number` is shown
+/// in the first column, and `image` next to it if given. Requests for the row are dropped
+/// once `interest` is gone, the row holds on to it.
+pub fn track_row(
+    number: usize,
+    row: Dynamic<Option<TrackRow>>,
+    image: Option<WidgetInstance>,
+    interest: &Interest,
+    app: &AppContext,
+) -> impl MakeWidget {
+    let like = like_button(
+        &app.likes,
+        row.map_each(|row| row.as_ref().and_then(TrackRow::track_id)),
+        Some(interest),
+    );
+    let queue = queue_buttons(&app.queue, row.map_each(|row| row.as_ref()?.uri.clone()));
+    let radio = radio_button(
+        &app.radio,
+        row.map_each(|row| {
+            let row = row.as_ref()?;
+            Some(RadioSeed::Track {
+                id: row.track_id()?,
+                name: row.name.clone(),
+            })
+        }),
+    );
+    let column = |f: fn(&TrackRow) -> String| {
+        row.map_each(move |row| {
+            row.as_ref()
+                .map(|row| {
+                    f(row)
+                        .into_label()
+                        .overflow(LabelOverflow::Clip)
+                        .make_widget()
+                })
+                .unwrap_or(Space::primary().make_widget())
+        })
+    };
+    let selected_page = app.selected_page.clone();
+
+    let number = number.to_string().align_right().size(Size {
+        width: Dimension::Lp(Lp::points(30)).into(),
+        height: DimensionRange::default(),
+    });
+    let columns = match image {
+        Some(image) => number.and(image.size(Size::squared(Dimension::Lp(Lp::points(40))))),
+        None => number.and(Space::clear()),
+    };
+    columns
+        .and(
+            row.map_each({
+                let selected_page = selected_page.clone();
+                move |row| {
+                    row.as_ref()
+                        .map(|row| {
+                            let subtitle = if row.artists.is_empty() {
+                                Label::new(row.subtitle.clone())
+                                    .overflow(LabelOverflow::Clip)
+                                    .make_widget()
+                            } else {
+                                artist_links(&row.artists, selected_page.clone()).make_widget()
+                            };
+                            Label::new(row.name.clone())
+                                .overflow(LabelOverflow::Clip)
+                                .align_left()
+                                .and(subtitle.align_left())
+                                .into_rows()
+                                .make_widget()
+                        })
+                        .unwrap_or(Space::primary().make_widget())
+                }
+            })
+            .align_left()
+            .expand_weighted(2),
+        )
+        .and(
+            row.map_each(move |row| match row {
+                Some(TrackRow {
+                    collection,
+                    album: Some(album),
+                    ..
+                }) => {
+                    let album = album.clone();
+                    let selected_page = selected_page.clone();
+                    link(collection.clone(), move |_| {
+                        selected_page.set(ActivePage::Album(album.clone()));
+                    })
+                    .make_widget()
+                }
+                Some(row) => row
+                    .collection
+                    .clone()
+                    .into_label()
+                    .overflow(LabelOverflow::Clip)
+                    .make_widget(),
+                None => Space::primary().make_widget(),
+            })
+            .align_left()
+            .expand_weighted(1),
+        )
+        .and(
+            column(|row| {
+                row.added_at
+                    .map(|added_at| added_at.format("%B %-e, %Y").to_string())
+                    .unwrap_or_default()
+            })
+            .align_left()
+            .expand_weighted(1),
+        )
+        .and(radio)
+        .and(queue)
+        .and(like)
+        .and(
+            column(|row| format_delta(row.duration))
+                .align_right()
+                .pad_by(Edges::default().with_horizontal(Dimension::Lp(Lp::points(5)))),
+        )
+        .into_columns()
+        .centered()
+        .size(Size {
+            width: DimensionRange::default(),
+            height: Dimension::Lp(Lp::points(60)).into(),
+        })
+        .expand_horizontally()
+}
+
+/// Formats a track length as `m:ss`.
+pub fn format_delta(delta: TimeDelta) -> String {
+    format!("{}:{:02}", delta.num_minutes(), delta.num_seconds() % 60)
+}
+
+/// Formats the length of a whole list, e.g. `3 hr 12 min`.
+pub fn format_total(delta: TimeDelta) -> String {
+    let hours = delta.num_hours();
+    let minutes = delta.num_minutes() % 60;
+    if hours > 0 {
+        format!("{hours} hr {minutes} min")
+    } else {
+        format!("{minutes} min {} sec", delta.num_seconds() % 60)
+    }
+}